@@ -0,0 +1,401 @@
+//! Anonymization for the RepoViewer application.
+//!
+//! Some code can't be shared with its internal names in it. With
+//! anonymization on (`N`), every export is passed through a list of configured
+//! terms (company names, hostnames, internal packages...) and each one is
+//! swapped for a stable placeholder like `COMPANY_1`. Email addresses can be
+//! replaced the same way.
+//!
+//! Terms live in `anonymize.toml`, read from `<config dir>/repoviewer/` and
+//! `<repo root>/.repoviewer/` (both apply):
+//!
+//! ```toml
+//! emails = true
+//!
+//! [terms]
+//! company = ["Acme Corp", "Acme"]
+//! host = ["db01.acme.internal"]
+//! package = ["acme_billing"]
+//! ```
+//!
+//! The placeholder each term got is kept in `.repoviewer/anonymize-map.json`
+//! next to the project's config, so a term keeps its placeholder across
+//! exports and sessions. The same mapping runs backwards: `U` (or
+//! `repoviewer deanonymize`) puts the real names back into text pasted back,
+//! such as a model's answer. Matching is exact and case-sensitive, so list
+//! every spelling that should be hidden.
+//!
+//! Both files hold the real names, so neither is ever exported, the same as
+//! if they were listed in `.repoviewerignore`.
+
+use super::App;
+use crate::{app_error::AppError, clipboard::ClipboardManager};
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::LazyLock,
+};
+
+/// Name of the config file, in the user config dir and in `.repoviewer/`
+pub const ANONYMIZE_FILE: &str = "anonymize.toml";
+
+/// Name of the mapping file in the project's `.repoviewer/`
+pub const MAPPING_FILE: &str = "anonymize-map.json";
+
+/// Group that email addresses are placed in
+const EMAIL_GROUP: &str = "email";
+
+/// Anything that looks like an email address
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}")
+        .expect("email pattern should compile")
+});
+
+/// What `anonymize.toml` holds
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AnonymizeConfig {
+    /// Terms to hide, by group. The group names the placeholder (`company` → `COMPANY_1`).
+    terms: BTreeMap<String, Vec<String>>,
+    /// Replace every email address
+    emails: bool,
+}
+
+impl AnonymizeConfig {
+    fn from_file(path: &Path) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path).map_err(|e| AppError::Io(e).with_path_context(path))?;
+        toml::from_str(&text).map_err(|e| AppError::AnonymizeError(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Placeholder prefix for a group, e.g. `internal-host` → `INTERNAL_HOST`
+fn placeholder_prefix(group: &str) -> String {
+    let prefix: String = group
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    if prefix.is_empty() { "TERM".to_string() } else { prefix }
+}
+
+/// Regex matching any of the given strings, longest first so `Acme Corp` wins over `Acme`
+fn alternation<'a>(strings: impl Iterator<Item = &'a String>) -> Option<Regex> {
+    let mut strings: Vec<&String> = strings.filter(|s| !s.is_empty()).collect();
+    if strings.is_empty() {
+        return None;
+    }
+    strings.sort_by_key(|s| std::cmp::Reverse(s.len()));
+
+    let pattern = strings.iter().map(|s| regex::escape(s)).collect::<Vec<_>>().join("|");
+    Regex::new(&pattern).ok()
+}
+
+/// Replaces configured terms with placeholders and back
+#[derive(Debug, Clone)]
+pub struct Anonymizer {
+    /// Each configured term and the placeholder prefix of its group
+    terms: HashMap<String, String>,
+    /// Matches every configured term (and email addresses when enabled)
+    matcher: Option<Regex>,
+    emails: bool,
+    mapping_path: PathBuf,
+    /// Placeholder → original, grows as new terms show up in exports.
+    /// Shared between clones (budget fitting exports a clone of the app), so
+    /// a term gets one placeholder and the file never loses one.
+    mapping: Rc<RefCell<BTreeMap<String, String>>>,
+}
+
+impl Anonymizer {
+    /// Load the config and mapping for a project
+    ///
+    /// Returns `None` when there's neither a config nor a mapping, so there's
+    /// nothing to hide and nothing to restore.
+    pub fn load(project_root: &Path) -> Result<Option<Self>, AppError> {
+        let mut config_paths = Vec::new();
+        if let Some(config_dir) = dirs::config_dir() {
+            config_paths.push(config_dir.join("repoviewer").join(ANONYMIZE_FILE));
+        }
+        config_paths.push(project_root.join(".repoviewer").join(ANONYMIZE_FILE));
+
+        let mut terms = HashMap::new();
+        let mut emails = false;
+        let mut configured = false;
+        for path in config_paths.iter().filter(|p| p.is_file()) {
+            let config = AnonymizeConfig::from_file(path)?;
+            configured = true;
+            emails |= config.emails;
+            for (group, group_terms) in config.terms {
+                let prefix = placeholder_prefix(&group);
+                for term in group_terms {
+                    terms.insert(term, prefix.clone());
+                }
+            }
+        }
+
+        let mapping_path = project_root.join(".repoviewer").join(MAPPING_FILE);
+        let mapping: BTreeMap<String, String> = if mapping_path.is_file() {
+            let text = fs::read_to_string(&mapping_path)
+                .map_err(|e| AppError::Io(e).with_path_context(&mapping_path))?;
+            serde_json::from_str(&text)
+                .map_err(|e| AppError::AnonymizeError(format!("{}: {}", mapping_path.display(), e)))?
+        } else {
+            BTreeMap::new()
+        };
+
+        if !configured && mapping.is_empty() {
+            return Ok(None);
+        }
+
+        let mut pattern_parts: Vec<String> = Vec::new();
+        if emails {
+            pattern_parts.push(EMAIL.as_str().to_string());
+        }
+        if let Some(terms) = alternation(terms.keys()) {
+            pattern_parts.push(terms.as_str().to_string());
+        }
+        // Emails go first so `bob@acme.com` becomes one placeholder, not `bob@COMPANY_1.com`
+        let matcher = (!pattern_parts.is_empty())
+            .then(|| Regex::new(&pattern_parts.join("|")))
+            .transpose()
+            .map_err(|e| AppError::AnonymizeError(e.to_string()))?;
+
+        Ok(Some(Anonymizer {
+            terms,
+            matcher,
+            emails,
+            mapping_path,
+            mapping: Rc::new(RefCell::new(mapping)),
+        }))
+    }
+
+    /// Whether there's anything to hide (as opposed to only a mapping to restore)
+    pub fn has_terms(&self) -> bool {
+        self.matcher.is_some()
+    }
+
+    /// Replace every configured term in `text` with its placeholder
+    ///
+    /// Terms seen for the first time get the next free number in their group,
+    /// and the mapping file is updated so they keep it.
+    pub fn anonymize(&self, text: &str) -> Result<String, AppError> {
+        let Some(matcher) = &self.matcher else {
+            return Ok(text.to_string());
+        };
+
+        let mut mapping = self.mapping.borrow_mut();
+        let mut placeholders: HashMap<String, String> =
+            mapping.iter().map(|(placeholder, original)| (original.clone(), placeholder.clone())).collect();
+        let mut changed = false;
+
+        let output = matcher.replace_all(text, |captures: &regex::Captures| {
+            let original = &captures[0];
+            if let Some(placeholder) = placeholders.get(original) {
+                return placeholder.clone();
+            }
+
+            let prefix = match self.terms.get(original) {
+                Some(prefix) => prefix.clone(),
+                None if self.emails => placeholder_prefix(EMAIL_GROUP),
+                None => return original.to_string(),
+            };
+            let next = mapping
+                .keys()
+                .filter_map(|p| p.strip_prefix(&prefix)?.strip_prefix('_')?.parse::<usize>().ok())
+                .max()
+                .unwrap_or(0)
+                + 1;
+            let placeholder = format!("{}_{}", prefix, next);
+
+            mapping.insert(placeholder.clone(), original.to_string());
+            placeholders.insert(original.to_string(), placeholder.clone());
+            changed = true;
+            placeholder
+        });
+
+        if changed {
+            self.save(&mapping)?;
+        }
+
+        Ok(output.into_owned())
+    }
+
+    /// Put the original names back in place of placeholders
+    pub fn restore(&self, text: &str) -> String {
+        let mapping = self.mapping.borrow();
+        let Some(placeholders) = alternation(mapping.keys()) else {
+            return text.to_string();
+        };
+
+        // Whole placeholders only, so COMPANY_1 doesn't eat the start of COMPANY_12
+        let Ok(matcher) = Regex::new(&format!(r"\b(?:{})\b", placeholders.as_str())) else {
+            return text.to_string();
+        };
+
+        matcher
+            .replace_all(text, |captures: &regex::Captures| {
+                mapping.get(&captures[0]).cloned().unwrap_or_else(|| captures[0].to_string())
+            })
+            .into_owned()
+    }
+
+    /// Write the mapping to `.repoviewer/anonymize-map.json`
+    fn save(&self, mapping: &BTreeMap<String, String>) -> Result<(), AppError> {
+        if let Some(dir) = self.mapping_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AppError::Io(e).with_path_context(dir))?;
+        }
+        let json = serde_json::to_string_pretty(mapping)?;
+        fs::write(&self.mapping_path, json + "\n").map_err(|e| AppError::Io(e).with_path_context(&self.mapping_path))
+    }
+}
+
+impl App {
+    /// Whether a path is one of the project's anonymization files, which are never exported
+    pub(super) fn is_anonymize_file(&self, path: &Path) -> bool {
+        let root = self.git_root.as_ref().unwrap_or(&self.start_dir);
+        let Ok(relative) = path.strip_prefix(root) else {
+            return false;
+        };
+        let dir = Path::new(".repoviewer");
+        relative == dir.join(ANONYMIZE_FILE) || relative == dir.join(MAPPING_FILE)
+    }
+
+    /// Turn anonymization of exports on or off
+    pub fn toggle_anonymize(&mut self) {
+        if let Some(e) = &self.anonymize_error {
+            self.set_error_message(format!(
+                "Exports are NOT anonymized, {} failed to load: {}",
+                ANONYMIZE_FILE,
+                e
+            ));
+            return;
+        }
+
+        if !self.anonymizer.as_ref().is_some_and(|a| a.has_terms()) {
+            self.set_error_message(format!(
+                "No terms to anonymize, add them to .repoviewer/{}",
+                ANONYMIZE_FILE
+            ));
+            return;
+        }
+
+        self.export_options.anonymize = !self.export_options.anonymize;
+        let state = if self.export_options.anonymize { "on" } else { "off" };
+        self.set_success_message(format!("Anonymization of exports {}", state));
+    }
+
+    /// Anonymize export output if that's turned on
+    pub(super) fn anonymize_output(&self, output: &str) -> Result<Option<String>, AppError> {
+        match &self.anonymizer {
+            Some(anonymizer) if self.export_options.anonymize => anonymizer.anonymize(output).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Restore original names in text using the project's mapping
+    pub fn deanonymize(&self, text: &str) -> Result<String, AppError> {
+        match &self.anonymizer {
+            Some(anonymizer) => Ok(anonymizer.restore(text)),
+            None => Err(AppError::AnonymizeError(match &self.anonymize_error {
+                Some(e) => format!("the anonymization config failed to load: {}", e),
+                None => format!("no {} found, nothing has been anonymized in this project", MAPPING_FILE),
+            })),
+        }
+    }
+
+    /// Restore original names in the text on the clipboard, e.g. a pasted answer
+    pub fn deanonymize_clipboard(&mut self) -> Result<(), AppError> {
+        let text = ClipboardManager::get_text()?;
+        let restored = self.deanonymize(&text)?;
+        let changed = restored != text;
+        ClipboardManager::set_text(restored)?;
+
+        if changed {
+            self.set_success_message("Restored original names in the clipboard text".to_string());
+        } else {
+            self.set_error_message("No placeholders found in the clipboard text".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A project with `anonymize.toml` holding the given config
+    fn project(config: &str) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".repoviewer")).unwrap();
+        fs::write(dir.path().join(".repoviewer").join(ANONYMIZE_FILE), config).unwrap();
+        dir
+    }
+
+    #[test]
+    fn anonymizes_and_restores() {
+        let dir = project("emails = true\n[terms]\ncompany = [\"Acme\", \"Acme Corp\"]\nhost = [\"db01.acme.internal\"]\n");
+        let anonymizer = Anonymizer::load(dir.path()).unwrap().unwrap();
+
+        let text = "Acme Corp runs Acme on db01.acme.internal, ask bob@acme.com";
+        let anonymized = anonymizer.anonymize(text).unwrap();
+        assert_eq!(anonymized, "COMPANY_1 runs COMPANY_2 on HOST_1, ask EMAIL_1");
+        assert_eq!(anonymizer.restore(&anonymized), text);
+
+        // Placeholders survive a new session
+        let reloaded = Anonymizer::load(dir.path()).unwrap().unwrap();
+        assert_eq!(reloaded.anonymize("Acme").unwrap(), "COMPANY_2");
+    }
+
+    #[test]
+    fn restore_only_replaces_whole_placeholders() {
+        let dir = project("[terms]\ncompany = [\"Acme\"]\n");
+        let anonymizer = Anonymizer::load(dir.path()).unwrap().unwrap();
+        anonymizer.anonymize("Acme").unwrap();
+        assert_eq!(anonymizer.restore("COMPANY_1 and COMPANY_12"), "Acme and COMPANY_12");
+    }
+
+    #[test]
+    fn clones_share_one_mapping() {
+        let dir = project("[terms]\ncompany = [\"Acme\", \"Globex\"]\n");
+        let anonymizer = Anonymizer::load(dir.path()).unwrap().unwrap();
+        let clone = anonymizer.clone();
+
+        assert_eq!(clone.anonymize("Acme").unwrap(), "COMPANY_1");
+        assert_eq!(anonymizer.anonymize("Globex").unwrap(), "COMPANY_2");
+
+        let reloaded = Anonymizer::load(dir.path()).unwrap().unwrap();
+        assert_eq!(reloaded.restore("COMPANY_1 COMPANY_2"), "Acme Globex");
+    }
+
+    #[test]
+    fn anonymization_files_are_never_exported() {
+        let dir = project("[terms]\ncompany = [\"Acme\"]\n");
+        fs::write(dir.path().join(".repoviewer").join(MAPPING_FILE), "{}\n").unwrap();
+        fs::write(dir.path().join(".repoviewer").join("notes.md"), "notes\n").unwrap();
+
+        let app = App::new(dir.path().to_path_buf()).unwrap();
+        let dot_dir = dir.path().join(".repoviewer");
+        assert!(app.is_export_ignored(&dot_dir.join(ANONYMIZE_FILE), false));
+        assert!(app.is_export_ignored(&dot_dir.join(MAPPING_FILE), false));
+        assert!(!app.is_export_ignored(&dot_dir.join("notes.md"), false));
+    }
+
+    #[test]
+    fn broken_config_is_reported_and_never_silently_skipped() {
+        let dir = project("[terms]\ncompany = \"Acme\"\n");
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+
+        let message = app.message.clone().expect("the broken config should be reported");
+        assert!(message.text.starts_with("Failed to load anonymize.toml: "), "{}", message.text);
+        assert!(!message.text.contains("template"), "{}", message.text);
+
+        app.toggle_anonymize();
+        assert!(!app.export_options.anonymize);
+        let message = app.message.expect("turning it on should say why it can't");
+        assert!(message.text.contains("NOT anonymized"), "{}", message.text);
+    }
+}
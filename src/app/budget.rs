@@ -0,0 +1,477 @@
+//! Token budget fitting for the RepoViewer application.
+//!
+//! The size warnings only tell you a collection is too big. With a token
+//! budget set, exports are fitted to it instead: pinned files are always kept
+//! whole, the rest are kept in collection order while they fit, the first ones
+//! that don't fit are cut down to their outline if that fits in the room left,
+//! or else truncated into it, and anything after that is dropped. Every
+//! decision ends up in a `FitReport` so it's clear what the model did (and
+//! didn't) get to see.
+//!
+//! Fitting never touches `collected_files` itself, it produces a fitted copy
+//! of the collection that only lives for the export.
+
+use super::{App, CollectedFile, ContentMode, Exporter};
+use crate::{app_error::AppError, tokens::format_count};
+
+/// Budgets that `b` cycles through (in tokens)
+const BUDGET_PRESETS: [usize; 5] = [8_000, 32_000, 100_000, 200_000, 1_000_000];
+
+/// Don't bother truncating a file into less room than this, drop it instead
+const MIN_TRUNCATED_TOKENS: usize = 256;
+
+/// How many times to re-fit when the real export still overshoots the budget
+const MAX_FIT_PASSES: usize = 3;
+
+/// What happened to a file when fitting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitOutcome {
+    /// Pinned by the user, always kept whole
+    Pinned,
+    /// Fit in the budget as-is
+    Kept,
+    /// Only its outline fit, `kept_lines` signatures and declarations
+    Outlined { kept_lines: usize, total_lines: usize },
+    /// Only the first `kept_lines` lines made it in
+    Truncated { kept_lines: usize, total_lines: usize },
+    /// Left out of the export
+    Dropped,
+}
+
+impl FitOutcome {
+    /// Short label for the report
+    pub fn label(&self) -> &'static str {
+        match self {
+            FitOutcome::Pinned => "pinned",
+            FitOutcome::Kept => "kept",
+            FitOutcome::Outlined { .. } => "outlined",
+            FitOutcome::Truncated { .. } => "truncated",
+            FitOutcome::Dropped => "dropped",
+        }
+    }
+}
+
+/// One line of the fit report
+#[derive(Debug, Clone)]
+pub struct FitEntry {
+    pub relative_path: String,
+    pub outcome: FitOutcome,
+    /// Tokens in the file as collected
+    pub original_tokens: usize,
+    /// Tokens of the file that made it into the export
+    pub kept_tokens: usize,
+    /// Why the file ended up this way
+    pub reason: String,
+}
+
+/// Everything fitting decided, in collection order
+#[derive(Debug, Clone)]
+pub struct FitReport {
+    pub budget: usize,
+    /// Tokens in the fitted export, wrapper text included
+    pub export_tokens: usize,
+    pub entries: Vec<FitEntry>,
+}
+
+impl FitReport {
+    /// Number of files with the given outcome label
+    pub fn count(&self, label: &str) -> usize {
+        self.entries.iter().filter(|e| e.outcome.label() == label).count()
+    }
+
+    /// Whether the export still doesn't fit
+    ///
+    /// Pinned files are kept whole even when they alone are over the budget,
+    /// and the wrapper cost per file is an estimate that re-fitting may not
+    /// have caught up with.
+    pub fn over_budget(&self) -> bool {
+        self.export_tokens > self.budget
+    }
+
+    /// Whether fitting had to cut anything
+    pub fn is_lossless(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|e| matches!(e.outcome, FitOutcome::Pinned | FitOutcome::Kept))
+    }
+
+    /// One-line summary for status messages, e.g. "fitted to 100k: 2 outlined, 1 truncated, 3 dropped"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        for label in ["outlined", "truncated", "dropped"] {
+            let count = self.count(label);
+            if count > 0 {
+                parts.push(format!("{} {}", count, label));
+            }
+        }
+
+        let mut summary = if !parts.is_empty() {
+            format!("fitted to {}: {}", format_count(self.budget), parts.join(", "))
+        } else if self.over_budget() {
+            format!("over the {} budget", format_count(self.budget))
+        } else {
+            format!("fits in {} budget", format_count(self.budget))
+        };
+
+        if self.over_budget() {
+            let over = format_count(self.export_tokens - self.budget);
+            let pinned = self.count("pinned");
+            let only_pinned_left = self.entries
+                .iter()
+                .all(|e| matches!(e.outcome, FitOutcome::Pinned | FitOutcome::Dropped));
+
+            if pinned > 0 && only_pinned_left {
+                summary.push_str(&format!(" ({} over, the {} pinned file(s) alone don't fit)", over, pinned));
+            } else {
+                summary.push_str(&format!(" ({} over)", over));
+            }
+        }
+
+        summary
+    }
+}
+
+impl App {
+    /// Move to the next budget preset, turning the budget off after the largest
+    ///
+    /// A custom budget from `--budget` is left for the next bigger preset.
+    pub fn cycle_token_budget(&mut self) {
+        self.token_budget = match self.token_budget {
+            None => Some(BUDGET_PRESETS[0]),
+            Some(current) => BUDGET_PRESETS.iter().copied().find(|&b| b > current),
+        };
+
+        let message = match self.token_budget {
+            Some(budget) => format!("Token budget: {} - exports will be fitted to it", format_count(budget)),
+            None => "Token budget off - exports include every file".to_string(),
+        };
+        self.set_success_message(message);
+    }
+
+    /// Pin or unpin the selected file so fitting always keeps it whole
+    pub fn toggle_pin_current_file(&mut self) {
+        let Some(path) = self.current_selection().map(|item| item.path.clone()) else {
+            self.set_error_message("No file selected".to_string());
+            return;
+        };
+
+        let display_name = self.get_display_path(&path);
+        let Some(file) = self.collected_files.iter_mut().find(|f| f.path == path) else {
+            self.set_error_message(format!("{} is not in the collection - add it with 'a' first", display_name));
+            return;
+        };
+
+        file.pinned = !file.pinned;
+        let message = if file.pinned {
+            format!("Pinned {} - it will always be exported whole", display_name)
+        } else {
+            format!("Unpinned {}", display_name)
+        };
+        self.set_success_message(message);
+    }
+
+    /// Fit the collection into the token budget for the given exporter
+    ///
+    /// Returns `None` when no budget is set or the exporter can't be fitted
+    /// (see `Exporter::fits_budget`). Otherwise returns a copy of the app
+    /// holding the fitted collection (ready to export) and the report.
+    ///
+    /// The budget covers the whole export, so the tokens used by headers, fences
+    /// and the project tree are measured from the exporter's own output first.
+    pub fn fit_collection(&self, exporter: &dyn Exporter) -> Result<Option<(App, FitReport)>, AppError> {
+        let Some(budget) = self.token_budget.filter(|_| exporter.fits_budget()) else {
+            return Ok(None);
+        };
+
+        let mut fitted = self.clone();
+
+        // Tokens the export costs with no files at all
+        fitted.collected_files.clear();
+        let base_tokens = self.tokenizer.count(&exporter.export(&fitted)?);
+
+        // Average wrapper cost per file (headers, fences, attributes...)
+        let content_tokens = self.get_collection_tokens();
+        let full_tokens = self.tokenizer.count(&exporter.export(self)?);
+        let per_file_tokens = full_tokens
+            .saturating_sub(base_tokens + content_tokens)
+            .checked_div(self.collected_files.len())
+            .unwrap_or(0);
+
+        let mut file_budget = budget.saturating_sub(base_tokens);
+        let mut report = FitReport { budget, export_tokens: 0, entries: Vec::new() };
+
+        // The overhead is an average, so check the real export and tighten if needed
+        for _ in 0..MAX_FIT_PASSES {
+            let (files, entries) = self.fit_files(file_budget, per_file_tokens);
+            fitted.collected_files = files;
+            report.entries = entries;
+            report.export_tokens = self.tokenizer.count(&exporter.export(&fitted)?);
+
+            if report.export_tokens <= budget || report.is_lossless() {
+                break;
+            }
+            file_budget = file_budget.saturating_sub(report.export_tokens - budget);
+        }
+
+        Ok(Some((fitted, report)))
+    }
+
+    /// Decide what happens to each file given the tokens available for files
+    fn fit_files(&self, file_budget: usize, per_file_tokens: usize) -> (Vec<CollectedFile>, Vec<FitEntry>) {
+        // Pinned files come off the top no matter what
+        let pinned_cost: usize = self.collected_files
+            .iter()
+            .filter(|f| f.pinned)
+            .map(|f| f.token_count + per_file_tokens)
+            .sum();
+        let mut remaining = file_budget.saturating_sub(pinned_cost);
+
+        // First pass: keep every unpinned file that fits whole, in collection order
+        let mut outcomes: Vec<Option<FitOutcome>> = self.collected_files
+            .iter()
+            .map(|file| {
+                let cost = file.token_count + per_file_tokens;
+                if file.pinned {
+                    Some(FitOutcome::Pinned)
+                } else if cost <= remaining {
+                    remaining -= cost;
+                    Some(FitOutcome::Kept)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        // Second pass: outline or truncate what didn't fit into the room that's left, drop the rest
+        let mut files = Vec::new();
+        let mut entries = Vec::new();
+
+        for (file, outcome) in self.collected_files.iter().zip(outcomes.iter_mut()) {
+            let available = remaining.saturating_sub(per_file_tokens);
+            let mut kept_file = None;
+
+            let reason = match outcome {
+                Some(FitOutcome::Pinned) => {
+                    kept_file = Some(file.clone());
+                    "pinned, always exported whole".to_string()
+                }
+                Some(FitOutcome::Kept) => {
+                    kept_file = Some(file.clone());
+                    "fits in the budget".to_string()
+                }
+                _ => {
+                    let total_lines = file.content.lines().count();
+                    if let Some(outlined) = self.outline_to_tokens(file, available) {
+                        let kept_lines = outlined.content.lines().count();
+                        remaining = remaining.saturating_sub(outlined.token_count + per_file_tokens);
+                        *outcome = Some(FitOutcome::Outlined { kept_lines, total_lines });
+                        kept_file = Some(outlined);
+                        format!("outline of all {} lines, the whole file didn't fit", total_lines)
+                    } else if let Some((truncated, kept_lines)) = (available >= MIN_TRUNCATED_TOKENS)
+                        .then(|| self.truncate_to_tokens(file, available))
+                        .flatten()
+                    {
+                        remaining = remaining.saturating_sub(truncated.token_count + per_file_tokens);
+                        *outcome = Some(FitOutcome::Truncated { kept_lines, total_lines });
+                        kept_file = Some(truncated);
+                        format!("kept lines 1-{} of {}, the budget ran out", kept_lines, total_lines)
+                    } else {
+                        *outcome = Some(FitOutcome::Dropped);
+                        format!(
+                            "needs {} but only {} left",
+                            self.format_tokens(file.token_count),
+                            self.format_tokens(available)
+                        )
+                    }
+                }
+            };
+
+            entries.push(FitEntry {
+                relative_path: file.relative_path.clone(),
+                outcome: outcome.unwrap_or(FitOutcome::Dropped),
+                original_tokens: file.token_count,
+                kept_tokens: kept_file.as_ref().map_or(0, |f| f.token_count),
+                reason,
+            });
+            files.extend(kept_file);
+        }
+
+        (files, entries)
+    }
+
+    /// The file's outline, if its language has one and it fits in `max_tokens`
+    ///
+    /// Signatures of the whole file tell a model more than the first stretch of
+    /// it, so this is tried before truncating. Files already collected as an
+    /// outline or as ranges are left to truncation.
+    fn outline_to_tokens(&self, file: &CollectedFile, max_tokens: usize) -> Option<CollectedFile> {
+        if file.mode != ContentMode::Full {
+            return None;
+        }
+
+        let mut outlined = file.clone();
+        outlined.make_outline(&self.tokenizer).ok()?;
+        (outlined.token_count <= max_tokens && outlined.token_count < file.token_count).then_some(outlined)
+    }
+
+    /// Cut a file down to its first lines so it fits in `max_tokens`
+    ///
+    /// A marker line saying what was cut is appended (and paid for), so the model
+    /// knows the file continues. Returns the truncated copy and the number of
+    /// lines kept, or `None` if not even one line fits.
+    fn truncate_to_tokens(&self, file: &CollectedFile, max_tokens: usize) -> Option<(CollectedFile, usize)> {
+        let total_lines = file.content.lines().count();
+        let marker = |kept: usize| {
+            format!("… truncated to fit the token budget: lines {}-{} omitted …\n", kept + 1, total_lines)
+        };
+
+        let available = max_tokens.saturating_sub(self.tokenizer.count(&marker(total_lines)));
+        let mut used = 0;
+        let mut end = 0;
+        let mut kept_lines = 0;
+
+        for line in file.content.split_inclusive('\n') {
+            let cost = self.tokenizer.count(line);
+            if used + cost > available {
+                break;
+            }
+            used += cost;
+            end += line.len();
+            kept_lines += 1;
+        }
+
+        if kept_lines == 0 {
+            return None;
+        }
+
+        let mut content = file.content[..end].to_string();
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&marker(kept_lines));
+
+        let mut truncated = file.clone();
+        truncated.token_count = self.tokenizer.count(&content);
+        truncated.content = content;
+
+        Some((truncated, kept_lines))
+    }
+
+    /// Work out the fit report for the active format and show it
+    pub fn open_fit_report(&mut self) -> Result<(), AppError> {
+        if self.token_budget.is_none() {
+            self.set_error_message("No token budget set - press 'b' to choose one".to_string());
+            return Ok(());
+        }
+
+        if self.collected_files.is_empty() {
+            self.set_error_message("Collection is empty".to_string());
+            return Ok(());
+        }
+
+        if !self.exporters.active().fits_budget() {
+            self.set_error_message(format!(
+                "The token budget isn't applied to {} exports",
+                self.exporters.active().name()
+            ));
+            return Ok(());
+        }
+
+        self.fit_report = self.fit_collection(self.exporters.active())?.map(|(_, report)| report);
+        self.show_fit_report = self.fit_report.is_some();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Rust source with `count` functions of `body` lines each
+    fn functions(count: usize, body: usize) -> String {
+        (0..count)
+            .map(|i| {
+                let body: String = (0..body).map(|j| format!("    let value_{} = compute({}, {});\n", j, i, j)).collect();
+                format!("pub fn function_{}(input: usize) -> usize {{\n{}    input\n}}\n\n", i, body)
+            })
+            .collect()
+    }
+
+    fn fit(files: &[(&str, String)], budget: usize) -> (App, FitReport) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        let names: Vec<String> = files.iter().map(|(name, _)| name.to_string()).collect();
+        app.collect_inputs(&names).unwrap();
+        app.token_budget = Some(budget);
+
+        app.fit_collection(app.exporters.active()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn outlines_files_before_truncating_them() {
+        let (fitted, report) = fit(&[("a.rs", functions(30, 20))], 1_000);
+
+        assert!(matches!(report.entries[0].outcome, FitOutcome::Outlined { kept_lines, .. } if kept_lines < 100));
+        assert_eq!(fitted.collected_files[0].mode, ContentMode::Outline);
+        assert!(fitted.collected_files[0].content.contains("pub fn function_29(input: usize) -> usize { … }"));
+        assert!(report.export_tokens <= 1_000);
+        assert_eq!(report.summary(), "fitted to 1k: 1 outlined");
+    }
+
+    #[test]
+    fn truncates_when_the_outline_does_not_fit_either() {
+        let (_, report) = fit(&[("a.rs", functions(200, 2))], 1_000);
+        assert!(matches!(report.entries[0].outcome, FitOutcome::Truncated { .. }));
+    }
+
+    #[test]
+    fn truncates_files_without_an_outline() {
+        let config: String = (0..400).map(|i| format!("key_{}: value_{}\n", i, i)).collect();
+        let (_, report) = fit(&[("a.yaml", config)], 1_000);
+        assert!(matches!(report.entries[0].outcome, FitOutcome::Truncated { .. }));
+    }
+
+    fn entry(outcome: FitOutcome) -> FitEntry {
+        FitEntry {
+            relative_path: String::new(),
+            outcome,
+            original_tokens: 0,
+            kept_tokens: 0,
+            reason: String::new(),
+        }
+    }
+
+    #[test]
+    fn summary_says_why_the_export_is_over_budget() {
+        let pinned = FitReport {
+            budget: 1_000,
+            export_tokens: 1_500,
+            entries: vec![entry(FitOutcome::Pinned), entry(FitOutcome::Dropped)],
+        };
+        assert_eq!(pinned.summary(), "fitted to 1k: 1 dropped (500 over, the 1 pinned file(s) alone don't fit)");
+
+        let estimate_off = FitReport {
+            budget: 1_000,
+            export_tokens: 1_200,
+            entries: vec![entry(FitOutcome::Kept), entry(FitOutcome::Kept)],
+        };
+        assert_eq!(estimate_off.summary(), "over the 1k budget (200 over)");
+    }
+
+    #[test]
+    fn diff_exports_are_not_fitted() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), functions(30, 20)).unwrap();
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        app.collect_inputs(&["a.rs".to_string()]).unwrap();
+        app.token_budget = Some(1_000);
+        app.exporters.select("diff").unwrap();
+
+        assert!(app.fit_collection(app.exporters.active()).unwrap().is_none());
+    }
+}
@@ -11,11 +11,14 @@
 //! 
 
 use super::{App, FileItem};
-use super::state::{FileStatus, RefreshResult, RefreshSummary};
+use super::ranges::{LineRange, ranges_label};
+use super::state::{CollectionHealth, FileStatus, RefreshResult, RefreshSummary};
 use crate::{
     app_error::AppError,
+    tokens::Tokenizer,
     utils::{get_file_type, read_file_safely, MEGABYTE},
 };
+use serde::{Serialize, Serializer};
 use std::{
     fs,
     path::PathBuf,
@@ -23,18 +26,111 @@ use std::{
 };
 
 /// Represents a file that has been collected for export
-#[derive(Debug, Clone)]
+/// 
+/// This derives `Serialize` for the JSON export. Times are written as unix
+/// seconds and the hash as a hex string so scripts don't have to deal with
+/// serde's `SystemTime` layout or u64s that overflow a JavaScript number.
+#[derive(Debug, Clone, Serialize)]
 pub struct CollectedFile {
     pub path: PathBuf,              // Absolute path to the file
     pub relative_path: String,      // relative path for display
     pub content: String,            // file content at collection time
     pub language: String,           // language for syntax highlighting
+    #[serde(serialize_with = "serialize_unix_time")]
     pub collected_at: SystemTime,   // When we collected this snapshot
+    #[serde(serialize_with = "serialize_hash")]
     pub content_hash: u64,          // fingerprint for change detection
+    #[serde(serialize_with = "serialize_unix_time")]
     pub last_modified: SystemTime,  // Files modification time when collected
+    pub token_count: usize,         // tokens in the content, using the app's tokenizer
+    pub pinned: bool,               // always exported whole when fitting to a token budget
+    pub mode: ContentMode,          // whole file, outline or line ranges
+    /// The lines collected when the mode is `Ranges`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ranges: Vec<LineRange>,
+    /// Line in the file on disk that each content line came from, `None` when
+    /// the content is the whole file
+    #[serde(skip)]
+    pub source_lines: Option<Vec<usize>>,
+}
+
+/// How much of a file a collected entry holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentMode {
+    #[default]
+    Full,
+    /// Signatures and declarations with bodies elided (see `outline.rs`)
+    Outline,
+    /// Some ranges of lines, the rest elided (see `ranges.rs`)
+    Ranges,
+}
+
+impl ContentMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentMode::Full => "full",
+            ContentMode::Outline => "outline",
+            ContentMode::Ranges => "ranges",
+        }
+    }
+}
+
+impl CollectedFile {
+    /// Carry the user's choices about a file over to a fresh snapshot of it
+    /// 
+    /// Things like pinning and outlining belong to the collection entry rather
+    /// than the content, so re-reading a file shouldn't reset them.
+    fn inherit_settings(&mut self, previous: &CollectedFile, tokenizer: &Tokenizer) {
+        self.pinned = previous.pinned;
+        match previous.mode {
+            ContentMode::Full => {}
+            // The language didn't change, so this can't fail
+            ContentMode::Outline => {
+                let _ = self.make_outline(tokenizer);
+            }
+            ContentMode::Ranges => self.reanchor_ranges(previous, tokenizer),
+        }
+    }
+
+    /// What's collected of the file when it isn't all of it, e.g. ` (outline)`
+    /// or ` (lines 10-20, 40-60)`, empty for whole files
+    pub fn mode_label(&self) -> String {
+        match self.mode {
+            ContentMode::Full => String::new(),
+            ContentMode::Outline => " (outline)".to_string(),
+            ContentMode::Ranges => format!(" (lines {})", ranges_label(&self.ranges)),
+        }
+    }
+}
+
+/// Serialize a `SystemTime` as whole seconds since the unix epoch
+fn serialize_unix_time<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    serializer.serialize_u64(secs)
+}
+
+/// Serialize the content hash as a fixed-width hex string
+fn serialize_hash<S: Serializer>(hash: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:016x}", hash))
 }
 
 // Implementation of file collection operations
+/// What a bulk add did, for its status message (see `set_add_message`)
+#[derive(Debug, Default)]
+pub(super) struct AddOutcome {
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    /// Skipped files worth naming, as `path: reason`
+    pub skipped_files: Vec<String>,
+    /// Files that couldn't be read, as `path: error`
+    pub errors: Vec<String>,
+}
+
 impl App {
     /// Add the currently selected file to the collection
     /// 
@@ -61,35 +157,12 @@ impl App {
 
         // Try to create a CollectedFile from the selected item
         // will call `read_file_safely()` and act as a file filter as well
-        let new_collected_file = match self.create_collected_file(current_item) {
+        let mut new_collected_file = match self.create_collected_file(current_item) {
             Ok(file) => file,
             Err(e) => {
                 // get the display path to help make the error cleaner
                 let display_name = self.get_display_path(&current_item.path);
-
-                // custom errors for the main possibilities then generic format for anything else
-                let error_message = match e {
-                    AppError::FileTooLarge { size, max } => {
-                        format!("{}: Too large ({} max: {})", 
-                            display_name,
-                            self.format_size(size as usize),
-                            self.format_size(max)
-                        )
-                    },
-                    AppError::BinaryFile => {
-                        format!("{}: Binary files cannot be collected", display_name)
-                    },
-                    AppError::UnrecognizedFileType { extension } => {
-                        match extension {
-                            Some(ext) => format!("{}: Unsupported type (.{})", display_name, ext),
-                            None => format!("{}: No file extension", display_name)
-                        }
-                    },
-                    AppError::EncodingError => {
-                        format!("{}: Too many invalid UTF-8 characters", display_name)
-                    },
-                    _ => format!("{}: {}", display_name, e),
-                };
+                let error_message = format!("{}: {}", display_name, self.collect_error_reason(&e));
                 self.set_error_message(error_message);
                 return Ok(());
             }
@@ -97,6 +170,7 @@ impl App {
 
         // Calculate size information for display
         let size_kb = new_collected_file.content.len() / 1024;
+        let tokens_str = self.format_tokens(new_collected_file.token_count);
 
         let display_name = self.get_display_path(&current_item.path);
 
@@ -113,12 +187,13 @@ impl App {
         match existing_index {
             Some(index) => {
                 // Replace the old version with the new one
+                new_collected_file.inherit_settings(&self.collected_files[index], &self.tokenizer);
                 self.collected_files[index] = new_collected_file;
                 
                 // Build success message with size warning if we detect too much
                 let mut message = format!(
-                    "Updated {} ({} KB) - Total: {} files",
-                    display_name, size_kb, old_count
+                    "Updated {} ({} KB, {}) - Total: {} files",
+                    display_name, size_kb, tokens_str, old_count
                 );
                 
                 // Add warning if collection is getting large
@@ -134,8 +209,8 @@ impl App {
                 
                 // Build success message with size warning if applicable
                 let mut message = format!(
-                    "Added {} ({} KB) - Total: {} files",
-                    display_name, size_kb, old_count + 1
+                    "Added {} ({} KB, {}) - Total: {} files",
+                    display_name, size_kb, tokens_str, old_count + 1
                 );
                 
                 // Add warning if collection is getting large
@@ -154,7 +229,7 @@ impl App {
     /// 
     /// This bulk operation is perfect for collecting all source files in a
     /// module or package. It intelligently:
-    /// - Skips directories and non text files
+    /// - Skips directories, non text files and anything in `.repoviewerignore`
     /// - Updates files that are already in the collection
     /// - Provides a summary of what was added/updated/skipped
     /// - Warns when the collection size is getting large
@@ -162,13 +237,12 @@ impl App {
         let mut added = 0;
         let mut updated = 0;
         let mut skipped = 0;
-        let mut errors = 0;
         
         // Keep track of problematic files for detailed reporting
         let mut error_files: Vec<String> = Vec::new();
 
-        // Store initial size to detect if we're crossing size thresholds
-        let initial_size = self.get_collection_size();
+        // Store initial health to detect if we're crossing size thresholds
+        let initial_health = self.collection_health();
 
         // Process each item in the current directory
         for item in &self.items {
@@ -182,15 +256,15 @@ impl App {
             if let Some(index) = self.collected_files.iter().position(|f| f.path == item.path) {
                 // File exists - try to update it with fresh content
                 match self.create_collected_file(item) {
-                    Ok(new_file) => {
+                    Ok(mut new_file) => {
+                        new_file.inherit_settings(&self.collected_files[index], &self.tokenizer);
                         self.collected_files[index] = new_file;
                         updated += 1;
                     }
-                    Err(AppError::NotAFile) => {
+                    Err(AppError::NotAFile | AppError::ExportIgnored) => {
                         skipped += 1;
                     }
                     Err(e) => {
-                        errors += 1;
                         // Use display path for cleaner error tracking
                         let display_name = self.get_display_path(&item.path);
                         error_files.push(format!("{}: {}", display_name, e));
@@ -203,11 +277,10 @@ impl App {
                         self.collected_files.push(new_file);
                         added += 1;
                     }
-                    Err(AppError::NotAFile) => {
+                    Err(AppError::NotAFile | AppError::ExportIgnored) => {
                         skipped += 1;
                     }
                     Err(e) => {
-                        errors += 1;
                         // Use display path for cleaner error tracking
                         let display_name = self.get_display_path(&item.path);
                         error_files.push(format!("{}: {}", display_name, e));
@@ -217,46 +290,81 @@ impl App {
         }
 
         // Prepare comprehensive feedback for the user
-        let total = self.collected_files.len();
-        let current_size = self.get_collection_size();
-        let size_str = self.format_size(current_size);
-        
-        // Build the status message with all the statistics
+        let outcome = AddOutcome { added, updated, skipped, errors: error_files, ..AddOutcome::default() };
+        self.set_add_message("", outcome, initial_health);
+
+        Ok(())
+    }
+
+    /// Report what a bulk add did, e.g. "Added 12 files from src, updated 2, skipped 1 - Total: ..."
+    ///
+    /// `source` says where the files came from (`from src`, `matching *.rs`),
+    /// empty for the current directory. The first couple of skipped files and
+    /// errors are named, and crossing a size threshold gets a warning.
+    pub(super) fn set_add_message(&mut self, source: &str, outcome: AddOutcome, initial_health: CollectionHealth) {
+        let source = if source.is_empty() { String::new() } else { format!(" {}", source) };
         let mut message = format!(
-            "Added {} files, updated {}, skipped {} (errors: {}) - Total: {} files ({})",
-            added, updated, skipped, errors, total, size_str
+            "Added {} files{}, updated {}, skipped {}",
+            outcome.added, source, outcome.updated, outcome.skipped
         );
-        
-        // If there were errors, add details about the first few
-        if !error_files.is_empty() {
-            let error_preview = error_files.iter()
-                .take(2)  // Show first 2 errors
-                .map(|e| e.as_str())
-                .collect::<Vec<_>>()
-                .join(", ");
-            
-            message.push_str(&format!("\nErrors: {}", error_preview));
-            if error_files.len() > 2 {
-                message.push_str(&format!(" and {} more", error_files.len() - 2));
+        if !outcome.errors.is_empty() {
+            message.push_str(&format!(" (errors: {})", outcome.errors.len()));
+        }
+        message.push_str(&format!(
+            " - Total: {} files ({}, {})",
+            self.collected_files.len(),
+            self.format_size(self.get_collection_size()),
+            self.format_tokens(self.get_collection_tokens())
+        ));
+
+        // Name the first few, the rest are only counted
+        for (label, details) in [("Skipped", &outcome.skipped_files), ("Errors", &outcome.errors)] {
+            if details.is_empty() {
+                continue;
+            }
+            message.push_str(&format!("\n{}: {}", label, details.iter().take(2).cloned().collect::<Vec<_>>().join(", ")));
+            if details.len() > 2 {
+                message.push_str(&format!(" and {} more", details.len() - 2));
             }
         }
-        
+
         // Add size warning if we've crossed a threshold
         if let Some(warning) = self.get_size_warning() {
             message.push_str(&format!("\n{}", warning));
-            
+
             // Special tip if we just crossed into warning territory
-            const WARNING_THRESHOLD: usize = 25 * MEGABYTE;
-            if initial_size < WARNING_THRESHOLD && current_size >= WARNING_THRESHOLD {
+            if initial_health == CollectionHealth::Healthy {
                 message.push_str("\nTip: Use 'd' to remove individual files or 'D' to clear all");
             }
         }
-        
-        self.set_success_message(message);
 
-        Ok(())
+        self.set_success_message(message);
     }
     
+    /// Add fresh snapshots to the collection, replacing the ones of files already in it
+    ///
+    /// Replaced files keep their settings. Returns how many were added and how many updated.
+    pub(super) fn add_or_update_files(&mut self, files: Vec<CollectedFile>) -> (usize, usize) {
+        let mut added = 0;
+        let mut updated = 0;
+
+        for mut file in files {
+            match self.collected_files.iter().position(|f| f.path == file.path) {
+                Some(index) => {
+                    file.inherit_settings(&self.collected_files[index], &self.tokenizer);
+                    self.collected_files[index] = file;
+                    updated += 1;
+                }
+                None => {
+                    self.collected_files.push(file);
+                    added += 1;
+                }
+            }
+        }
+
+        (added, updated)
+    }
+
     /// Remove the currently selected file from the collection
     /// 
     /// This allows users to curate their collection by removing files
@@ -286,8 +394,8 @@ impl App {
         
         if let Some(index) = index {
             // Remove the file from the collection
-            // `swap_remove()`` is O(1) but changes order but we dont care about that
-            let removed_file = self.collected_files.swap_remove(index);
+            // Keep the order intact, it's the priority order when fitting to a token budget
+            let removed_file = self.collected_files.remove(index);
             let size_kb = removed_file.content.len() / 1024;
             self.set_success_message(format!(
                 "Removed {} ({} KB) - Total: {} files",
@@ -390,9 +498,10 @@ impl App {
 
                 // Try to re-read the file with all our safety checks
                 match self.create_collected_file(&temp_item) {
-                    Ok(new_file) => {
+                    Ok(mut new_file) => {
                         // Compare content hashes before declaring it modified
                         if new_file.content_hash != self.collected_files[index].content_hash {
+                            new_file.inherit_settings(&self.collected_files[index], &self.tokenizer);
                             self.collected_files[index] = new_file;
                             Ok(RefreshResult::Updated)
                         } else {
@@ -454,6 +563,23 @@ impl App {
         summary
     }
 
+    /// Short reason a file couldn't be collected, for messages and skip reports
+    pub(super) fn collect_error_reason(&self, error: &AppError) -> String {
+        // custom errors for the main possibilities then generic format for anything else
+        match error {
+            AppError::FileTooLarge { size, max } => {
+                format!("Too large ({} max: {})", self.format_size(*size as usize), self.format_size(*max))
+            },
+            AppError::BinaryFile => "Binary files cannot be collected".to_string(),
+            AppError::UnrecognizedFileType { extension } => match extension {
+                Some(ext) => format!("Unsupported type (.{})", ext),
+                None => "No file extension".to_string(),
+            },
+            AppError::EncodingError => "Too many invalid UTF-8 characters".to_string(),
+            _ => error.to_string(),
+        }
+    }
+
     /// Create a CollectedFile from a FileItem
     /// 
     /// This is the core method that transforms a file reference into a
@@ -468,6 +594,11 @@ impl App {
         if item.is_dir {
             return Err(AppError::NotAFile);
         }
+
+        // Listed in .repoviewerignore, never let it near an export
+        if self.is_export_ignored(&item.path, false) {
+            return Err(AppError::ExportIgnored);
+        }
         
         // Get file metadata before reading content
         // This helps us validate the file and capture its state
@@ -493,6 +624,9 @@ impl App {
         let language = get_file_type(&item.path)
             .unwrap_or("plaintext")
             .to_string();
+
+        // Count tokens once here, a BPE tokenizer is far too slow to run every frame
+        let token_count = self.tokenizer.count(&content);
         
         Ok(CollectedFile {
             path: item.path.clone(),
@@ -502,6 +636,11 @@ impl App {
             collected_at: SystemTime::now(),
             content_hash,
             last_modified,
+            token_count,
+            pinned: false,
+            mode: ContentMode::Full,
+            ranges: Vec::new(),
+            source_lines: None,
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,455 @@
+//! Git diff export for the RepoViewer application.
+//!
+//! For code review the model needs to see what changed, not just the final
+//! content. The diff export emits a unified diff for every collected file,
+//! taken from the working tree against HEAD, the index or any ref git can
+//! resolve. Files git doesn't track yet are shown as new files, unless git
+//! finds they were renamed from a file that's gone since the base. Ignored
+//! files have nothing to be compared with and are labelled as such.
+
+use super::{App, ContentMode};
+use super::export::fence_for;
+use super::split::pack_parts;
+use super::ranges::ranges_label;
+use super::secrets::redact_patch;
+use crate::app_error::AppError;
+use git2::{Delta, Diff, DiffFindOptions, DiffFormat, DiffOptions, ErrorCode, Repository};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// What the working tree is compared against
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DiffBase {
+    /// The last commit, so staged and unstaged changes both show up
+    #[default]
+    Head,
+    /// The index, so only unstaged changes show up
+    Index,
+    /// Any revision git can resolve (a branch, tag, `HEAD~3`, a commit hash...)
+    Ref(String),
+}
+
+impl DiffBase {
+    /// Parse the value given to `--diff-base`
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "HEAD" | "head" => DiffBase::Head,
+            "index" | "staged" => DiffBase::Index,
+            other => DiffBase::Ref(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for DiffBase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffBase::Head => write!(f, "HEAD"),
+            DiffBase::Index => write!(f, "the index"),
+            DiffBase::Ref(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// The diff of one file, as found in the repository
+struct FileDiff {
+    status: Delta,
+    /// Where the file was at the base, when it was renamed or copied
+    old_path: Option<PathBuf>,
+    patch: String,
+}
+
+impl FileDiff {
+    /// Human-readable status, e.g. `modified` or `renamed from src/old.rs`
+    fn label(&self) -> String {
+        match (&self.old_path, self.status) {
+            (Some(old_path), Delta::Renamed | Delta::Copied) => {
+                format!("{} from {}", status_label(self.status), old_path.to_string_lossy().replace('\\', "/"))
+            }
+            _ => status_label(self.status).to_string(),
+        }
+    }
+}
+
+/// The collection as git sees it
+struct RepoDiffs {
+    /// Each collected file's path relative to the repository, `None` outside it
+    repo_paths: Vec<Option<PathBuf>>,
+    /// Diffs of the files that changed, by repo path
+    diffs: HashMap<PathBuf, FileDiff>,
+    /// Files git ignores and doesn't track, which never show up in a diff
+    ignored: HashSet<PathBuf>,
+}
+
+/// A fenced code block, with a blank line before it
+fn code_block(language: &str, content: &str) -> String {
+    let fence = fence_for(content);
+    let newline = if content.ends_with('\n') { "" } else { "\n" };
+    format!("\n{}{}\n{}{}{}\n", fence, language, content, newline, fence)
+}
+
+/// Cut a patch into its hunks, the file header staying with the first one
+fn split_hunks(patch: &str) -> Vec<String> {
+    let mut hunks: Vec<String> = Vec::new();
+    for line in patch.split_inclusive('\n') {
+        match hunks.last_mut() {
+            Some(hunk) if !line.starts_with("@@") || !hunk.contains("@@") => hunk.push_str(line),
+            _ => hunks.push(line.to_string()),
+        }
+    }
+    hunks
+}
+
+/// Human-readable name for a delta status
+fn status_label(status: Delta) -> &'static str {
+    match status {
+        Delta::Added => "new",
+        Delta::Untracked => "new, untracked",
+        Delta::Deleted => "deleted",
+        Delta::Renamed => "renamed",
+        Delta::Copied => "copied",
+        Delta::Typechange => "type changed",
+        _ => "modified",
+    }
+}
+
+impl App {
+    /// Generate a markdown document with the git diff of every collected file
+    ///
+    /// Unchanged files are listed so the model knows they were part of the
+    /// context. With `diff_content` turned on each file's full content follows
+    /// its diff.
+    pub fn generate_diff(&self) -> Result<String, AppError> {
+        Ok(self.diff_pieces(usize::MAX)?.concat())
+    }
+
+    /// Generate the diff export as parts of at most `part_size` bytes
+    ///
+    /// Parts end between files, so every code block is closed in the part it
+    /// was opened in. A file too big for a part gets its diff cut between
+    /// hunks and its content between lines, each piece in a block of its own.
+    pub(super) fn generate_diff_parts(&self, part_size: usize) -> Result<Vec<String>, AppError> {
+        Ok(pack_parts(self.diff_pieces(part_size)?, part_size))
+    }
+
+    /// The diff export as pieces that each stand on their own: the header,
+    /// then every file, with files bigger than `room` cut up
+    fn diff_pieces(&self, room: usize) -> Result<Vec<String>, AppError> {
+        let RepoDiffs { repo_paths, diffs, ignored } = self.repo_diffs()?;
+        let base = &self.export_options.diff_base;
+
+        let mut output = String::new();
+        output.push_str("# Code Review\n\n");
+        output.push_str(&format!("Generated from: {}\n", self.source_display_name()));
+        output.push_str(&format!("Changes against: {}\n", base));
+        // Diffs are always raw, only the full contents go through the transforms
+        if self.export_options.diff_content && self.export_options.transforms.any() {
+            output.push_str(&format!(
+                "File contents transformed for export: {}.\n",
+                self.export_options.transforms.describe()
+            ));
+        }
+        output.push('\n');
+
+        if let Some(metadata) = self.export_metadata()? {
+            output.push_str("## Repository\n\n");
+            output.push_str(&metadata.to_markdown());
+        }
+
+        if let Some(tree) = self.export_tree()? {
+            let fence = fence_for(&tree);
+            output.push_str(&format!(
+                "## Directory Structure\n\nFiles marked with [+] are included below.\n\n{}text\n{}{}\n",
+                fence, tree, fence
+            ));
+        }
+
+        let mut pieces = vec![output];
+        for (file, repo_path) in self.collected_files.iter().zip(&repo_paths) {
+            let diff = repo_path.as_ref().and_then(|p| diffs.get(p));
+
+            let status = match (repo_path, diff) {
+                (None, _) => "outside the repository".to_string(),
+                (Some(_), Some(diff)) => diff.label(),
+                (Some(path), None) if ignored.contains(path) => "not tracked, ignored".to_string(),
+                (Some(_), None) => "unchanged".to_string(),
+            };
+            // The diff always covers the whole file, only the content below it is cut down
+            let mode = match file.mode {
+                ContentMode::Outline if self.export_options.diff_content => ", outline".to_string(),
+                ContentMode::Ranges if self.export_options.diff_content => {
+                    format!(", lines {}", ranges_label(&file.ranges))
+                }
+                _ => String::new(),
+            };
+            let mut section = format!("\n## {} ({}{})\n", file.relative_path, status, mode);
+
+            if self.export_options.file_info {
+                section.push_str(&format!("\n{}\n", self.file_info_line(file)));
+            }
+
+            // Code blocks under the heading, as the pieces they can be cut into
+            let mut blocks: Vec<(&str, Vec<String>)> = Vec::new();
+            if let Some(diff) = diff {
+                // Patches come from git rather than the collection, redact them line by line
+                let patch = if self.redacting() {
+                    let file_name = file.path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+                    redact_patch(&diff.patch, &file_name)
+                } else {
+                    Cow::Borrowed(diff.patch.as_str())
+                };
+                blocks.push(("diff", split_hunks(&patch)));
+            }
+
+            if self.export_options.diff_content {
+                let content = self.export_content(file);
+                blocks.push((&file.language, content.split_inclusive('\n').map(str::to_string).collect()));
+            }
+
+            let whole: usize = section.len() + blocks.iter()
+                .map(|(language, lines)| code_block(language, &lines.concat()).len())
+                .sum::<usize>();
+            if whole <= room {
+                for (language, lines) in &blocks {
+                    section.push_str(&code_block(language, &lines.concat()));
+                }
+                pieces.push(section);
+                continue;
+            }
+
+            // Too big for one part, every chunk gets its own block and says whose it is
+            let continued = format!("\n## {} ({}, continued)\n", file.relative_path, status);
+            let heading_len = section.len().max(continued.len());
+            let mut heading = Some(section);
+            for (language, lines) in &blocks {
+                // Heading, fences and language tag, newlines included
+                let wrapper = heading_len + 2 * fence_for(&lines.concat()).len() + language.len() + 4;
+                for chunk in pack_parts(lines.clone(), room.saturating_sub(wrapper).max(1)) {
+                    let mut piece = heading.take().unwrap_or_else(|| continued.clone());
+                    piece.push_str(&code_block(language, &chunk));
+                    pieces.push(piece);
+                }
+            }
+            pieces.extend(heading);
+        }
+
+        Ok(pieces)
+    }
+
+    /// The raw patch of every collected file that has one, by file index
+    ///
+    /// Patches come from git rather than the collection, so they're scanned
+    /// for secrets on their own (see `scan_export`).
+    pub(super) fn diff_patches(&self) -> Result<Vec<(usize, String)>, AppError> {
+        let RepoDiffs { repo_paths, mut diffs, .. } = self.repo_diffs()?;
+        Ok(repo_paths
+            .iter()
+            .enumerate()
+            .filter_map(|(index, path)| Some((index, diffs.remove(path.as_ref()?)?.patch)))
+            .collect())
+    }
+
+    /// Find the collected files in the repository and diff them
+    fn repo_diffs(&self) -> Result<RepoDiffs, AppError> {
+        let root = self.git_root.as_ref().ok_or_else(|| {
+            AppError::NotInGitRepo("the diff export compares files against git".to_string())
+        })?;
+        let repo = Repository::open(root)?;
+        let workdir = repo.workdir().unwrap_or(root).to_path_buf();
+
+        // Paths as git sees them, relative to the working directory
+        let repo_paths: Vec<Option<PathBuf>> = self.collected_files
+            .iter()
+            .map(|file| file.path.strip_prefix(&workdir).ok().map(Path::to_path_buf))
+            .collect();
+
+        let diffs = self.collect_file_diffs(&repo, repo_paths.iter().flatten())?;
+
+        // Without a diff a file is unchanged, unless git never looked at it
+        let index = repo.index()?;
+        let ignored = repo_paths
+            .iter()
+            .flatten()
+            .filter(|path| !diffs.contains_key(*path) && index.get_path(path, 0).is_none())
+            .filter(|path| repo.status_should_ignore(path).unwrap_or(false))
+            .cloned()
+            .collect();
+
+        Ok(RepoDiffs { repo_paths, diffs, ignored })
+    }
+
+    /// Diff the given repo-relative paths against the configured base
+    ///
+    /// Everything is done in one diff so big collections don't walk the repo
+    /// once per file. Files without changes simply don't appear in the result.
+    ///
+    /// A collected file that was renamed since the base looks new, and the
+    /// path it was deleted from is outside the pathspec. So when anything is
+    /// new the diff is redone with every deleted path too, and git pairs
+    /// renames up from there.
+    fn collect_file_diffs<'a>(
+        &self,
+        repo: &Repository,
+        paths: impl Iterator<Item = &'a PathBuf>,
+    ) -> Result<HashMap<PathBuf, FileDiff>, AppError> {
+        // Pathspecs always use forward slashes
+        let mut pathspecs: Vec<String> = paths.map(|path| path.to_string_lossy().replace('\\', "/")).collect();
+
+        // An empty pathspec means "everything", which is not what we want
+        if pathspecs.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let wanted: HashSet<PathBuf> = pathspecs.iter().map(PathBuf::from).collect();
+
+        let mut diff = self.diff_against_base(repo, &pathspecs, true)?;
+
+        let any_new = diff.deltas().any(|delta| matches!(delta.status(), Delta::Added | Delta::Untracked));
+        if any_new {
+            // Untracked files can't be deleted ones, leave them out of the search
+            let deleted: Vec<String> = self.diff_against_base(repo, &[], false)?
+                .deltas()
+                .filter(|delta| delta.status() == Delta::Deleted)
+                .filter_map(|delta| delta.old_file().path().map(|p| p.to_string_lossy().replace('\\', "/")))
+                .collect();
+
+            if !deleted.is_empty() {
+                pathspecs.extend(deleted);
+                diff = self.diff_against_base(repo, &pathspecs, true)?;
+
+                let mut find = DiffFindOptions::new();
+                find.renames(true).for_untracked(true);
+                diff.find_similar(Some(&mut find))?;
+            }
+        }
+
+        let mut diffs: HashMap<PathBuf, FileDiff> = HashMap::new();
+        diff.print(DiffFormat::Patch, |delta, _hunk, line| {
+            let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) else {
+                return true;
+            };
+            // Deleted paths that turned out not to be renames aren't ours
+            if !wanted.contains(path) {
+                return true;
+            }
+
+            let entry = diffs.entry(path.to_path_buf()).or_insert_with(|| FileDiff {
+                status: delta.status(),
+                old_path: delta.old_file().path().filter(|old| *old != path).map(Path::to_path_buf),
+                patch: String::new(),
+            });
+
+            // Content lines carry their +/-/space marker separately
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                entry.patch.push(line.origin());
+            }
+            entry.patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+
+        Ok(diffs)
+    }
+
+    /// Diff the working tree against the configured base, limited to `pathspecs`
+    ///
+    /// An empty `pathspecs` diffs the whole repository.
+    fn diff_against_base<'r>(
+        &self,
+        repo: &'r Repository,
+        pathspecs: &[String],
+        untracked: bool,
+    ) -> Result<Diff<'r>, AppError> {
+        let mut options = DiffOptions::new();
+        options
+            .include_untracked(untracked)
+            .recurse_untracked_dirs(untracked)
+            .show_untracked_content(untracked)
+            .disable_pathspec_match(true);
+        for pathspec in pathspecs {
+            options.pathspec(pathspec);
+        }
+
+        let diff = match &self.export_options.diff_base {
+            DiffBase::Index => repo.diff_index_to_workdir(None, Some(&mut options))?,
+            base => {
+                let revision = match base {
+                    DiffBase::Ref(name) => name.as_str(),
+                    _ => "HEAD",
+                };
+
+                // A repo without commits has no HEAD tree, so everything is new
+                let tree = match repo.revparse_single(revision) {
+                    Ok(object) => Some(object.peel_to_tree()?),
+                    Err(e) if *base == DiffBase::Head
+                        && matches!(e.code(), ErrorCode::NotFound | ErrorCode::UnbornBranch) => None,
+                    Err(e) => return Err(e.into()),
+                };
+                repo.diff_tree_to_workdir_with_index(tree.as_ref(), Some(&mut options))?
+            }
+        };
+
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn renamed_files_diff_against_their_old_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let content: String = (1..=20).map(|i| format!("value_{} = {}\n", i, i)).collect();
+        fs::write(dir.path().join("old.py"), &content).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("old.py")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+
+        fs::remove_file(dir.path().join("old.py")).unwrap();
+        fs::write(dir.path().join("new.py"), content.replace("value_20 = 20", "value_20 = 21")).unwrap();
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        app.collect_inputs(&["new.py".to_string()]).unwrap();
+        let output = app.generate_diff().unwrap();
+
+        assert!(output.contains("## new.py (renamed from old.py)"), "{}", output);
+        assert!(output.contains("-value_20 = 20\n+value_20 = 21\n"), "{}", output);
+        assert!(!output.contains("+value_1 = 1\n"), "{}", output);
+    }
+
+    #[test]
+    fn ignored_files_are_not_called_unchanged() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*_local.py\n").unwrap();
+        fs::write(dir.path().join("main.py"), "print('hi')\n").unwrap();
+        fs::write(dir.path().join("forced_local.py"), "DEBUG = True\n").unwrap();
+        fs::write(dir.path().join("settings_local.py"), "DEBUG = False\n").unwrap();
+
+        // A tracked file that matches .gitignore is still tracked
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("main.py")).unwrap();
+        index.add_path(Path::new("forced_local.py")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        let inputs = ["main.py", "forced_local.py", "settings_local.py"].map(String::from);
+        app.collect_inputs(&inputs).unwrap();
+        let output = app.generate_diff().unwrap();
+
+        assert!(output.contains("## main.py (unchanged)"), "{}", output);
+        assert!(output.contains("## forced_local.py (unchanged)"), "{}", output);
+        assert!(output.contains("## settings_local.py (not tracked, ignored)"), "{}", output);
+    }
+}
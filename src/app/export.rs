@@ -1,68 +1,370 @@
 //! Export and output functionality for the RepoViewer application.
 //! 
 //! This module handles all operations related to exporting collected files
-//! and directory structures. It includes markdown generation, file saving,
+//! and directory structures. It includes markdown, XML and JSON generation, file saving,
 //! clipboard operations, and tree visualization.
 
-use super::App;
-use crate::app_error::AppError;
-use crate::clipboard::ClipboardManager;
+use super::{App, CollectedFile, ContentMode, DiffBase, FitReport};
+use super::metadata::RepoMetadata;
+use super::exporter::{ExportDestination, ExportSummary, TreeExporter};
+use super::template::ExportTemplate;
+use super::secrets::{ExportAction, SecretFinding};
+use super::split::ExportPart;
+use super::transform::{ExportTransforms, transform_lines};
+use crate::{app_error::AppError, utils::format_utc_timestamp};
+use serde::Serialize;
 use std::{
-    fs,
+    borrow::Cow,
     path::Path,
     time::{Duration, SystemTime},
 };
 
+/// Options that change what goes into a collection export
+/// 
+/// These apply to every export format, so they live on the app rather than
+/// on any one exporter.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Put the project's directory tree (with collected files marked) before the files
+    pub include_tree: bool,
+    /// Split exports bigger than this many bytes into numbered parts
+    pub part_size: Option<usize>,
+    /// What the diff export compares the working tree against
+    pub diff_base: DiffBase,
+    /// Put each file's full content after its diff in the diff export
+    pub diff_content: bool,
+    /// Add a section with the branch, commit, status and remote of the repo
+    pub include_metadata: bool,
+    /// Prefix every exported line with its line number so models can cite `file:line`
+    pub line_numbers: bool,
+    /// Show size, line count, language and modified time above each file
+    pub file_info: bool,
+    /// Sort files by directory under directory headings, with a table of contents
+    pub group_by_directory: bool,
+    /// Replace configured terms with placeholders (see `anonymize.rs`)
+    pub anonymize: bool,
+    /// Strip comments and whitespace from exported content (see `transform.rs`)
+    pub transforms: ExportTransforms,
+}
+
+/// A collection export rendered and ready to write
+#[derive(Debug, Clone)]
+pub struct RenderedExport {
+    /// The whole export, or its numbered parts when it was split
+    pub parts: Vec<String>,
+    /// What budget fitting did, if a budget is set
+    pub report: Option<FitReport>,
+}
+
+/// Top level of the JSON export
+/// 
+/// Kept separate from `App` so the JSON layout is explicit and doesn't change
+/// just because we add a field to the app state.
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    generator: String,
+    exported_at: u64,
+    source: String,
+    git_root: Option<&'a Path>,
+    start_dir: &'a Path,
+    file_count: usize,
+    total_size: usize,
+    total_tokens: usize,
+    tokenizer: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    part: Option<JsonPart<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repository: Option<RepoMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tree: Option<String>,
+    files: Vec<JsonFile<'a>>,
+}
+
+/// Which part of a split JSON export this is
+#[derive(Serialize)]
+struct JsonPart<'a> {
+    number: usize,
+    total: usize,
+    note: &'a str,
+}
+
+/// A collected file plus the derived fields that only make sense in the export
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    #[serde(flatten)]
+    file: &'a CollectedFile,
+    size: usize,
+}
+
 impl App {
-    /// Generate a markdown document from all collected files
+    /// Name of the project we're exporting from
     /// 
+    /// For git repos this is the repository folder name, otherwise the name of
+    /// the directory RepoViewer was started in.
+    pub fn source_display_name(&self) -> String {
+        let root = self.git_root.as_ref().unwrap_or(&self.start_dir);
+        root.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.to_string_lossy().to_string())
+    }
+
+    /// Generate a markdown document from all collected files
     /// 
-    /// This is the main function that actually provides the formatting for the file contents
-    /// and encases them in "`" and the markdown language "code"(?)
+    /// This renders the built-in default template: a `# Code Context` title,
+    /// then each file's relative path as a `##` header followed by its content
+    /// in a fenced code block tagged with the language for syntax highlighting.
+    pub fn generate_markdown(&self) -> Result<String, AppError> {
+        self.generate_from_template(&ExportTemplate::builtin())
+    }
+
+    /// The project tree for a collection export, if the user asked for one
+    pub(super) fn export_tree(&self) -> Result<Option<String>, AppError> {
+        if self.export_options.include_tree {
+            self.generate_project_tree().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Generate an XML document from all collected files
     /// 
-    /// We provide the path as the header and then it is followed by the encased file contents
-    pub fn generate_markdown(&self) -> String {
+    /// The layout is the `<documents>`/`<document>` structure a lot of models are
+    /// trained to read. File content goes into a CDATA section so code stays readable
+    /// (no `&lt;` soup), and `escape_cdata` takes care of content that would otherwise
+    /// close the section early.
+    pub fn generate_xml(&self) -> Result<String, AppError> {
+        self.render_xml(None)
+    }
+
+    /// Render the XML export, as one part of a split export when `part` is set
+    ///
+    /// A part is a complete `<documents>` of its own with a `<part>` element
+    /// saying which one it is, and its documents keep their index in the whole export.
+    pub(super) fn render_xml(&self, part: Option<&ExportPart>) -> Result<String, AppError> {
         let mut output = String::new();
-        
-        output.push_str("# Code Context\n\n");
-        
-        // We would like to get a source to display for our markdown file for more info
-        let source_display = if let Some(git_root) = &self.git_root {
-            // For git repos, show the repository name (last component of path)
-            git_root.file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_else(|| git_root.to_string_lossy().to_string())
-        } else {
-            // For non-git directories, show the directory name
-            self.start_dir.file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_else(|| self.start_dir.to_string_lossy().to_string())
-        };
-        
-        output.push_str(&format!("Generated from: {}\n\n", source_display));
-        
-        // For each collected file create a section with proper formatting
-        for file in &self.collected_files {
-            // Add file header using ## to provide files relative path
-            output.push_str(&format!("\n## {}\n\n", file.relative_path));
-            
-            // Add code block with syntax highlighting via language name/code thing
-            output.push_str(&format!("````{}\n", file.language));
-            output.push_str(&file.content);
-            // Ensure the code block is properly closed even if file doesnt end with newline
-            if !file.content.ends_with('\n') {
-                output.push('\n');
+
+        output.push_str("<documents>\n");
+
+        if let Some(part) = part {
+            output.push_str(&format!(
+                "<part number=\"{}\" total=\"{}\">{}</part>\n",
+                part.number,
+                part.total,
+                escape_xml_text(&part.note)
+            ));
+        }
+
+        if self.export_options.transforms.any() {
+            output.push_str(&format!(
+                "<transforms>{}</transforms>\n",
+                escape_xml_text(&self.export_options.transforms.describe())
+            ));
+        }
+
+        if let Some(metadata) = self.export_metadata()? {
+            output.push_str("<metadata>\n");
+            for (label, value) in metadata.fields() {
+                let tag = label.to_lowercase();
+                output.push_str(&format!("<{}>{}</{}>\n", tag, escape_xml_text(&value), tag));
             }
-            output.push_str("````\n");
+            output.push_str("</metadata>\n");
         }
-        
-        output
+
+        if let Some(tree) = self.export_tree()? {
+            output.push_str("<directory_tree><![CDATA[");
+            output.push_str(&escape_cdata(&tree));
+            output.push_str("]]></directory_tree>\n");
+        }
+
+        // Index starts at 1 so it matches how people refer to "document 1" in prompts
+        let first_index = part.map_or(0, |part| part.first_index);
+        for (i, file) in self.collected_files.iter().enumerate().map(|(i, file)| (first_index + i, file)) {
+            // Per-file info goes into attributes so the content stays untouched
+            let info = if self.export_options.file_info {
+                format!(
+                    " size=\"{}\" lines=\"{}\" modified=\"{}\"",
+                    file.content.len(),
+                    file.content.lines().count(),
+                    escape_xml_attr(&file_modified(file))
+                )
+            } else {
+                String::new()
+            };
+
+            // Only flagged when it's not the whole file, so the usual case stays short
+            let mode = match file.mode {
+                ContentMode::Full => String::new(),
+                ContentMode::Outline => " mode=\"outline\"".to_string(),
+                ContentMode::Ranges => format!(
+                    " mode=\"ranges\" ranges=\"{}\"",
+                    file.ranges.iter().map(|range| range.to_string()).collect::<Vec<_>>().join(",")
+                ),
+            };
+
+            output.push_str(&format!(
+                "<document index=\"{}\" path=\"{}\" language=\"{}\"{}{}>\n",
+                i + 1,
+                escape_xml_attr(&file.relative_path),
+                escape_xml_attr(&file.language),
+                mode,
+                info
+            ));
+            output.push_str(&format!("<source>{}</source>\n", escape_xml_text(&file.relative_path)));
+            output.push_str("<document_content><![CDATA[");
+            output.push_str(&escape_cdata(&self.export_content(file)));
+            output.push_str("]]></document_content>\n");
+            output.push_str("</document>\n");
+        }
+
+        output.push_str("</documents>\n");
+
+        Ok(output)
+    }
+
+    /// Generate a JSON document from all collected files
+    /// 
+    /// This is meant for scripts that post-process exports, so everything is a
+    /// plain field: repo info at the top and one object per file with its metadata
+    /// and content.
+    pub fn generate_json(&self) -> Result<String, AppError> {
+        self.render_json(None)
+    }
+
+    /// Render the JSON export, as one part of a split export when `part` is set
+    ///
+    /// A part is a complete JSON document of its own with a `part` field
+    /// saying which one it is.
+    pub(super) fn render_json(&self, part: Option<&ExportPart>) -> Result<String, AppError> {
+        let exported_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let export = JsonExport {
+            generator: format!("RepoViewer {}", env!("CARGO_PKG_VERSION")),
+            exported_at,
+            source: self.source_display_name(),
+            git_root: self.git_root.as_deref(),
+            start_dir: &self.start_dir,
+            file_count: self.collected_files.len(),
+            total_size: self.get_collection_size(),
+            total_tokens: self.get_collection_tokens(),
+            tokenizer: self.tokenizer.name(),
+            part: part.map(|part| JsonPart { number: part.number, total: part.total, note: &part.note }),
+            repository: self.export_metadata()?,
+            tree: self.export_tree()?,
+            files: self.collected_files
+                .iter()
+                .map(|file| JsonFile { file, size: file.content.len() })
+                .collect(),
+        };
+
+        let mut output = serde_json::to_string_pretty(&export)?;
+        output.push('\n');
+
+        Ok(output)
+    }
+
+    /// File content as it should appear in text exports
+    /// 
+    /// This is where per-line options like transforms and line numbers are
+    /// applied. The JSON export skips it on purpose so tools always get the
+    /// raw content.
+    pub(super) fn export_content<'a>(&self, file: &'a CollectedFile) -> Cow<'a, str> {
+        // Outlines and ranges carry the line numbers of the file on disk
+        let source_line = |line: usize| file.source_line(line);
+
+        let transforms = &self.export_options.transforms;
+        if !transforms.any() {
+            return if self.export_options.line_numbers {
+                let lines = file.content.lines().enumerate().map(|(i, line)| (source_line(i + 1), line));
+                Cow::Owned(number_lines(lines))
+            } else {
+                Cow::Borrowed(&file.content)
+            };
+        }
+
+        // Numbers stay those of the file on disk, even with lines stripped
+        let lines = transform_lines(&file.content, &file.language, transforms);
+        if self.export_options.line_numbers {
+            let lines = lines.iter().map(|(number, line)| (source_line(*number), line.trim_end_matches(['\r', '\n'])));
+            Cow::Owned(number_lines(lines))
+        } else {
+            Cow::Owned(lines.into_iter().map(|(_, line)| line).collect())
+        }
+    }
+
+    /// Line in the file on disk behind each line `export_content` renders
+    pub(super) fn export_source_lines(&self, file: &CollectedFile) -> Vec<usize> {
+        let transforms = &self.export_options.transforms;
+        if transforms.any() {
+            transform_lines(&file.content, &file.language, transforms)
+                .iter()
+                .map(|(number, _)| file.source_line(*number))
+                .collect()
+        } else {
+            (1..=file.content.lines().count()).map(|line| file.source_line(line)).collect()
+        }
+    }
+
+    /// Summary of a file's metadata for per-file headers
+    /// 
+    /// e.g. `12.30 KB · 340 lines · rust · ~2.1k tokens · modified 2025-06-01 14:03:22 UTC`
+    pub(super) fn file_info_line(&self, file: &CollectedFile) -> String {
+        format!(
+            "{} · {} lines · {} · {} · modified {}",
+            self.format_size(file.content.len()),
+            file.content.lines().count(),
+            file.language,
+            self.format_tokens(file.token_count),
+            file_modified(file)
+        )
+    }
+
+    /// Render the collection in the active format
+    ///
+    /// With a token budget set the collection is fitted first, and the report
+    /// comes back with the export so 'F' can show what was cut. The findings
+    /// are redacted before fitting, so the budget counts what's really
+    /// exported. With `split` and a part size set, the export is rendered as
+    /// numbered parts (see `render_parts`).
+    ///
+    /// The text is final: redaction and anonymization have already happened,
+    /// so what's scanned and measured is exactly what gets written.
+    pub fn render_collection(&self, split: bool, findings: &[SecretFinding]) -> Result<RenderedExport, AppError> {
+        let exporter = self.exporters.active();
+        let redacted = self.redacted_collection(findings);
+        let unfitted = redacted.as_ref().unwrap_or(self);
+        let fitted = unfitted.fit_collection(exporter)?;
+        let (source, report) = match &fitted {
+            Some((app, report)) => (app, Some(report.clone())),
+            None => (unfitted, None),
+        };
+
+        let parts = match self.export_options.part_size.filter(|_| split) {
+            Some(part_size) => source.render_parts(exporter, part_size)?,
+            None => vec![source.finish_output(&exporter.export(source)?)?],
+        };
+
+        Ok(RenderedExport { parts, report })
     }
 
-    /// Save the collection to a markdown file in the current directory
+    /// Write a rendered export to a destination in one piece
+    pub fn write_export(
+        &mut self,
+        export: RenderedExport,
+        destination: &ExportDestination,
+    ) -> Result<(ExportSummary, Option<FitReport>), AppError> {
+        let summary = self.write_output(&export.parts.concat(), destination)?;
+        self.fit_report = export.report.clone();
+        Ok((summary, export.report))
+    }
+
+    /// Save the collection to a file in the current directory
     /// 
     /// If no filename is provided generates one with a timestamp to avoid
     /// overwriting existing files. This makes it safe to export multiple times.
+    /// The export is scanned for secrets first, see `prepare_export`.
     pub fn save_collection_to_file(&mut self, filename: Option<String>) -> Result<(), AppError> {
         // Check if we have anything to save
         if self.collected_files.is_empty() {
@@ -70,9 +372,21 @@ impl App {
             return Ok(());
         }
 
-        // Generate the markdown content
-        let markdown = self.generate_markdown();
-        
+        match self.prepare_export(ExportAction::Save(filename.clone()))? {
+            Some(export) => self.save_rendered(filename, export),
+            None => Ok(()),
+        }
+    }
+
+    /// Save an export that's already been rendered and scanned
+    pub(super) fn save_rendered(&mut self, filename: Option<String>, export: RenderedExport) -> Result<(), AppError> {
+        self.fit_report = export.report.clone();
+
+        // Too big for one part, write one file per part instead
+        if export.parts.len() > 1 {
+            return self.save_collection_parts(export);
+        }
+
         // Create filename with timestamp if not provided
         // This ensures we never accidentally overwrite previous exports
         let filename = filename.unwrap_or_else(|| {
@@ -80,33 +394,39 @@ impl App {
             // Handle potential system time before UNIX_EPOCH gracefully
             let since_epoch = now.duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap_or_else(|_| Duration::from_secs(0));
-            format!("code_context_{}.md", since_epoch.as_secs())
+            format!("code_context_{}.{}", since_epoch.as_secs(), self.exporters.active().extension())
         });
 
         // Save to the current directory where the user is browsing
         let output_path = self.current_dir.join(&filename);
-        fs::write(&output_path, markdown)?;
+        let summary = self.write_output(&export.parts.concat(), &ExportDestination::File(output_path.clone()))?;
 
         // Provide feedback with a friendly display path
         // This makes the success message much more readable, especially when
         // the user is deep in a directory structure
         let display_path = self.get_display_path(&output_path);
         
-        // Include file count and size information for user awareness
-        let total_size = self.get_collection_size();
-        let size_str = self.format_size(total_size);
+        // Include file count, size and token information for user awareness
+        let size_str = self.format_size(summary.bytes);
+        let tokens_str = self.format_tokens(summary.tokens);
         
-        self.set_success_message(format!(
-            "Saved {} files ({}) to {}", 
+        let mut message = format!(
+            "Saved {} files ({}, {}) as {} to {}", 
             self.collected_files.len(),
             size_str,
+            tokens_str,
+            self.exporters.active().name(),
             display_path
-        ));
+        );
+        message.push_str(&self.export_notes(export.report.as_ref()));
+        self.set_success_message(message);
         
         Ok(())
     }
 
-    /// Copy the markdown collection to the system clipboard
+    /// Copy the exported collection to the system clipboard
+    ///
+    /// The export is scanned for secrets first, see `prepare_export`.
     pub fn copy_collection_to_clipboard(&mut self) -> Result<(), AppError> {
         // Check if we have anything to copy
         if self.collected_files.is_empty() {
@@ -114,129 +434,58 @@ impl App {
             return Ok(());
         }
 
-        let markdown = self.generate_markdown();
+        match self.prepare_export(ExportAction::Copy)? {
+            Some(export) => self.copy_rendered(export),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy an export that's already been rendered and scanned
+    pub(super) fn copy_rendered(&mut self, export: RenderedExport) -> Result<(), AppError> {
+        self.fit_report = export.report.clone();
+
+        // Too big for one part, copy part 1 and let 'n' copy the rest
+        if export.parts.len() > 1 {
+            return self.copy_collection_parts(export);
+        }
+
+        // The clipboard manager handles platform-specific lifetime issues for us
+        let summary = self.write_output(&export.parts.concat(), &ExportDestination::Clipboard)?;
         
-        // Calculate the size of what we're copying to let people know how much they copied
-        let size_str = self.format_size(markdown.len());
+        // Calculate the size of what we copied to let people know how much they copied
+        let size_str = self.format_size(summary.bytes);
+        let tokens_str = self.format_tokens(summary.tokens);
         let file_count = self.collected_files.len();
         
-        // Use our new clipboard manager for proper lifetime handling
-        ClipboardManager::set_text(markdown)?;
-        
         // Provide detailed success feedback so users know what was copied
-        self.set_success_message(format!(
-            "Copied {} files ({}) to clipboard!",
+        let mut message = format!(
+            "Copied {} files ({}, {}) as {} to clipboard!",
             file_count,
-            size_str
-        ));
+            size_str,
+            tokens_str,
+            self.exporters.active().name()
+        );
+        message.push_str(&self.export_notes(export.report.as_ref()));
+        self.set_success_message(message);
         
         Ok(())
     }
 
-    /// Generate a tree structure string of the current directory
-    /// 
-    /// This creates a visual representation of the directory structure which is
-    /// one of the major features of this program. Having the ability to easily get
-    /// the tree structure of repos is super helpful when providing context/metadata
-    /// about projects and was techincally the reason I made this! 🐈🐈🐈
-    /// 
-    /// This is just the public facing function, the actual function is recursive and priv
-    pub fn generate_tree(&self, max_depth: Option<usize>) -> Result<String, AppError> {
-        let mut output = String::new();
-        
-        // Start with a friendly display path instead of absolute path
-        // This makes the tree output cleaner and more focused on structure
-        // rather than system-specific paths
-        let root_display = self.get_display_path(&self.current_dir);
-        output.push_str(&format!("{}\n", root_display));
-        
-        // call our recursive which has the actual logic
-        self.generate_tree_recursive(&self.current_dir, &mut output, "", 0, max_depth)?;
-
-        Ok(output)
-    }
-
-    /// Recursive helper function to build the tree structure
-    /// 
-    /// This function does the heavy lifting of creating the ASCII art tree.
-    /// It handles:
-    /// - Proper indentation with tree drawing characters
-    /// - Respecting visibility settings (hidden files, gitignored files)
-    /// - Depth limiting to prevent extremely deep trees
-    /// - Sorting entries (directories first, then alphabetically)
-    fn generate_tree_recursive(
-        &self,
-        dir: &Path,
-        output: &mut String,
-        prefix: &str, 
-        depth: usize,
-        max_depth: Option<usize>,
-    ) -> Result<(), AppError> {
-        // Check if we've reached the maximum depth
-        if let Some(max) = max_depth {
-            if depth >= max {
-                return Ok(());
-            }
-        }
-
-        // Read directory entries and filter based on visibility settings
-        let mut entries = fs::read_dir(dir)?
-            .filter_map(|entry_result| entry_result.ok())
-            .filter(|entry| {
-                let path = entry.path();
-                let name = entry.file_name().to_string_lossy().to_string();
-                // Respect the same visibility rules as the main file list
-                name != ".git" && self.should_include_file(&path, &name, path.is_dir())
-            })
-            .collect::<Vec<fs::DirEntry>>();
-
-        // Sort entries: directories first, then alphabetically by name
-        // This consistent ordering makes the tree easier to read
-        entries.sort_by_key(|entry| (!entry.path().is_dir(), entry.file_name()));
-
-        let total_entries = entries.len();
-
-        // Draw each entry with appropriate tree characters
-        for (i, entry) in entries.iter().enumerate() {
-            let is_last_entry = i == total_entries - 1;
-            let path = entry.path();
-            let name = entry.file_name().to_string_lossy().to_string();
-
-            // Tree drawing characters:
-            // ├── for entries that have siblings below them
-            // └── for the last entry in a directory
-            let connector = if is_last_entry { "└── " } else { "├── " };
-            // Continue the vertical line for non-last entries
-            let extension = if is_last_entry { "    " } else { "│   " };
-
-            // Build the line with proper prefix
-            output.push_str(prefix);
-            output.push_str(connector);
-
-            if path.is_dir() {
-                // Add directory name with trailing slash for clarity
-                output.push_str(&name);
-                output.push('/');
-                output.push('\n');
-
-                // Recurse into subdirectory with extended prefix
-                // The prefix maintains the tree structure visually
-                let new_prefix = format!("{}{}", prefix, extension);
-                self.generate_tree_recursive(
-                    &path, 
-                    output, 
-                    &new_prefix, 
-                    depth + 1, 
-                    max_depth
-                )?;
-            } else {
-                // Just add the filename for regular files
-                output.push_str(&name);
-                output.push('\n');
+    /// What budget fitting did and which options the format ignored, for the
+    /// end of a status message
+    pub(super) fn export_notes(&self, report: Option<&FitReport>) -> String {
+        let mut notes = match report {
+            Some(report) if report.is_lossless() => format!(" | {}", report.summary()),
+            Some(report) => format!(" | {} - press F for details", report.summary()),
+            None if self.token_budget.is_some() => {
+                format!(" | token budget not applied to {} exports", self.exporters.active().name())
             }
+            None => String::new(),
+        };
+        if let Some(note) = self.grouping_note() {
+            notes.push_str(&format!(" | {}", note));
         }
-        
-        Ok(())
+        notes
     }
 
     /// Copy the directory tree to clipboard
@@ -244,21 +493,159 @@ impl App {
     /// This provides a quick way to share project structure without
     /// having to save it to a file first.
     pub fn copy_tree_to_clipboard(&mut self) -> Result<(), AppError> {
-        let tree = self.generate_tree(None)?;
+        let summary = self.export_to(&TreeExporter { max_depth: None }, &ExportDestination::Clipboard)?;
         
         // Calculate the size for better user feedback
-        let size_str = self.format_size(tree.len());
-        
-        // Use our new clipboard manager with retry logic
-        // This handles busy clipboard scenarios gracefully
-        ClipboardManager::set_text(tree)?;
+        let size_str = self.format_size(summary.bytes);
+        let tokens_str = self.format_tokens(summary.tokens);
         
         // Provide informative success message
         self.set_success_message(format!(
-            "Tree ({}) copied to clipboard!",
-            size_str
+            "Tree ({}, {}) copied to clipboard!",
+            size_str,
+            tokens_str
+        ));
+
+        Ok(())
+    }
+
+    /// Save the directory tree to `tree.<ext>` in the current directory
+    pub fn save_tree_to_file(&mut self) -> Result<(), AppError> {
+        let output_path = self.current_dir.join(format!("tree.{}", self.tree_options.format.extension()));
+        let summary = self.export_to(&TreeExporter { max_depth: None }, &ExportDestination::File(output_path.clone()))?;
+
+        // Use display path for cleaner feedback
+        let display_path = self.get_display_path(&output_path);
+        self.set_success_message(format!(
+            "Tree ({}) saved to {}",
+            self.format_tokens(summary.tokens),
+            display_path
         ));
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Pick a code fence that can't be closed by anything inside the content
+/// 
+/// Markdown closes a fence at the first line with at least as many backticks,
+/// so the fence has to be longer than the longest backtick run in the content.
+/// Four is the minimum to keep exports looking the way they always have.
+pub(super) fn fence_for(content: &str) -> String {
+    let longest_run = content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+
+    "`".repeat((longest_run + 1).max(4))
+}
+
+/// Prefix each line with its right-aligned line number (`  12 | code`)
+fn number_lines<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> String {
+    let lines: Vec<(usize, &str)> = lines.collect();
+    let width = lines.iter().map(|(number, _)| *number).max().unwrap_or(1).to_string().len();
+    let mut output = String::new();
+
+    for (number, line) in lines {
+        output.push_str(&format!("{:>width$} | {}\n", number, line, width = width));
+    }
+
+    output
+}
+
+/// When the file was last modified, as a UTC timestamp
+pub(super) fn file_modified(file: &CollectedFile) -> String {
+    let secs = file.last_modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_utc_timestamp(secs)
+}
+
+/// Escape a string for use inside a double-quoted XML attribute
+fn escape_xml_attr(value: &str) -> String {
+    strip_invalid_xml_chars(value)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Escape a string for use as XML element text
+fn escape_xml_text(value: &str) -> String {
+    strip_invalid_xml_chars(value)
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Make arbitrary content safe to place inside a CDATA section
+/// 
+/// CDATA can't contain `]]>` so we use the standard trick of ending the section
+/// right after `]]` and starting a new one before `>`. Parsers join the pieces
+/// back together, so the content round-trips exactly.
+fn escape_cdata(content: &str) -> String {
+    strip_invalid_xml_chars(content).replace("]]>", "]]]]><![CDATA[>")
+}
+
+/// Replace characters that aren't allowed anywhere in an XML 1.0 document
+/// 
+/// `read_file_safely` lets a few stray control characters through (up to 5%),
+/// and even CDATA can't hold those, so we swap them for the replacement character.
+fn strip_invalid_xml_chars(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' => c,
+            c if c < '\u{20}' => '\u{FFFD}',
+            '\u{FFFE}' | '\u{FFFF}' => '\u{FFFD}',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::{Reader, events::Event};
+    use std::fs;
+
+    #[test]
+    fn xml_with_ranges_and_file_info_parses() {
+        let dir = tempfile::tempdir().unwrap();
+        let content: String = (1..=30).map(|i| format!("x{} = {}\n", i, i)).collect();
+        fs::write(dir.path().join("a.py"), content).unwrap();
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        app.collect_inputs(&["a.py:2-4,10-12".to_string()]).unwrap();
+        app.export_options.file_info = true;
+        let xml = app.generate_xml().unwrap();
+
+        let mut reader = Reader::from_str(&xml);
+        let mut document = None;
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(element)) if element.name().as_ref() == b"document" => {
+                    // Attributes are checked for duplicates as they're read
+                    let attributes = element
+                        .attributes()
+                        .map(|a| a.map(|a| (String::from_utf8(a.key.as_ref().to_vec()).unwrap(), a.unescape_value().unwrap().to_string())))
+                        .collect::<Result<Vec<_>, _>>()
+                        .unwrap();
+                    document = Some(attributes);
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => panic!("export isn't well-formed XML: {}\n{}", e, xml),
+            }
+        }
+
+        let attributes = document.expect("a document element");
+        let value = |name: &str| attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+        assert_eq!(value("mode"), Some("ranges"));
+        assert_eq!(value("ranges"), Some("2-4,10-12"));
+        assert_eq!(value("lines"), Some("9"));
+    }
+}
@@ -0,0 +1,317 @@
+//! Pluggable exporters for the RepoViewer application.
+//!
+//! An exporter turns the app state into text (markdown, XML, a tree, ...) and
+//! doesn't care where that text ends up. Where it ends up is an
+//! `ExportDestination` (a file, the clipboard or stdout). Keeping the two apart
+//! means a new format only has to implement `Exporter` and be registered, and
+//! every destination picks it up for free.
+
+use super::App;
+use super::split::split_text;
+use super::template::ExportTemplate;
+use crate::app_error::AppError;
+use crate::clipboard::ClipboardManager;
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+/// Something that can render the app state as exportable text
+pub trait Exporter {
+    /// Name shown in the status bar and accepted by `--format`
+    fn name(&self) -> &str;
+
+    /// File extension used when saving this export to disk
+    fn extension(&self) -> &str;
+
+    /// Render the export
+    fn export(&self, app: &App) -> Result<String, AppError>;
+
+    /// Render the export as parts of at most `part_size` bytes each
+    ///
+    /// The default cuts the finished export between lines. Formats that know
+    /// where their file boundaries are can split more cleanly.
+    fn export_parts(&self, app: &App, part_size: usize) -> Result<Vec<String>, AppError> {
+        Ok(split_text(&self.export(app)?, part_size))
+    }
+
+    /// Whether `export_parts` puts the part number inside each part itself
+    ///
+    /// Otherwise every part gets a plain text `Part 2/4` header, which would
+    /// break formats whose parts have to stay valid documents.
+    fn labels_parts(&self) -> bool {
+        false
+    }
+
+    /// Whether the export shrinks when collected files are cut down, so it
+    /// can be fitted to a token budget
+    fn fits_budget(&self) -> bool {
+        true
+    }
+
+    /// Whether the export puts files under directory headings when grouping is on
+    fn groups_by_directory(&self) -> bool {
+        true
+    }
+
+    /// Text the export adds for each collected file that isn't in the
+    /// collection, by file index
+    ///
+    /// It's scanned for secrets with that file's rules.
+    fn file_sections(&self, _app: &App) -> Result<Vec<(usize, String)>, AppError> {
+        Ok(Vec::new())
+    }
+}
+
+/// `## path` headers followed by fenced code blocks (the built-in default template)
+pub struct MarkdownExporter;
+
+impl Exporter for MarkdownExporter {
+    fn name(&self) -> &str { "markdown" }
+    fn extension(&self) -> &str { "md" }
+    fn export(&self, app: &App) -> Result<String, AppError> {
+        app.generate_markdown()
+    }
+    fn export_parts(&self, app: &App, part_size: usize) -> Result<Vec<String>, AppError> {
+        app.generate_parts_from_template(&ExportTemplate::builtin(), part_size)
+    }
+}
+
+/// `<documents>` with one `<document>` element per file
+pub struct XmlExporter;
+
+impl Exporter for XmlExporter {
+    fn name(&self) -> &str { "xml" }
+    fn extension(&self) -> &str { "xml" }
+    fn export(&self, app: &App) -> Result<String, AppError> {
+        app.generate_xml()
+    }
+    fn export_parts(&self, app: &App, part_size: usize) -> Result<Vec<String>, AppError> {
+        app.export_file_parts(part_size, |app, part| app.render_xml(part))
+    }
+    fn labels_parts(&self) -> bool { true }
+
+    fn groups_by_directory(&self) -> bool { false }
+}
+
+/// Machine-readable JSON with file metadata, for tooling pipelines
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn name(&self) -> &str { "json" }
+    fn extension(&self) -> &str { "json" }
+    fn export(&self, app: &App) -> Result<String, AppError> {
+        app.generate_json()
+    }
+    fn export_parts(&self, app: &App, part_size: usize) -> Result<Vec<String>, AppError> {
+        app.export_file_parts(part_size, |app, part| app.render_json(part))
+    }
+    fn labels_parts(&self) -> bool { true }
+
+    fn groups_by_directory(&self) -> bool { false }
+}
+
+/// Unified git diffs of the collected files, for code review
+pub struct DiffExporter;
+
+impl Exporter for DiffExporter {
+    fn name(&self) -> &str { "diff" }
+    fn extension(&self) -> &str { "md" }
+    fn export(&self, app: &App) -> Result<String, AppError> {
+        app.generate_diff()
+    }
+    fn export_parts(&self, app: &App, part_size: usize) -> Result<Vec<String>, AppError> {
+        app.generate_diff_parts(part_size)
+    }
+    fn file_sections(&self, app: &App) -> Result<Vec<(usize, String)>, AppError> {
+        app.diff_patches()
+    }
+    // The patches come from git, outlining or truncating a file doesn't shrink them
+    fn fits_budget(&self) -> bool { false }
+
+    fn groups_by_directory(&self) -> bool { false }
+}
+
+/// A user-defined template loaded from disk
+pub struct TemplateExporter {
+    pub template: ExportTemplate,
+}
+
+impl Exporter for TemplateExporter {
+    fn name(&self) -> &str { &self.template.name }
+    fn extension(&self) -> &str { &self.template.extension }
+    fn export(&self, app: &App) -> Result<String, AppError> {
+        app.generate_from_template(&self.template)
+    }
+    fn export_parts(&self, app: &App, part_size: usize) -> Result<Vec<String>, AppError> {
+        app.generate_parts_from_template(&self.template, part_size)
+    }
+}
+
+/// The directory tree of the current directory
+///
+/// This isn't part of the cyclable registry (it doesn't export the collection)
+/// but goes through the same destinations as everything else.
+pub struct TreeExporter {
+    pub max_depth: Option<usize>,
+}
+
+impl Exporter for TreeExporter {
+    fn name(&self) -> &str { "tree" }
+    fn extension(&self) -> &str { "txt" }
+    fn export(&self, app: &App) -> Result<String, AppError> {
+        app.generate_tree(self.max_depth)
+    }
+}
+
+/// Named collection exporters plus which one is currently active
+///
+/// Built-in formats come first, followed by user templates in load order.
+/// `cycle` walks through them in that order.
+#[derive(Clone)]
+pub struct ExporterRegistry {
+    exporters: Vec<Arc<dyn Exporter>>,
+    active: usize,
+}
+
+impl ExporterRegistry {
+    /// Create a registry with the built-in formats and the given user templates
+    ///
+    /// Templates whose name clashes with an existing exporter are skipped and
+    /// reported in the returned error list.
+    pub fn new(templates: Vec<ExportTemplate>) -> (Self, Vec<String>) {
+        let mut registry = ExporterRegistry {
+            exporters: vec![
+                Arc::new(MarkdownExporter),
+                Arc::new(XmlExporter),
+                Arc::new(JsonExporter),
+                Arc::new(DiffExporter),
+            ],
+            active: 0,
+        };
+
+        let mut errors = Vec::new();
+        for template in templates {
+            if let Err(e) = registry.register(Arc::new(TemplateExporter { template })) {
+                errors.push(e.to_string());
+            }
+        }
+
+        (registry, errors)
+    }
+
+    /// Add a new exporter, refusing names that are already taken
+    pub fn register(&mut self, exporter: Arc<dyn Exporter>) -> Result<(), AppError> {
+        if self.find(exporter.name()).is_some() {
+            return Err(AppError::TemplateError(format!(
+                "an export format named '{}' already exists",
+                exporter.name()
+            )));
+        }
+
+        self.exporters.push(exporter);
+        Ok(())
+    }
+
+    /// Index of the exporter with the given name (case-insensitive)
+    fn find(&self, name: &str) -> Option<usize> {
+        self.exporters
+            .iter()
+            .position(|e| e.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The currently selected exporter
+    pub fn active(&self) -> &dyn Exporter {
+        self.exporters[self.active].as_ref()
+    }
+
+    /// Make the exporter with the given name the active one
+    pub fn select(&mut self, name: &str) -> Result<(), AppError> {
+        self.active = self.find(name).ok_or_else(|| AppError::UnknownExportFormat {
+            name: name.to_string(),
+            available: self.names().join(", "),
+        })?;
+        Ok(())
+    }
+
+    /// Move to the next exporter, wrapping around at the end
+    pub fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.exporters.len();
+    }
+
+    /// Names of every registered exporter, in cycling order
+    pub fn names(&self) -> Vec<&str> {
+        self.exporters.iter().map(|e| e.name()).collect()
+    }
+}
+
+/// What an export produced, for success messages
+#[derive(Debug, Clone, Copy)]
+pub struct ExportSummary {
+    /// Size of the rendered output in bytes
+    pub bytes: usize,
+    /// Tokens in the rendered output, wrapper text included
+    pub tokens: usize,
+}
+
+/// Where an export should be written
+#[derive(Debug, Clone)]
+pub enum ExportDestination {
+    File(PathBuf),
+    Clipboard,
+    Stdout,
+}
+
+impl App {
+    /// Render an export and send it to a destination
+    ///
+    /// Returns the size of what was written so callers can report it.
+    pub fn export_to(
+        &self,
+        exporter: &dyn Exporter,
+        destination: &ExportDestination,
+    ) -> Result<ExportSummary, AppError> {
+        let output = self.finish_output(&exporter.export(self)?)?;
+        self.write_output(&output, destination)
+    }
+
+    /// Last step of rendering, before anything is measured or written
+    ///
+    /// Redacted secrets are taken out of the rendered text and anonymization
+    /// happens here, so every export has to pass through it.
+    pub(super) fn finish_output(&self, output: &str) -> Result<String, AppError> {
+        let redacted = self.redact_output(output);
+        Ok(match self.anonymize_output(&redacted)? {
+            Some(anonymized) => anonymized,
+            None => redacted.into_owned(),
+        })
+    }
+
+    /// Send finished text (see `finish_output`) to a destination
+    pub(super) fn write_output(
+        &self,
+        output: &str,
+        destination: &ExportDestination,
+    ) -> Result<ExportSummary, AppError> {
+        let summary = ExportSummary {
+            bytes: output.len(),
+            tokens: self.tokenizer.count(output),
+        };
+
+        match destination {
+            ExportDestination::File(path) => {
+                fs::write(path, output).map_err(|e| AppError::Io(e).with_path_context(path))?;
+            }
+            ExportDestination::Clipboard => ClipboardManager::set_text(output.to_string())?,
+            ExportDestination::Stdout => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(output.as_bytes())?;
+                stdout.flush()?;
+            }
+        }
+
+        Ok(summary)
+    }
+}
@@ -0,0 +1,424 @@
+//! Headless collection for the RepoViewer application.
+//!
+//! Scripts and CI jobs want the same context bundles the TUI builds, without
+//! a terminal. `collect_inputs` turns command line inputs into collected
+//! files: plain file paths, directories (every visible file below them),
+//! glob patterns matched against the project, parts of files
+//! (`src/lib.rs:10-40` or `src/lib.rs#Config::load`), or a path list piped in
+//! from something like `git ls-files`. Every file goes through
+//! `create_collected_file`, so the size, binary and encoding checks are the
+//! same as in the TUI, and anything that can't be collected is reported
+//! rather than silently dropped. Directories and globs quietly pass over
+//! `.repoviewerignore` paths, a file named outright is reported as skipped.
+
+use super::{App, CollectedFile, ContentMode, FileItem};
+use super::outline::symbol_lines;
+use super::ranges::{LineRange, parse_ranges};
+use crate::app_error::AppError;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+/// A file (or input) that didn't make it into the collection
+#[derive(Debug, Clone)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+/// What a headless collection did
+#[derive(Debug, Clone, Default)]
+pub struct CollectReport {
+    /// Files added to the collection
+    pub collected: usize,
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl CollectReport {
+    /// Fail when anything was skipped (`collect --fail-on-skip`)
+    pub fn check_skipped(&self) -> Result<(), AppError> {
+        if self.skipped.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::FilesSkipped(self.skipped.len()))
+        }
+    }
+}
+
+/// Read a path list piped in with `collect --stdin`, one input per line
+///
+/// Blank lines and the `\r` of Windows line endings are left for
+/// `collect_inputs`, which trims every input anyway.
+pub fn read_input_list(reader: impl BufRead) -> Result<Vec<String>, AppError> {
+    let mut inputs = Vec::new();
+    for line in reader.lines() {
+        inputs.push(line?);
+    }
+    Ok(inputs)
+}
+
+/// Whether an input should be treated as a glob rather than a path
+fn is_glob(input: &str) -> bool {
+    input.contains(['*', '?', '[', '{'])
+}
+
+/// Which part of a file an input asks for
+enum Selection<'a> {
+    /// `path:10-20,40-60`
+    Lines(&'a str),
+    /// `path#Config::load`
+    Symbol(&'a str),
+}
+
+/// Split an input like `src/lib.rs:10-20` or `src/lib.rs#parse` into the path and the part of it
+fn split_selection(input: &str) -> Option<(&str, Selection<'_>)> {
+    if let Some((path, symbol)) = input.rsplit_once('#')
+        && !path.is_empty()
+        && !symbol.is_empty()
+        && symbol.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '$' | ':' | '.'))
+    {
+        return Some((path, Selection::Symbol(symbol)));
+    }
+
+    let (path, lines) = input.rsplit_once(':')?;
+    let is_lines = !lines.is_empty() && lines.chars().all(|c| c.is_ascii_digit() || matches!(c, '-' | ','));
+    (!path.is_empty() && is_lines).then_some((path, Selection::Lines(lines)))
+}
+
+impl App {
+    /// Collect every file named by the given inputs, in input order
+    ///
+    /// Relative paths and globs are resolved against the start directory.
+    /// Directories and globs only pick up files that would be visible in the
+    /// TUI, so `show_hidden` and `show_gitignored` apply to them. A file named
+    /// more than once is only collected the first time, except that ranges of
+    /// the same file add up.
+    pub fn collect_inputs(&mut self, inputs: &[String]) -> Result<CollectReport, AppError> {
+        let mut report = CollectReport::default();
+
+        for input in inputs {
+            let input = input.trim();
+            if input.is_empty() {
+                continue;
+            }
+
+            let path = self.resolve_input(input);
+            let paths = if path.is_dir() {
+                let mut files = Vec::new();
+                self.walk_visible_files(&path, &mut files);
+                files
+            } else if path.exists() {
+                vec![path]
+            } else if let Some((file, selection)) = split_selection(input)
+                && self.resolve_input(file).is_file()
+            {
+                self.collect_selection(self.resolve_input(file), selection, &mut report);
+                continue;
+            } else if is_glob(input) {
+                let matches = self.expand_glob(input)?;
+                if matches.is_empty() {
+                    report.skipped.push(SkippedFile {
+                        path: input.to_string(),
+                        reason: "glob matched no files".to_string(),
+                    });
+                }
+                matches
+            } else {
+                report.skipped.push(SkippedFile {
+                    path: input.to_string(),
+                    reason: "no such file or directory".to_string(),
+                });
+                continue;
+            };
+
+            for path in paths {
+                self.collect_path(path, &mut report);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Turn an input into an absolute path without touching the filesystem
+    ///
+    /// `.` components are dropped so `./src/main.rs` gets the same relative
+    /// path in the export as `src/main.rs`.
+    fn resolve_input(&self, input: &str) -> PathBuf {
+        self.start_dir.join(input).components().collect()
+    }
+
+    /// Add one file to the collection, recording why if it can't be
+    fn collect_path(&mut self, path: PathBuf, report: &mut CollectReport) {
+        if self.collected_files.iter().any(|f| f.path == path) {
+            return;
+        }
+
+        if let Some(file) = self.read_input_file(path, report) {
+            self.collected_files.push(file);
+            report.collected += 1;
+        }
+    }
+
+    /// Add some lines of a file to the collection
+    ///
+    /// Lines of a file that's already collected as ranges are added to its
+    /// ranges. A file that's already collected whole stays whole.
+    fn collect_selection(&mut self, path: PathBuf, selection: Selection, report: &mut CollectReport) {
+        let existing = self.collected_files.iter().position(|f| f.path == path);
+        if existing.is_some_and(|index| self.collected_files[index].mode != ContentMode::Ranges) {
+            return;
+        }
+        let Some(mut file) = self.read_input_file(path, report) else {
+            return;
+        };
+
+        let ranges = match selection {
+            Selection::Lines(lines) => parse_ranges(lines),
+            Selection::Symbol(symbol) => symbol_lines(&file.content, &file.language, symbol)
+                .map(|(start, end)| vec![LineRange { start, end, symbol: Some(symbol.to_string()) }]),
+        };
+        let mut ranges = match ranges {
+            Ok(ranges) => ranges,
+            Err(e) => {
+                report.skipped.push(SkippedFile { path: self.get_display_path(&file.path), reason: e.to_string() });
+                return;
+            }
+        };
+        if let Some(index) = existing {
+            ranges.extend(self.collected_files[index].ranges.iter().cloned());
+        }
+
+        if let Err(e) = file.select_ranges(ranges, &self.tokenizer) {
+            report.skipped.push(SkippedFile { path: self.get_display_path(&file.path), reason: e.to_string() });
+            return;
+        }
+        match existing {
+            Some(index) => self.collected_files[index] = file,
+            None => {
+                self.collected_files.push(file);
+                report.collected += 1;
+            }
+        }
+    }
+
+    /// Read a file named on the command line, recording why if it can't be collected
+    fn read_input_file(&self, path: PathBuf, report: &mut CollectReport) -> Option<CollectedFile> {
+        let name = path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let item = FileItem {
+            is_dir: path.is_dir(),
+            is_symlink: path.is_symlink(),
+            is_hidden: self.is_hidden(&path, &name),
+            path,
+            name,
+        };
+
+        match self.create_collected_file(&item) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                report.skipped.push(SkippedFile {
+                    path: self.get_display_path(&item.path),
+                    reason: self.collect_error_reason(&e),
+                });
+                None
+            }
+        }
+    }
+
+    /// Every visible file below `dir`, sorted by path
+    ///
+    /// Paths in `.repoviewerignore` are left out along with everything below them.
+    /// Symlinked directories aren't followed so a link loop can't hang a script.
+    pub(super) fn walk_visible_files(&self, dir: &Path, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let is_dir = file_type.is_dir();
+
+            if name == ".git"
+                || !self.should_include_file(&path, &name, is_dir)
+                || self.is_export_ignored(&path, is_dir)
+            {
+                continue;
+            }
+
+            if is_dir {
+                self.walk_visible_files(&path, files);
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+    }
+
+    /// Files below the start directory matching a glob like `src/**/*.rs`
+    ///
+    /// The glob is matched against paths relative to the start directory and
+    /// `*` doesn't cross `/`, same as in a shell.
+    fn expand_glob(&self, pattern: &str) -> Result<Vec<PathBuf>, AppError> {
+        let matcher = glob_matcher([pattern])?;
+
+        let mut files = Vec::new();
+        self.walk_visible_files(&self.start_dir, &mut files);
+        files.retain(|path| self.matches_glob(&matcher, path));
+
+        Ok(files)
+    }
+
+    /// Whether a path relative to the start directory matches
+    fn matches_glob(&self, matcher: &GlobSet, path: &Path) -> bool {
+        path.strip_prefix(&self.start_dir)
+            .is_ok_and(|rel| matcher.is_match(rel.to_string_lossy().replace('\\', "/")))
+    }
+
+    /// Turn the collected files matching a glob into outlines (`collect --outline`)
+    ///
+    /// Returns the files that stayed whole because their language has no outline support.
+    pub fn outline_matching(&mut self, pattern: &str) -> Result<Vec<String>, AppError> {
+        let matcher = glob_matcher([pattern])?;
+        let selected: Vec<PathBuf> = self.collected_files
+            .iter()
+            .filter(|file| self.matches_glob(&matcher, &file.path))
+            .map(|file| file.path.clone())
+            .collect();
+
+        Ok(self.outline_collection(|file| selected.contains(&file.path)))
+    }
+}
+
+/// Compile globs the way a shell reads them, `*` doesn't cross `/`
+pub(super) fn glob_matcher<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<GlobSet, AppError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern.trim_start_matches("./"))
+            .literal_separator(true)
+            .build()
+            .map_err(|e| AppError::InvalidPattern(e.to_string()))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| AppError::InvalidPattern(e.to_string()))
+}
+
+/// Compile globs where ones without a `/` match names at any depth, `*.rs` reads as `**/*.rs`
+pub(super) fn anywhere_matcher<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Result<GlobSet, AppError> {
+    let patterns: Vec<String> = patterns
+        .into_iter()
+        .map(|p| if p.contains('/') { p.to_string() } else { format!("**/{}", p) })
+        .collect();
+    glob_matcher(patterns.iter().map(String::as_str))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let files = [
+            ("src/main.rs", "fn main() {\n    run();\n}\n\nfn run() {}\n"),
+            ("src/lib.rs", "pub mod config;\n"),
+            ("docs/guide.md", "# Guide\n"),
+            ("secrets/prod.env", "TOKEN=1\n"),
+            (".repoviewerignore", "secrets/\n"),
+        ];
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        fs::write(dir.path().join("logo.png"), [0x89, b'P', b'N', b'G', 0, 0, 0, 0]).unwrap();
+
+        let app = App::new(dir.path().to_path_buf()).unwrap();
+        (dir, app)
+    }
+
+    fn inputs(inputs: &[&str]) -> Vec<String> {
+        inputs.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn collected(app: &App) -> Vec<&str> {
+        app.collected_files.iter().map(|f| f.relative_path.as_str()).collect()
+    }
+
+    #[test]
+    fn collects_paths_directories_globs_and_lines() {
+        let (_dir, mut app) = project();
+        let report = app
+            .collect_inputs(&inputs(&["./src/lib.rs", "docs", "src/*.rs", "src/main.rs:1-3"]))
+            .unwrap();
+
+        assert_eq!(report.collected, 3);
+        assert!(report.skipped.is_empty());
+        assert_eq!(collected(&app), ["src/lib.rs", "docs/guide.md", "src/main.rs"]);
+        // Already collected whole by the glob, so the line range is ignored
+        assert_eq!(app.collected_files[2].mode, ContentMode::Full);
+    }
+
+    #[test]
+    fn reports_every_skipped_input() {
+        let (_dir, mut app) = project();
+        let report = app
+            .collect_inputs(&inputs(&["missing.rs", "*.toml", "logo.png", "secrets/prod.env", "src/lib.rs"]))
+            .unwrap();
+
+        assert_eq!(report.collected, 1);
+        let skipped: Vec<(&str, &str)> = report.skipped
+            .iter()
+            .map(|s| (s.path.as_str(), s.reason.as_str()))
+            .collect();
+        assert_eq!(skipped[0], ("missing.rs", "no such file or directory"));
+        assert_eq!(skipped[1], ("*.toml", "glob matched no files"));
+        assert_eq!(skipped[2], ("logo.png", "Unsupported type (.png)"));
+        assert_eq!(skipped[3].0, "./secrets/prod.env");
+        assert_eq!(skipped[3].1, AppError::ExportIgnored.to_string());
+        assert_eq!(skipped.len(), 4);
+    }
+
+    #[test]
+    fn directories_and_globs_pass_over_ignored_paths() {
+        let (_dir, mut app) = project();
+        let report = app.collect_inputs(&inputs(&[".", "**/*.env"])).unwrap();
+
+        assert!(!collected(&app).contains(&"secrets/prod.env"));
+        assert_eq!(report.skipped.len(), 2, "{:?}", report.skipped);
+        assert_eq!(report.skipped[0].path, "logo.png");
+        assert_eq!(report.skipped[1].reason, "glob matched no files");
+    }
+
+    #[test]
+    fn fail_on_skip_only_fails_when_something_was_skipped() {
+        let (_dir, mut app) = project();
+        let report = app.collect_inputs(&inputs(&["src/lib.rs"])).unwrap();
+        assert!(report.check_skipped().is_ok());
+
+        let report = app.collect_inputs(&inputs(&["src/main.rs", "missing.rs", "logo.png"])).unwrap();
+        assert!(matches!(report.check_skipped(), Err(AppError::FilesSkipped(2))));
+    }
+
+    #[test]
+    fn piped_path_lists_are_collected_line_by_line() {
+        let (_dir, mut app) = project();
+        let piped = "src/main.rs\r\n\n  ./src/lib.rs  \nsrc/main.rs\n".as_bytes();
+        let list = read_input_list(piped).unwrap();
+        assert_eq!(list.len(), 4);
+
+        let report = app.collect_inputs(&list).unwrap();
+        assert_eq!(report.collected, 2);
+        assert!(report.skipped.is_empty());
+        assert_eq!(collected(&app), ["src/main.rs", "src/lib.rs"]);
+    }
+}
@@ -2,11 +2,40 @@ mod state;
 mod collection;
 mod export;
 mod navigation;
+mod template;
+mod exporter;
+mod budget;
+mod split;
+mod diff;
+mod metadata;
+mod toc;
+mod headless;
+mod tree;
+mod secrets;
+mod anonymize;
+mod transform;
+mod outline;
+mod ranges;
+mod recursive;
+mod pattern;
 
 // Re-export important types so they're accessible as `app::TypeName`
 // This maintains the same public API that other files expect
-pub use state::Message;
-pub use collection::CollectedFile;
+pub use state::{CollectionHealth, Message};
+pub use budget::{FitOutcome, FitReport};
+pub use split::{PendingParts, MIN_PART_SIZE};
+pub use diff::DiffBase;
+pub use collection::{CollectedFile, ContentMode};
+pub use export::ExportOptions;
+pub use tree::{TreeAnnotations, TreeFormat, TreeOptions, DEPENDENCY_DIRS};
+pub use secrets::{SecretFinding, SecretPolicy, SecretReview};
+pub use anonymize::Anonymizer;
+pub use transform::ExportTransforms;
+pub use ranges::RangePicker;
+pub use recursive::{DirFilter, DirPreview};
+pub use pattern::PatternPrompt;
+pub use headless::read_input_list;
+pub use exporter::{ExportDestination, Exporter, ExporterRegistry, TreeExporter};
 
 // Standard library imports
 use std::{
@@ -15,14 +44,16 @@ use std::{
 };
 
 // External crate imports
-use ignore::gitignore::Gitignore;
 use ratatui::widgets::ListState;
 
 // Internal imports from our project
 use crate::{
     app_error::AppError,
+    ignores::IgnoreStack,
+    tokens::{TokenLimits, Tokenizer},
     utils::{find_repo},
 };
+use anonymize::ANONYMIZE_FILE;
 
 /// Main application state
 /// This struct holds all the data our TUI needs to function
@@ -34,11 +65,41 @@ pub struct App {
     pub items: Vec<FileItem>,
     pub collected_files: Vec<CollectedFile>,
     pub state: ListState,
-    pub gitignore: Option<Gitignore>,
+    pub ignores: Option<IgnoreStack>,
+    /// `.repoviewerignore` rules, paths that are listed but never exported
+    pub export_ignores: IgnoreStack,
     pub show_hidden: bool,
     pub show_gitignored: bool,
     pub message: Option<Message>,
     pub show_help: bool,
+    pub exporters: ExporterRegistry,
+    pub export_options: ExportOptions,
+    pub tree_options: TreeOptions,
+    pub tokenizer: Tokenizer,
+    pub token_limits: TokenLimits,
+    pub token_budget: Option<usize>,
+    pub fit_report: Option<FitReport>,
+    pub show_fit_report: bool,
+    pub pending_parts: Option<PendingParts>,
+    pub secret_policy: SecretPolicy,
+    /// An export waiting on the user to decide what to do about possible secrets
+    pub secret_review: Option<SecretReview>,
+    /// Findings to redact from the export being rendered, only ever set on
+    /// the copy it's rendered from (see `redacted_collection`)
+    pub redactions: Vec<SecretFinding>,
+    /// Terms to hide in exports and the placeholders they were given
+    pub anonymizer: Option<Anonymizer>,
+    /// Why the anonymization config couldn't be loaded, nothing can be
+    /// anonymized until it's fixed
+    pub anonymize_error: Option<String>,
+    /// The line picker, while it's open
+    pub range_picker: Option<RangePicker>,
+    /// What `W` adds when adding a directory recursively
+    pub dir_filter: DirFilter,
+    /// The recursive add waiting to be confirmed
+    pub dir_preview: Option<DirPreview>,
+    /// The glob/regex prompt, while it's open
+    pub pattern_prompt: Option<PatternPrompt>,
 }
 
 /// Represents a file system entry
@@ -57,7 +118,28 @@ impl App {
     /// Create a new App instance with the given starting directory
     pub fn new(start_dir: PathBuf) -> Result<Self, AppError> {
         // Try to see if there's a repo where we're looking
-        let (git_root, gitignore) = find_repo(&start_dir)?;
+        let (git_root, ignores, mut ignore_warnings) = match find_repo(&start_dir)? {
+            Some(repo) => (Some(repo.root), Some(repo.ignores), repo.warnings),
+            None => (None, None, Vec::new()),
+        };
+
+        // Never-export rules apply with or without git
+        let (export_ignores, warnings) =
+            IgnoreStack::export_rules(git_root.clone().unwrap_or_else(|| start_dir.clone()));
+        ignore_warnings.extend(warnings);
+
+        // User templates come from the config dir and the project root
+        let (templates, mut template_errors) =
+            template::load_templates(git_root.as_ref().unwrap_or(&start_dir));
+        let (exporters, registry_errors) = ExporterRegistry::new(templates);
+        template_errors.extend(registry_errors);
+
+        // A broken anonymize.toml is remembered, so turning anonymization on can say why it can't
+        let (anonymizer, anonymize_error) = match Anonymizer::load(git_root.as_ref().unwrap_or(&start_dir)) {
+            Ok(anonymizer) => (anonymizer, None),
+            Err(AppError::AnonymizeError(e)) => (None, Some(e)),
+            Err(e) => (None, Some(e.to_string())),
+        };
 
         // Create app struct to be filled in and returned
         let mut app = App {
@@ -67,13 +149,49 @@ impl App {
             items: Vec::new(),
             collected_files: Vec::new(),
             state: ListState::default(),
-            gitignore,
+            ignores,
+            export_ignores,
             show_hidden: false,
             show_gitignored: false,
             message: None,
             show_help: false,
+            exporters,
+            export_options: ExportOptions::default(),
+            tree_options: TreeOptions::default(),
+            tokenizer: Tokenizer::default(),
+            token_limits: TokenLimits::default(),
+            token_budget: None,
+            fit_report: None,
+            show_fit_report: false,
+            pending_parts: None,
+            secret_policy: SecretPolicy::default(),
+            secret_review: None,
+            redactions: Vec::new(),
+            anonymizer,
+            anonymize_error,
+            range_picker: None,
+            dir_filter: DirFilter::default(),
+            dir_preview: None,
+            pattern_prompt: None,
         };
 
+        // Don't fail startup over a broken template or ignore file, just let the user know
+        let mut problems = Vec::new();
+        if !template_errors.is_empty() {
+            problems.push(format!(
+                "Failed to load {} template(s): {}",
+                template_errors.len(),
+                template_errors.join(", ")
+            ));
+        }
+        if let Some(e) = &app.anonymize_error {
+            problems.push(format!("Failed to load {}: {}", ANONYMIZE_FILE, e));
+        }
+        problems.extend(ignore_warnings);
+        if !problems.is_empty() {
+            app.set_error_message(problems.join(" | "));
+        }
+
         // populate app 
         app.refresh_files()?;
 
@@ -143,11 +261,11 @@ impl App {
 
     /// Navigate into the selected directory
     pub fn navigate_into(&mut self) -> Result<(), AppError> {
-        if let Some(selection) = self.current_selection() {
-            if selection.is_dir {
-                self.current_dir = selection.path.clone();
-                self.refresh_files()?;
-            }
+        if let Some(selection) = self.current_selection()
+            && selection.is_dir
+        {
+            self.current_dir = selection.path.clone();
+            self.refresh_files()?;
         }
 
         Ok(())
@@ -0,0 +1,967 @@
+//! Outline collection for the RepoViewer application.
+//!
+//! Often a model only needs to know a file exists and what it exposes, not
+//! read all 2,000 lines of it. Collecting a file as an outline (`o`) keeps
+//! its imports, types, traits and classes, function and method signatures,
+//! and top-level constants, with every body elided:
+//!
+//! ```text
+//! pub fn parse(input: &str) -> Result<Config, Error> { … }
+//! ```
+//!
+//! Outlines work for Rust, Python, TypeScript/JavaScript, Go, Java, C#,
+//! Kotlin and C/C++. They're built from the same comment and string scanner
+//! the export transforms use, so braces and colons inside strings or comments
+//! don't throw off where a body ends. Struct, enum and interface bodies are
+//! kept whole since their fields are the interface. Comments and docstrings
+//! are dropped.
+//!
+//! An outline keeps the line numbers of the file on disk, and exports label
+//! outlined files so the model knows it isn't seeing everything.
+//!
+//! The same scanning finds where a definition starts and ends, which is how
+//! line ranges are picked by symbol (`src/lib.rs#Config::load`, see `ranges.rs`).
+
+use super::{App, CollectedFile, ContentMode, transform::scan_code};
+use crate::{app_error::AppError, tokens::Tokenizer};
+use std::ops::Range;
+
+/// Marker for an elided body
+const ELIDED: &str = "…";
+
+/// Keywords that decide how a brace-delimited block is outlined
+struct BraceLanguage {
+    /// Blocks kept whole: their contents are the interface (fields, variants)
+    data: &'static [&'static str],
+    /// Blocks whose members are outlined one by one
+    scopes: &'static [&'static str],
+    /// Keywords that start a function, so `fn f() -> impl Trait {` isn't an `impl` block
+    functions: &'static [&'static str],
+}
+
+/// How a language's outline is built
+enum OutlineStyle {
+    Braces(BraceLanguage),
+    /// Python: blocks are indentation, bodies become `...`
+    Indent,
+}
+
+fn style_for(language: &str) -> Option<OutlineStyle> {
+    let language = match language {
+        "rust" => BraceLanguage {
+            data: &["struct", "enum", "union"],
+            scopes: &["impl", "trait", "mod"],
+            functions: &["fn"],
+        },
+        "go" => BraceLanguage { data: &["struct", "interface"], scopes: &[], functions: &["func"] },
+        "javascript" | "typescript" | "jsx" => BraceLanguage {
+            data: &["interface", "enum", "type"],
+            scopes: &["class", "namespace", "module"],
+            functions: &["function"],
+        },
+        "java" => BraceLanguage { data: &[], scopes: &["class", "interface", "enum", "record"], functions: &[] },
+        "csharp" => BraceLanguage {
+            data: &[],
+            scopes: &["class", "interface", "struct", "enum", "record", "namespace"],
+            functions: &[],
+        },
+        "kotlin" => BraceLanguage {
+            data: &[],
+            scopes: &["class", "interface", "object"],
+            functions: &["fun"],
+        },
+        "c" | "cpp" => BraceLanguage {
+            data: &["enum", "union"],
+            scopes: &["class", "struct", "namespace"],
+            functions: &[],
+        },
+        "python" => return Some(OutlineStyle::Indent),
+        _ => return None,
+    };
+
+    Some(OutlineStyle::Braces(language))
+}
+
+/// A file split into lines three ways, all the same length so columns line up
+struct Source {
+    /// The code with comments blanked out
+    code: Vec<String>,
+    /// The code with comments and literals blanked out, for finding brackets
+    masked: Vec<String>,
+    /// Whether each line ends inside a (multi-line) literal
+    open_literal: Vec<bool>,
+    /// Whether each line is blank in the file itself (not just once comments are gone)
+    empty: Vec<bool>,
+}
+
+impl Source {
+    fn new(content: &str, language: &str) -> Self {
+        let spans = scan_code(content, language).unwrap_or_default();
+        let code = blank_spans(content, &spans.comments);
+        let masked = blank_spans(&code, &spans.literals);
+
+        // Line ends are the `\n` positions, compare them with the literal spans
+        let mut open_literal = Vec::new();
+        let mut literals = spans.literals.iter().peekable();
+        for (position, _) in content.match_indices('\n') {
+            while literals.next_if(|span| span.end <= position).is_some() {}
+            open_literal.push(literals.peek().is_some_and(|span| span.start < position));
+        }
+        open_literal.push(false);
+
+        Source {
+            code: code.lines().map(str::to_string).collect(),
+            masked: masked.lines().map(str::to_string).collect(),
+            open_literal,
+            empty: content.lines().map(|line| line.trim().is_empty()).collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.code.len()
+    }
+
+    fn is_blank(&self, line: usize) -> bool {
+        self.masked[line].trim().is_empty()
+    }
+}
+
+/// Replace every byte of the spans with a space, keeping newlines
+fn blank_spans(text: &str, spans: &[Range<usize>]) -> String {
+    let mut bytes = text.as_bytes().to_vec();
+    for span in spans {
+        for byte in &mut bytes[span.clone()] {
+            if *byte != b'\n' && *byte != b'\r' {
+                *byte = b' ';
+            }
+        }
+    }
+    // Spans start and end on character boundaries and only whole characters
+    // become ASCII spaces, so this is still valid UTF-8
+    String::from_utf8(bytes).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+/// The outline lines so far, each with the line it came from (1-based)
+#[derive(Default)]
+struct Outline {
+    lines: Vec<(usize, String)>,
+}
+
+impl Outline {
+    fn push(&mut self, line: usize, text: &str) {
+        self.lines.push((line + 1, text.trim_end().to_string()));
+    }
+
+    /// Keep one blank line between items, like the file did
+    fn separate(&mut self, line: usize) {
+        if self.lines.last().is_some_and(|(_, text)| !text.is_empty()) {
+            self.lines.push((line + 1, String::new()));
+        }
+    }
+
+    fn finish(mut self) -> Vec<(usize, String)> {
+        while self.lines.last().is_some_and(|(_, text)| text.is_empty()) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+}
+
+/// Build the outline of a file
+///
+/// Returns each outline line with the number of the line it came from, or
+/// `None` when outlines aren't supported for the language.
+pub fn outline_lines(content: &str, language: &str) -> Option<Vec<(usize, String)>> {
+    let style = style_for(language)?;
+    let source = Source::new(content, language);
+
+    let lines = match style {
+        OutlineStyle::Braces(language) => outline_braces(&source, &language),
+        OutlineStyle::Indent => outline_indented(&source),
+    };
+
+    Some(lines)
+}
+
+/// Where a statement's body opens, if it has one
+#[derive(Clone, Copy)]
+struct Opener {
+    line: usize,
+    column: usize,
+}
+
+/// Indentation at the start of a line
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Emit a statement, with multi-line values cut down to their first line,
+/// `…` and their last line
+fn push_statement(outline: &mut Outline, source: &Source, lines: Range<usize>) {
+    let is_value = source.masked[lines.start].contains('=');
+    if is_value && lines.len() > 2 {
+        let first = lines.start;
+        outline.push(first, &source.code[first]);
+        outline.push(first + 1, &format!("{}    {}", indentation(&source.code[first]), ELIDED));
+        outline.push(lines.end - 1, &source.code[lines.end - 1]);
+    } else {
+        for i in lines {
+            outline.push(i, &source.code[i]);
+        }
+    }
+}
+
+fn outline_braces(source: &Source, language: &BraceLanguage) -> Vec<(usize, String)> {
+    let mut outline = Outline::default();
+    // How many scope blocks we're inside
+    let mut scopes = 0;
+    let mut i = 0;
+
+    while i < source.len() {
+        if source.is_blank(i) {
+            if source.empty[i] {
+                outline.separate(i);
+            }
+            i += 1;
+            continue;
+        }
+
+        // The end of the scope we're in
+        if scopes > 0 && brace_delta(&source.masked[i]) < 0 {
+            outline.push(i, &source.code[i]);
+            scopes -= 1;
+            i += 1;
+            continue;
+        }
+
+        // Gather a statement: up to a body opening, or until brackets balance
+        let start = i;
+        let mut brackets = 0i32;
+        let mut braces = 0i32;
+        let mut opener = None;
+        let mut end = i;
+        while end < source.len() {
+            for (column, c) in source.masked[end].char_indices() {
+                match c {
+                    '(' | '[' => brackets += 1,
+                    ')' | ']' => brackets -= 1,
+                    '{' => {
+                        if braces == 0 {
+                            opener = Some(Opener { line: end, column });
+                        }
+                        braces += 1;
+                    }
+                    '}' => braces -= 1,
+                    _ => {}
+                }
+            }
+            if braces > 0 || (brackets <= 0 && !source.open_literal[end]) {
+                break;
+            }
+            end += 1;
+        }
+        let end = end.min(source.len() - 1);
+
+        let opener = match opener {
+            Some(opener) if braces > 0 => opener,
+            _ => {
+                push_statement(&mut outline, source, start..end + 1);
+                i = end + 1;
+                continue;
+            }
+        };
+
+        let head: String = (start..=opener.line)
+            .map(|l| if l == opener.line { &source.masked[l][..opener.column] } else { source.masked[l].as_str() })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        match classify(&head, language) {
+            Block::Data => {
+                let close = block_close(source, &opener);
+                for l in start..=close.line {
+                    outline.push(l, &source.code[l]);
+                }
+                i = close.line + 1;
+            }
+            Block::Scope => {
+                for l in start..=opener.line {
+                    outline.push(l, &source.code[l]);
+                }
+                scopes += 1;
+                i = opener.line + 1;
+            }
+            Block::Body => {
+                for l in start..opener.line {
+                    outline.push(l, &source.code[l]);
+                }
+                let (text, last) = elide_body(source, opener);
+                outline.push(opener.line, &text);
+                i = last + 1;
+            }
+        }
+    }
+
+    outline.finish()
+}
+
+/// Net change in brace depth over a line
+fn brace_delta(masked: &str) -> i32 {
+    masked.chars().fold(0, |depth, c| match c {
+        '{' => depth + 1,
+        '}' => depth - 1,
+        _ => depth,
+    })
+}
+
+/// What a block is
+enum Block {
+    /// Kept whole
+    Data,
+    /// Members outlined
+    Scope,
+    /// Elided
+    Body,
+}
+
+/// Decide what a block is from the code in front of its `{`
+///
+/// The first data, scope or function keyword outside parentheses decides.
+/// Scopes can't be assigned, so `x = new Foo() {` is a body.
+fn classify(head: &str, language: &BraceLanguage) -> Block {
+    let mut depth = 0;
+    let mut assigned = false;
+    let mut word = String::new();
+    let mut words = Vec::new();
+
+    for c in head.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            if depth == 0 {
+                words.push((std::mem::take(&mut word), assigned));
+            }
+            word.clear();
+        }
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            '=' if depth == 0 => assigned = true,
+            _ => {}
+        }
+    }
+
+    for (word, assigned) in words {
+        let word = word.as_str();
+        if language.data.contains(&word) {
+            return Block::Data;
+        }
+        if language.scopes.contains(&word) && !assigned {
+            return Block::Scope;
+        }
+        if language.functions.contains(&word) {
+            return Block::Body;
+        }
+    }
+
+    Block::Body
+}
+
+/// Where the `}` matching an opener is
+fn block_close(source: &Source, opener: &Opener) -> Opener {
+    let mut depth = 0;
+
+    for line in opener.line..source.len() {
+        let skip = if line == opener.line { opener.column } else { 0 };
+        for (column, c) in source.masked[line].char_indices().skip_while(|(column, _)| *column < skip) {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Opener { line, column };
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let line = source.len() - 1;
+    Opener { line, column: source.masked[line].len() }
+}
+
+/// Collapse a body into `{ … }`, returns the line and the last source line it covered
+///
+/// Chains like `} else {` are followed, so the whole statement ends up on one line.
+fn elide_body(source: &Source, mut opener: Opener) -> (String, usize) {
+    let mut text = source.code[opener.line][..=opener.column].to_string();
+
+    loop {
+        let close = block_close(source, &opener);
+        text.push_str(&format!(" {} ", ELIDED));
+
+        // See whether the rest of the closing line opens another body
+        let rest = &source.masked[close.line][close.column..];
+        let mut depth = 0;
+        let mut next = None;
+        for (offset, c) in rest.char_indices() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    if depth == 1 {
+                        next = Some(offset);
+                    }
+                }
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        next = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match next {
+            Some(offset) if depth > 0 => {
+                let column = close.column + offset;
+                text.push_str(&source.code[close.line][close.column..=column]);
+                opener = Opener { line: close.line, column };
+            }
+            _ => {
+                text.push_str(source.code[close.line][close.column..].trim_end());
+                return (text, close.line);
+            }
+        }
+    }
+}
+
+fn outline_indented(source: &Source) -> Vec<(usize, String)> {
+    let mut outline = Outline::default();
+    // Lines indented deeper than this belong to an elided body
+    let mut elided_below: Option<usize> = None;
+    let mut i = 0;
+
+    while i < source.len() {
+        if source.is_blank(i) {
+            if elided_below.is_none() && source.empty[i] {
+                outline.separate(i);
+            }
+            i += 1;
+            continue;
+        }
+
+        let indent = indentation(&source.code[i]).chars().count();
+        if let Some(body) = elided_below {
+            if indent > body {
+                i += 1;
+                continue;
+            }
+            elided_below = None;
+            // Blank lines at the end of the body still separate it from what's next
+            if i > 0 && source.empty[i - 1] {
+                outline.separate(i - 1);
+            }
+        }
+
+        // A logical line runs until brackets balance, past backslash continuations
+        let start = i;
+        let mut brackets = 0i32;
+        let mut end = i;
+        while end < source.len() {
+            for c in source.masked[end].chars() {
+                match c {
+                    '(' | '[' | '{' => brackets += 1,
+                    ')' | ']' | '}' => brackets -= 1,
+                    _ => {}
+                }
+            }
+            let continued = source.masked[end].trim_end().ends_with('\\');
+            if brackets <= 0 && !continued && !source.open_literal[end] {
+                break;
+            }
+            end += 1;
+        }
+        let end = end.min(source.len() - 1);
+
+        let masked = source.masked[start].trim_start();
+        let keyword = masked.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or_default();
+        let keyword = if keyword == "async" {
+            masked["async".len()..].trim_start().split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or_default()
+        } else {
+            keyword
+        };
+        let opens_block = source.masked[end].trim_end().ends_with(':');
+
+        if keyword == "class" || !opens_block {
+            // Class members get outlined in turn, simple statements stay
+            push_statement(&mut outline, source, start..end + 1);
+        } else {
+            // def, if, for, try...: keep the header, the body becomes `...`
+            for l in start..=end {
+                outline.push(l, &source.code[l]);
+            }
+            let body_indent = (end + 1..source.len())
+                .find(|&l| !source.is_blank(l))
+                .map(|l| indentation(&source.code[l]).to_string())
+                .filter(|body| body.chars().count() > indent)
+                .unwrap_or_else(|| format!("{}    ", indentation(&source.code[start])));
+            outline.push(end + 1, &format!("{}...", body_indent));
+            elided_below = Some(indent);
+        }
+
+        i = end + 1;
+    }
+
+    outline.finish()
+}
+
+impl CollectedFile {
+    /// Replace the content with its outline
+    ///
+    /// The content hash still describes the file on disk, so refreshing
+    /// notices changes the same way for outlines and full files.
+    pub(super) fn make_outline(&mut self, tokenizer: &Tokenizer) -> Result<(), AppError> {
+        let lines = outline_lines(&self.content, &self.language)
+            .ok_or_else(|| AppError::OutlineUnsupported(self.language.clone()))?;
+
+        let mut content = String::new();
+        let mut source_lines = Vec::with_capacity(lines.len());
+        for (number, line) in lines {
+            content.push_str(&line);
+            content.push('\n');
+            source_lines.push(number);
+        }
+
+        self.source_lines = Some(source_lines);
+        self.content = content;
+        self.token_count = tokenizer.count(&self.content);
+        self.mode = ContentMode::Outline;
+
+        Ok(())
+    }
+}
+
+impl App {
+    /// Collect the selected file as an outline, or back as the full file if it already is one
+    pub fn toggle_outline_current_file(&mut self) -> Result<(), AppError> {
+        let Some(item) = self.current_selection().cloned() else {
+            self.set_error_message("No file selected".to_string());
+            return Ok(());
+        };
+        if item.is_dir {
+            self.set_error_message("Cannot collect directories".to_string());
+            return Ok(());
+        }
+
+        let display_name = self.get_display_path(&item.path);
+        let existing = self.collected_files.iter().position(|f| f.path == item.path);
+        let to_outline = existing.is_none_or(|index| self.collected_files[index].mode == ContentMode::Full);
+
+        let mut file = match self.create_collected_file(&item) {
+            Ok(file) => file,
+            Err(e) => {
+                self.set_error_message(format!("{}: {}", display_name, self.collect_error_reason(&e)));
+                return Ok(());
+            }
+        };
+        let full_lines = file.content.lines().count();
+
+        if to_outline && let Err(e) = file.make_outline(&self.tokenizer) {
+            self.set_error_message(format!("{}: {}", display_name, e));
+            return Ok(());
+        }
+
+        let message = if to_outline {
+            format!(
+                "Collected {} as an outline ({} of {} lines, {})",
+                display_name,
+                file.content.lines().count(),
+                full_lines,
+                self.format_tokens(file.token_count)
+            )
+        } else {
+            format!("Collected all of {} ({})", display_name, self.format_tokens(file.token_count))
+        };
+
+        match existing {
+            Some(index) => {
+                file.pinned = self.collected_files[index].pinned;
+                self.collected_files[index] = file;
+            }
+            None => self.collected_files.push(file),
+        }
+        self.set_success_message(message);
+
+        Ok(())
+    }
+
+    /// Turn every collected file matching `select` into an outline
+    ///
+    /// Returns the files that stayed whole because their language has no outline support.
+    pub fn outline_collection(&mut self, select: impl Fn(&CollectedFile) -> bool) -> Vec<String> {
+        let mut unsupported = Vec::new();
+
+        for file in self.collected_files.iter_mut().filter(|f| f.mode == ContentMode::Full && select(f)) {
+            if file.make_outline(&self.tokenizer).is_err() {
+                unsupported.push(file.relative_path.clone());
+            }
+        }
+
+        unsupported
+    }
+}
+
+/// Words in front of a name that make it a definition (`fn parse`, `class Config`)
+const DEFINITION_KEYWORDS: &[&str] = &[
+    "fn", "struct", "enum", "union", "trait", "mod", "type", "const", "static", "macro_rules",
+    "def", "class", "func", "function", "interface", "namespace", "module", "record", "object", "fun",
+    "var", "val",
+];
+
+/// Words that can come before the name of a method without being its name
+const MODIFIERS: &[&str] = &[
+    "pub", "export", "default", "async", "static", "public", "private", "protected", "internal",
+    "abstract", "final", "override", "virtual", "unsafe", "extern", "inline", "sealed", "open",
+    "suspend", "declare", "readonly", "const",
+];
+
+/// Words that start a statement, so `if (ready) {` isn't a method called `if`
+const CONTROL_WORDS: &[&str] = &[
+    "if", "else", "for", "while", "switch", "catch", "return", "new", "await", "throw", "match",
+    "case", "do", "try", "using", "lock", "synchronized", "foreach", "elif", "with", "yield",
+    "typeof", "sizeof", "delete", "defer", "go", "select",
+];
+
+/// Line starts that continue the statement above, like a `where` clause or an
+/// opening brace on its own line
+const CONTINUATIONS: &[&str] = &["{", "where", "throws", ":", "->", "=>", ".", "=", "|", "&", "+", "?"];
+
+/// A word in a line of code, with its column and how deep in brackets it is
+struct Word<'a> {
+    column: usize,
+    text: &'a str,
+    depth: i32,
+}
+
+fn words(line: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+
+    for (column, c) in line.char_indices().chain(std::iter::once((line.len(), ' '))) {
+        if c.is_alphanumeric() || c == '_' || c == '$' {
+            start.get_or_insert(column);
+            continue;
+        }
+        if let Some(start) = start.take() {
+            words.push(Word { column: start, text: &line[start..column], depth });
+        }
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    words
+}
+
+/// Which word names the type of an `impl` line: the one after `for`, or else
+/// the first after `impl` that isn't a generic parameter
+fn impl_target(line: &str, words: &[Word]) -> Option<usize> {
+    let impl_word = words.iter().position(|w| w.text == "impl")?;
+    let after = words.iter().position(|w| w.text == "for").unwrap_or(impl_word) + 1;
+    let from = words[impl_word].column;
+
+    (after..words.len()).find(|&i| {
+        let between = &line[from..words[i].column];
+        words[i].text != "dyn" && between.matches('<').count() <= between.matches('>').count()
+    })
+}
+
+/// How a statement ends
+enum StatementEnd {
+    /// It opens a body at this `{`
+    Body(Opener),
+    /// It ends on this line without one
+    Line(usize),
+}
+
+/// Follow a statement from `line`, `column` to its body or its end
+///
+/// Brackets are skipped over. A line that ends with the brackets balanced
+/// ends the statement unless the line after it obviously carries on.
+fn statement_end(source: &Source, line: usize, column: usize) -> StatementEnd {
+    let mut depth = 0;
+
+    for current in line..source.len() {
+        let skip = if current == line { column } else { 0 };
+        let masked = &source.masked[current];
+        for (column, c) in masked.char_indices().skip_while(|(column, _)| *column < skip) {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                '{' if depth <= 0 => return StatementEnd::Body(Opener { line: current, column }),
+                ';' if depth <= 0 => return StatementEnd::Line(current),
+                _ => {}
+            }
+        }
+
+        let trimmed = masked.trim_end();
+        if depth > 0 || source.open_literal[current] || trimmed.ends_with([',', '=', '\\', '(', '<']) || trimmed.ends_with("where") {
+            continue;
+        }
+        let carries_on = source.masked.get(current + 1).is_some_and(|next| {
+            let next = next.trim_start();
+            CONTINUATIONS.iter().any(|start| next.starts_with(start))
+        });
+        if !carries_on {
+            return StatementEnd::Line(current);
+        }
+    }
+
+    StatementEnd::Line(source.len() - 1)
+}
+
+/// Whether the word at `index` is the name of something defined on this line
+fn defines(source: &Source, line: usize, words: &[Word], index: usize) -> bool {
+    let masked = &source.masked[line];
+    let word = &words[index];
+    if word.depth != 0 || masked[..word.column].trim_end().ends_with('.') {
+        return false;
+    }
+
+    let previous = index.checked_sub(1).map(|i| words[i].text);
+    if previous.is_some_and(|p| DEFINITION_KEYWORDS.contains(&p)) {
+        return true;
+    }
+    if words.iter().any(|w| w.text == "impl") {
+        return impl_target(masked, words) == Some(index);
+    }
+
+    // Methods without a keyword: a name, its parameters, then a body
+    let after = &masked[word.column + word.text.len()..];
+    let first = words.iter().find(|w| !MODIFIERS.contains(&w.text)).map(|w| w.text);
+    after.trim_start().starts_with('(')
+        && !MODIFIERS.contains(&word.text)
+        && !first.is_some_and(|w| CONTROL_WORDS.contains(&w))
+        && previous != Some("new")
+        && matches!(statement_end(source, line, word.column), StatementEnd::Body(_))
+}
+
+/// The last line of the definition whose name ends at `line`, `column`
+fn definition_end(source: &Source, style: &OutlineStyle, line: usize, column: usize) -> usize {
+    if let OutlineStyle::Braces(_) = style {
+        return match statement_end(source, line, column) {
+            StatementEnd::Body(opener) => block_close(source, &opener).line,
+            StatementEnd::Line(end) => end,
+        };
+    }
+
+    // Python: the header runs until brackets balance, then the body is indented deeper
+    let mut header_end = line;
+    let mut brackets = 0i32;
+    while header_end < source.len() {
+        for c in source.masked[header_end].chars() {
+            match c {
+                '(' | '[' | '{' => brackets += 1,
+                ')' | ']' | '}' => brackets -= 1,
+                _ => {}
+            }
+        }
+        let continued = source.masked[header_end].trim_end().ends_with('\\');
+        if brackets <= 0 && !continued && !source.open_literal[header_end] {
+            break;
+        }
+        header_end += 1;
+    }
+    let header_end = header_end.min(source.len() - 1);
+    if !source.masked[header_end].trim_end().ends_with(':') {
+        return header_end;
+    }
+
+    let indent = indentation(&source.code[line]).chars().count();
+    let body_end = (header_end + 1..source.len())
+        .find(|&l| !source.is_blank(l) && indentation(&source.code[l]).chars().count() <= indent)
+        .unwrap_or(source.len());
+    (header_end + 1..body_end)
+        .rev()
+        .find(|&l| !source.code[l].trim().is_empty())
+        .unwrap_or(header_end)
+}
+
+/// The name defined on a line and the last line of its definition
+fn definition_on<'a>(source: &'a Source, style: &OutlineStyle, line: usize) -> Option<(&'a str, usize)> {
+    let words = words(&source.masked[line]);
+    let index = (0..words.len()).find(|&i| defines(source, line, &words, i))?;
+    let word = &words[index];
+    let end = definition_end(source, style, line, word.column + word.text.len());
+    Some((word.text, end))
+}
+
+/// Attributes, decorators and doc comments directly above a definition
+///
+/// Returns the first line that belongs to the definition starting on `line`.
+fn definition_start(source: &Source, line: usize) -> usize {
+    let mut start = line;
+    while start > 0 {
+        let above = start - 1;
+        let code = source.masked[above].trim_start();
+        let leading = source.code[above].trim().is_empty() || code.starts_with("#[") || code.starts_with(['@', '[']);
+        if source.empty[above] || !leading {
+            break;
+        }
+        start = above;
+    }
+    start
+}
+
+/// Find a definition by its path (`Config`, `Config::load`) within some lines
+///
+/// Returns its first and last line, 0-based.
+fn find_definition(source: &Source, style: &OutlineStyle, lines: Range<usize>, path: &[&str]) -> Option<(usize, usize)> {
+    let (name, inner) = path.split_first()?;
+
+    for line in lines.start..lines.end.min(source.len()) {
+        let words = words(&source.masked[line]);
+        let Some(index) = (0..words.len()).find(|&i| words[i].text == *name && defines(source, line, &words, i)) else {
+            continue;
+        };
+        let word = &words[index];
+        let end = definition_end(source, style, line, word.column + word.text.len());
+
+        if inner.is_empty() {
+            return Some((definition_start(source, line), end));
+        }
+        if let Some(found) = find_definition(source, style, line + 1..end + 1, inner) {
+            return Some(found);
+        }
+    }
+
+    None
+}
+
+/// The lines (1-based, inclusive) of the definition of `symbol`
+///
+/// Nested names are written `Outer::inner` or `Outer.inner`. Attributes,
+/// decorators and doc comments right above the definition are included.
+pub fn symbol_lines(content: &str, language: &str, symbol: &str) -> Result<(usize, usize), AppError> {
+    let style = style_for(language).ok_or_else(|| AppError::SymbolsUnsupported(language.to_string()))?;
+    let source = Source::new(content, language);
+    let path: Vec<&str> = symbol.split("::").flat_map(|part| part.split('.')).filter(|p| !p.is_empty()).collect();
+
+    if source.len() == 0 || path.is_empty() {
+        return Err(AppError::SymbolNotFound(symbol.to_string()));
+    }
+
+    find_definition(&source, &style, 0..source.len(), &path)
+        .map(|(start, end)| (start + 1, end + 1))
+        .ok_or_else(|| AppError::SymbolNotFound(symbol.to_string()))
+}
+
+/// The innermost definition around a line (0-based), e.g. the method the
+/// cursor is in
+///
+/// Returns its full name (`Config::load`) and its first and last line, 1-based.
+pub fn symbol_at(content: &str, language: &str, line: usize) -> Result<(String, usize, usize), AppError> {
+    let style = style_for(language).ok_or_else(|| AppError::SymbolsUnsupported(language.to_string()))?;
+    let source = Source::new(content, language);
+    let not_found = || AppError::SymbolNotFound(format!("anything around line {}", line + 1));
+    if line >= source.len() {
+        return Err(not_found());
+    }
+
+    // On an attribute or doc comment, look at what it's attached to
+    let mut from = line;
+    while from + 1 < source.len() && definition_start(&source, from + 1) <= line && definition_on(&source, &style, from).is_none() {
+        from += 1;
+    }
+
+    let mut names = Vec::new();
+    let mut found: Option<(usize, usize)> = None;
+    for current in (0..=from).rev() {
+        let Some((name, end)) = definition_on(&source, &style, current) else {
+            continue;
+        };
+        if end < from {
+            continue;
+        }
+        names.push(name);
+        found.get_or_insert((definition_start(&source, current), end));
+    }
+
+    let (start, end) = found.ok_or_else(not_found)?;
+    let separator = if matches!(language, "rust" | "cpp" | "c") { "::" } else { "." };
+    names.reverse();
+    Ok((names.join(separator), start + 1, end + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outline(content: &str, language: &str) -> String {
+        outline_lines(content, language)
+            .expect("language has outlines")
+            .into_iter()
+            .map(|(number, line)| format!("{}: {}\n", number, line))
+            .collect()
+    }
+
+    const RUST: &str = "use std::fmt;\n\n/// Config\npub struct Config {\n    pub name: String,\n}\n\nimpl Config {\n    pub fn load(path: &str) -> Result<Config, Error> {\n        let text = \"{ not a brace }\";\n        parse(text)\n    }\n}\n\nconst MAX: usize = 10;\n";
+
+    #[test]
+    fn rust_bodies_are_elided_and_types_kept() {
+        assert_eq!(
+            outline(RUST, "rust"),
+            concat!(
+                "1: use std::fmt;\n",
+                "2: \n",
+                "4: pub struct Config {\n",
+                "5:     pub name: String,\n",
+                "6: }\n",
+                "7: \n",
+                "8: impl Config {\n",
+                "9:     pub fn load(path: &str) -> Result<Config, Error> { … }\n",
+                "13: }\n",
+                "14: \n",
+                "15: const MAX: usize = 10;\n",
+            )
+        );
+    }
+
+    #[test]
+    fn python_bodies_and_docstrings_are_dropped() {
+        let python = "import os\n\nclass Greeter:\n    \"\"\"Says hi\"\"\"\n\n    def greet(self, name):\n        return f\"hi {name}:\"\n\ndef main():\n    Greeter().greet(\"x\")\n";
+        assert_eq!(
+            outline(python, "python"),
+            concat!(
+                "1: import os\n",
+                "2: \n",
+                "3: class Greeter:\n",
+                "5: \n",
+                "6:     def greet(self, name):\n",
+                "7:         ...\n",
+                "8: \n",
+                "9: def main():\n",
+                "10:     ...\n",
+            )
+        );
+    }
+
+    #[test]
+    fn unsupported_languages_have_no_outline() {
+        assert!(outline_lines("a: 1\n", "yaml").is_none());
+    }
+
+    #[test]
+    fn symbols_are_found_by_path() {
+        assert_eq!(symbol_lines(RUST, "rust", "Config::load").unwrap(), (9, 12));
+        assert_eq!(symbol_lines(RUST, "rust", "Config").unwrap(), (3, 6));
+        assert!(symbol_lines(RUST, "rust", "Config::save").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! Pattern add for the RepoViewer application.
+//!
+//! More often than not we know the files we want by name rather than by where
+//! they are: every `*handler*.rs`, the migrations from 2024. `/` opens a prompt
+//! that takes a glob or a regex (Tab switches) and lists the matching files
+//! under the git root (or the start directory) as it's typed. Enter adds all
+//! of them to the collection, Ctrl+D removes all of them.
+//!
+//! Globs follow the same rule as the recursive add filters: without a `/` they
+//! match names at any depth, with one they match paths from the root
+//! (`migrations/2024*`). Regexes are searched for anywhere in the relative path.
+//! Files are listed once when the prompt opens, with the file list's hidden and
+//! gitignore settings, so typing doesn't walk the repository again.
+
+use super::{App, FileItem, collection::AddOutcome, headless::anywhere_matcher};
+use regex::Regex;
+use std::{collections::HashSet, fs, path::PathBuf};
+use tui_input::Input;
+
+/// How the prompt reads what's typed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PatternKind {
+    #[default]
+    Glob,
+    Regex,
+}
+
+impl PatternKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PatternKind::Glob => "glob",
+            PatternKind::Regex => "regex",
+        }
+    }
+
+    fn toggled(self) -> Self {
+        match self {
+            PatternKind::Glob => PatternKind::Regex,
+            PatternKind::Regex => PatternKind::Glob,
+        }
+    }
+}
+
+/// A file the prompt can match
+#[derive(Debug, Clone)]
+pub struct PatternCandidate {
+    pub path: PathBuf,
+    /// Path from the root with `/` separators, what patterns are matched against
+    pub relative: String,
+    pub size: u64,
+}
+
+/// The prompt `/` opens, with the files matching what's typed so far
+#[derive(Debug, Clone)]
+pub struct PatternPrompt {
+    pub input: Input,
+    pub kind: PatternKind,
+    pub root: PathBuf,
+    pub candidates: Vec<PatternCandidate>,
+    /// Indexes into `candidates` of the files matching the pattern
+    pub matches: Vec<usize>,
+    /// Why the pattern doesn't compile, while it doesn't
+    pub error: Option<String>,
+    /// First match shown
+    pub scroll: usize,
+}
+
+impl PatternPrompt {
+    /// Match the pattern again after it or the kind changed
+    pub fn update_matches(&mut self) {
+        self.scroll = 0;
+        self.matches.clear();
+        self.error = None;
+
+        let pattern = self.input.value().trim();
+        if pattern.is_empty() {
+            return;
+        }
+
+        let is_match: Box<dyn Fn(&str) -> bool> = match self.kind {
+            PatternKind::Glob => match anywhere_matcher([pattern]) {
+                Ok(matcher) => Box::new(move |path| matcher.is_match(path)),
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    return;
+                }
+            },
+            PatternKind::Regex => match Regex::new(pattern) {
+                Ok(regex) => Box::new(move |path| regex.is_match(path)),
+                Err(e) => {
+                    // The regex crate's errors span several lines
+                    let message = e.to_string();
+                    let message = message.lines().last().unwrap_or_default().trim().trim_start_matches("error: ").to_string();
+                    self.error = Some(format!("Invalid pattern: {}", message));
+                    return;
+                }
+            },
+        };
+
+        self.matches = self.candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, candidate)| is_match(&candidate.relative))
+            .map(|(i, _)| i)
+            .collect();
+    }
+
+    /// Switch between glob and regex
+    pub fn toggle_kind(&mut self) {
+        self.kind = self.kind.toggled();
+        self.update_matches();
+    }
+
+    pub fn matched(&self) -> impl Iterator<Item = &PatternCandidate> {
+        self.matches.iter().map(|&i| &self.candidates[i])
+    }
+
+    /// Size of the matching files on disk
+    pub fn matched_size(&self) -> u64 {
+        self.matched().map(|candidate| candidate.size).sum()
+    }
+
+    pub fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta).min(self.matches.len().saturating_sub(1));
+    }
+}
+
+impl App {
+    /// Open the pattern prompt on every visible file under the git root or start directory
+    pub fn open_pattern_prompt(&mut self) {
+        let root = self.git_root.clone().unwrap_or_else(|| self.start_dir.clone());
+
+        let mut paths = Vec::new();
+        self.walk_visible_files(&root, &mut paths);
+        let candidates = paths
+            .into_iter()
+            .map(|path| PatternCandidate {
+                relative: path.strip_prefix(&root).unwrap_or(&path).to_string_lossy().replace('\\', "/"),
+                size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                path,
+            })
+            .collect();
+
+        self.pattern_prompt = Some(PatternPrompt {
+            input: Input::default(),
+            kind: PatternKind::default(),
+            root,
+            candidates,
+            matches: Vec::new(),
+            error: None,
+            scroll: 0,
+        });
+    }
+
+    /// Add every file matching the prompt's pattern and close it
+    ///
+    /// Files already collected are updated and keep their settings, files that
+    /// can't be collected are counted as skipped.
+    pub fn add_pattern_matches(&mut self) {
+        let Some(prompt) = self.matched_prompt() else {
+            return;
+        };
+
+        let initial_health = self.collection_health();
+        let mut files = Vec::new();
+        let mut skipped_files = Vec::new();
+        for candidate in prompt.matched() {
+            let name = candidate.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let item = FileItem {
+                is_dir: false,
+                is_symlink: candidate.path.is_symlink(),
+                is_hidden: self.is_hidden(&candidate.path, &name),
+                path: candidate.path.clone(),
+                name,
+            };
+            match self.create_collected_file(&item) {
+                Ok(file) => files.push(file),
+                Err(e) => skipped_files.push(format!("{}: {}", candidate.relative, self.collect_error_reason(&e))),
+            }
+        }
+        let (added, updated) = self.add_or_update_files(files);
+
+        let outcome = AddOutcome {
+            added,
+            updated,
+            skipped: skipped_files.len(),
+            skipped_files,
+            errors: Vec::new(),
+        };
+        let source = format!("matching {}", prompt.input.value().trim());
+        self.set_add_message(&source, outcome, initial_health);
+    }
+
+    /// Remove every file matching the prompt's pattern from the collection and close it
+    pub fn remove_pattern_matches(&mut self) {
+        let Some(prompt) = self.matched_prompt() else {
+            return;
+        };
+
+        let matched: HashSet<&PathBuf> = prompt.matched().map(|candidate| &candidate.path).collect();
+        let before = self.collected_files.len();
+        self.collected_files.retain(|file| !matched.contains(&file.path));
+
+        self.set_success_message(format!(
+            "Removed {} files matching {} - Total: {} files",
+            before - self.collected_files.len(),
+            prompt.input.value().trim(),
+            self.collected_files.len()
+        ));
+    }
+
+    /// Take the prompt if its pattern matches anything, otherwise leave it open and say why
+    fn matched_prompt(&mut self) -> Option<PatternPrompt> {
+        let prompt = self.pattern_prompt.as_ref()?;
+        if let Some(error) = &prompt.error {
+            self.set_error_message(error.clone());
+            return None;
+        }
+        if prompt.matches.is_empty() {
+            self.set_error_message("No files match the pattern".to_string());
+            return None;
+        }
+        self.pattern_prompt.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let files = [
+            ("src/handlers/user_handler.rs", "pub fn user() {}\n"),
+            ("src/handlers/admin_handler.rs", "pub fn admin() {}\n"),
+            ("src/main.rs", "fn main() {}\n"),
+            ("migrations/2024_01_init.sql", "create table users ();\n"),
+            ("migrations/2023_12_seed.sql", "insert into users values ();\n"),
+        ];
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        app.open_pattern_prompt();
+        (dir, app)
+    }
+
+    /// Type a pattern into the open prompt and list what it matches
+    fn matching(app: &mut App, pattern: &str) -> Vec<String> {
+        let prompt = app.pattern_prompt.as_mut().unwrap();
+        prompt.input = Input::new(pattern.to_string());
+        prompt.update_matches();
+        let mut matched: Vec<String> = prompt.matched().map(|c| c.relative.clone()).collect();
+        matched.sort();
+        matched
+    }
+
+    #[test]
+    fn globs_match_names_anywhere_and_paths_from_the_root() {
+        let (_dir, mut app) = project();
+        assert_eq!(
+            matching(&mut app, "*handler*.rs"),
+            ["src/handlers/admin_handler.rs", "src/handlers/user_handler.rs"]
+        );
+        assert_eq!(matching(&mut app, "migrations/2024*"), ["migrations/2024_01_init.sql"]);
+        assert_eq!(matching(&mut app, "2024*"), ["migrations/2024_01_init.sql"]);
+        assert!(matching(&mut app, "src/2024*").is_empty());
+    }
+
+    #[test]
+    fn regexes_search_the_whole_path() {
+        let (_dir, mut app) = project();
+        app.pattern_prompt.as_mut().unwrap().toggle_kind();
+        assert_eq!(matching(&mut app, r"^src/[a-z]+\.rs$"), ["src/main.rs"]);
+        assert_eq!(matching(&mut app, "2023|admin").len(), 2);
+
+        matching(&mut app, "(unclosed");
+        let error = app.pattern_prompt.as_ref().unwrap().error.clone();
+        assert!(error.is_some_and(|e| e.starts_with("Invalid pattern")));
+    }
+
+    #[test]
+    fn adds_and_removes_every_match() {
+        let (_dir, mut app) = project();
+        matching(&mut app, "*.rs");
+        app.add_pattern_matches();
+
+        assert_eq!(app.collected_files.len(), 3);
+        assert!(app.pattern_prompt.is_none());
+        let message = app.message.clone().unwrap();
+        assert!(message.text.starts_with("Added 3 files matching *.rs, updated 0, skipped 0"), "{}", message.text);
+
+        app.open_pattern_prompt();
+        matching(&mut app, "*handler*");
+        app.remove_pattern_matches();
+
+        let left: Vec<&str> = app.collected_files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(left, ["src/main.rs"]);
+        assert!(app.message.unwrap().text.starts_with("Removed 2 files matching *handler*"));
+    }
+
+    #[test]
+    fn no_match_keeps_the_prompt_open() {
+        let (_dir, mut app) = project();
+        matching(&mut app, "*.toml");
+        app.add_pattern_matches();
+
+        assert!(app.pattern_prompt.is_some());
+        assert!(app.collected_files.is_empty());
+    }
+}
@@ -0,0 +1,495 @@
+//! Line range collection for the RepoViewer application.
+//!
+//! Often only one function of a 3,000-line file matters. A file can be
+//! collected as one or more ranges of lines instead: picked in a preview of
+//! the file (`R`), given on the command line as `src/lib.rs:120-180,300-340`,
+//! or named by symbol as `src/lib.rs#Config::load`. The lines in between are
+//! replaced by a marker in the file's own comment syntax so the model knows
+//! they exist:
+//!
+//! ```text
+//! // … lines 1-119 elided …
+//! ```
+//!
+//! Exports keep the line numbers of the file on disk. When a collected file
+//! changes, refreshing re-anchors each range: to the symbol it was picked by,
+//! to the same block of lines wherever it moved, or else to the nearest
+//! matches of its first and last lines.
+
+use super::{
+    App, CollectedFile, ContentMode,
+    outline::{symbol_at, symbol_lines},
+    transform::comment_delimiters,
+};
+use crate::{app_error::AppError, tokens::Tokenizer};
+use serde::Serialize;
+use std::fmt;
+
+/// End of every elision marker, how markers are told apart from the file's own comments
+const ELIDED: &str = "elided …";
+
+/// Lines moved by PgUp/PgDn in the picker
+const PAGE: isize = 20;
+
+/// A run of lines of a file, 1-based and inclusive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+    /// The symbol the range was picked by, followed when the file changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+}
+
+impl LineRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        LineRange { start: start.min(end), end: start.max(end), symbol: None }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.end).contains(&line)
+    }
+}
+
+impl fmt::Display for LineRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+/// Parse a list of line ranges like `10-20,40-60,75`
+pub fn parse_ranges(spec: &str) -> Result<Vec<LineRange>, AppError> {
+    let mut ranges = Vec::new();
+
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (start, end) = part.split_once('-').unwrap_or((part, part));
+        let number = |n: &str| n.trim().parse::<usize>().ok().filter(|&n| n > 0);
+        match (number(start), number(end)) {
+            (Some(start), Some(end)) if start <= end => ranges.push(LineRange::new(start, end)),
+            _ => return Err(AppError::InvalidRange(format!("'{}' (expected lines like 10-20)", part))),
+        }
+    }
+
+    if ranges.is_empty() {
+        return Err(AppError::InvalidRange(format!("'{}' names no lines", spec)));
+    }
+
+    Ok(merge_ranges(ranges))
+}
+
+/// Sort ranges and join the ones that overlap
+///
+/// Ranges that only touch are joined too, unless they were picked by
+/// different symbols. A range swallowed by another leaves that one's symbol
+/// alone, a range that grows by joining keeps its symbol only if both had the same.
+fn merge_ranges(mut ranges: Vec<LineRange>) -> Vec<LineRange> {
+    ranges.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+
+    let mut merged: Vec<LineRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end || (range.start == last.end + 1 && range.symbol == last.symbol) => {
+                if range.end > last.end {
+                    last.end = range.end;
+                    if range.symbol != last.symbol {
+                        last.symbol = None;
+                    }
+                }
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+}
+
+/// Short list of ranges for labels, e.g. `10-20, 40-60`
+pub fn ranges_label(ranges: &[LineRange]) -> String {
+    ranges.iter().map(LineRange::to_string).collect::<Vec<_>>().join(", ")
+}
+
+/// The line standing in for lines left out, in the language's comment syntax
+fn elision_marker(language: &str, start: usize, end: usize) -> String {
+    let lines = if start == end {
+        format!("line {}", start)
+    } else {
+        format!("lines {}-{}", start, end)
+    };
+
+    match comment_delimiters(language) {
+        Some((open, "")) => format!("{} … {} {}", open, lines, ELIDED),
+        Some((open, close)) => format!("{} … {} {} {}", open, lines, ELIDED, close),
+        None => format!("… {} {}", lines, ELIDED),
+    }
+}
+
+/// Whether a comment is one of our elision markers, so stripping comments leaves it in
+pub(super) fn is_elision_marker(comment: &str) -> bool {
+    !comment.contains('\n') && comment.contains("… line") && comment.contains(ELIDED)
+}
+
+/// Where `block` appears in `lines`, nearest to line `near`, as a 1-based line
+fn find_block(lines: &[&str], block: &[&str], near: usize) -> Option<usize> {
+    if block.is_empty() || block.len() > lines.len() {
+        return None;
+    }
+
+    (0..=lines.len() - block.len())
+        .filter(|&i| lines[i..i + block.len()] == *block)
+        .min_by_key(|&i| (i + 1).abs_diff(near))
+        .map(|i| i + 1)
+}
+
+/// Where a (non-blank) line appears nearest to `near`, only looking from `from` on
+fn find_line(lines: &[&str], line: &str, near: usize, from: usize) -> Option<usize> {
+    if line.trim().is_empty() {
+        return None;
+    }
+
+    (from.max(1)..=lines.len())
+        .filter(|&number| lines[number - 1] == line)
+        .min_by_key(|number| number.abs_diff(near))
+}
+
+impl CollectedFile {
+    /// Line in the file on disk that a content line (1-based) came from
+    pub fn source_line(&self, line: usize) -> usize {
+        self.source_lines
+            .as_ref()
+            .and_then(|lines| lines.get(line - 1).copied())
+            .unwrap_or(line)
+    }
+
+    /// Cut the content down to some ranges of its lines
+    ///
+    /// The content has to be the whole file. Ranges running past its end are
+    /// cut short, and like outlines the content hash keeps describing the file
+    /// on disk so refreshing notices changes the same way.
+    pub(super) fn select_ranges(&mut self, ranges: Vec<LineRange>, tokenizer: &Tokenizer) -> Result<(), AppError> {
+        let lines: Vec<&str> = self.content.split_inclusive('\n').collect();
+        let total = lines.len();
+
+        let ranges = merge_ranges(
+            ranges
+                .into_iter()
+                .filter(|range| range.start <= total)
+                .map(|mut range| {
+                    range.end = range.end.min(total);
+                    range
+                })
+                .collect(),
+        );
+        if ranges.is_empty() {
+            return Err(AppError::InvalidRange(format!("the file only has {} lines", total)));
+        }
+
+        // Each line of the new content with the line it came from, a marker
+        // stands at the first line it replaces
+        let mut kept: Vec<(usize, String)> = Vec::new();
+        let mut next = 1;
+        for range in &ranges {
+            if range.start > next {
+                kept.push((next, elision_marker(&self.language, next, range.start - 1) + "\n"));
+            }
+            for number in range.start..=range.end {
+                kept.push((number, lines[number - 1].to_string()));
+            }
+            next = range.end + 1;
+        }
+        if next <= total {
+            kept.push((next, elision_marker(&self.language, next, total) + "\n"));
+        }
+
+        let source_lines = kept.iter().map(|(number, _)| *number).collect();
+        let content = kept.into_iter().map(|(_, line)| line).collect();
+
+        self.source_lines = Some(source_lines);
+        self.content = content;
+        self.token_count = tokenizer.count(&self.content);
+        self.mode = ContentMode::Ranges;
+        self.ranges = ranges;
+
+        Ok(())
+    }
+
+    /// The collected lines of one of the ranges
+    fn range_lines(&self, range: &LineRange) -> Vec<&str> {
+        self.content
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| range.contains(self.source_line(i + 1)))
+            .map(|(_, line)| line)
+            .collect()
+    }
+
+    /// Take the ranges of a previous snapshot over to this one, wherever they moved
+    ///
+    /// A range picked by symbol follows the symbol. Otherwise it's looked for
+    /// as the same block of lines nearest where it was, then as its first and
+    /// last lines. A range nothing finds keeps its numbers. If the file got too
+    /// short for any of them it stays whole.
+    pub(super) fn reanchor_ranges(&mut self, previous: &CollectedFile, tokenizer: &Tokenizer) {
+        let lines: Vec<&str> = self.content.lines().collect();
+
+        let ranges = previous
+            .ranges
+            .iter()
+            .map(|range| {
+                if let Some(symbol) = &range.symbol
+                    && let Ok((start, end)) = symbol_lines(&self.content, &self.language, symbol)
+                {
+                    return LineRange { start, end, symbol: Some(symbol.clone()) };
+                }
+
+                let block = previous.range_lines(range);
+                let (start, end) = match find_block(&lines, &block, range.start) {
+                    Some(start) => (start, start + block.len() - 1),
+                    None => {
+                        let first = block.first().and_then(|line| find_line(&lines, line, range.start, 1));
+                        let from = first.unwrap_or(1);
+                        let last = block.last().and_then(|line| find_line(&lines, line, from + block.len() - 1, from));
+                        match (first, last) {
+                            (Some(start), Some(end)) => (start, end),
+                            (Some(start), None) => (start, start + range.len() - 1),
+                            (None, Some(end)) => (end.saturating_sub(range.len() - 1).max(1), end),
+                            (None, None) => (range.start, range.end),
+                        }
+                    }
+                };
+                LineRange { start, end, symbol: range.symbol.clone() }
+            })
+            .collect();
+
+        let _ = self.select_ranges(ranges, tokenizer);
+    }
+}
+
+/// The preview `R` opens to pick which lines of a file to collect
+#[derive(Debug, Clone)]
+pub struct RangePicker {
+    /// Snapshot of the whole file
+    pub file: CollectedFile,
+    pub lines: Vec<String>,
+    /// Line under the cursor, 0-based
+    pub cursor: usize,
+    /// Where the selection being made started, 0-based
+    pub anchor: Option<usize>,
+    pub ranges: Vec<LineRange>,
+}
+
+impl RangePicker {
+    pub fn move_cursor(&mut self, delta: isize) {
+        self.cursor = self.cursor.saturating_add_signed(delta).min(self.lines.len().saturating_sub(1));
+    }
+
+    /// Move a page up (`-1`) or down (`1`)
+    pub fn page(&mut self, direction: isize) {
+        self.move_cursor(direction * PAGE);
+    }
+
+    /// Start a selection at the cursor, or end the one in progress there
+    pub fn toggle_selection(&mut self) {
+        match self.anchor.take() {
+            Some(anchor) => self.add(LineRange::new(anchor + 1, self.cursor + 1)),
+            None => self.anchor = Some(self.cursor),
+        }
+    }
+
+    /// Drop the selection in progress, or else the range under the cursor
+    pub fn remove_at_cursor(&mut self) {
+        if self.anchor.take().is_none() {
+            self.ranges.retain(|range| !range.contains(self.cursor + 1));
+        }
+    }
+
+    fn add(&mut self, range: LineRange) {
+        let mut ranges = std::mem::take(&mut self.ranges);
+        ranges.push(range);
+        self.ranges = merge_ranges(ranges);
+    }
+
+    /// Whether a line (0-based) is in one of the picked ranges
+    pub fn is_selected(&self, line: usize) -> bool {
+        self.ranges.iter().any(|range| range.contains(line + 1))
+    }
+
+    /// Whether a line (0-based) is in the selection being made
+    pub fn is_marking(&self, line: usize) -> bool {
+        self.anchor.is_some_and(|anchor| (anchor.min(self.cursor)..=anchor.max(self.cursor)).contains(&line))
+    }
+
+    /// How many lines the picked ranges cover
+    pub fn selected_lines(&self) -> usize {
+        self.ranges.iter().map(LineRange::len).sum()
+    }
+}
+
+impl App {
+    /// Open the line picker on the selected file
+    ///
+    /// If the file is already collected as ranges they start out picked.
+    pub fn open_range_picker(&mut self) {
+        let Some(item) = self.current_selection().cloned() else {
+            self.set_error_message("No file selected".to_string());
+            return;
+        };
+        if item.is_dir {
+            self.set_error_message("Cannot collect directories".to_string());
+            return;
+        }
+
+        let file = match self.create_collected_file(&item) {
+            Ok(file) => file,
+            Err(e) => {
+                let display_name = self.get_display_path(&item.path);
+                self.set_error_message(format!("{}: {}", display_name, self.collect_error_reason(&e)));
+                return;
+            }
+        };
+
+        let ranges = self.collected_files
+            .iter()
+            .find(|f| f.path == file.path && f.mode == ContentMode::Ranges)
+            .map(|f| f.ranges.clone())
+            .unwrap_or_default();
+        let cursor = ranges.first().map(|range| range.start - 1).unwrap_or(0);
+        let lines = file.content.lines().map(|line| line.replace('\t', "    ")).collect();
+
+        self.range_picker = Some(RangePicker { file, lines, cursor, anchor: None, ranges });
+    }
+
+    /// Pick the definition the picker's cursor is in, e.g. the whole function
+    pub fn pick_symbol_at_cursor(&mut self) {
+        let Some(picker) = self.range_picker.as_mut() else {
+            return;
+        };
+
+        match symbol_at(&picker.file.content, &picker.file.language, picker.cursor) {
+            Ok((symbol, start, end)) => {
+                picker.anchor = None;
+                picker.cursor = start - 1;
+                picker.add(LineRange { start, end, symbol: Some(symbol.clone()) });
+                self.set_success_message(format!("Picked {} (lines {}-{})", symbol, start, end));
+            }
+            Err(e) => self.set_error_message(e.to_string()),
+        }
+    }
+
+    /// Collect the lines picked in the picker and close it
+    ///
+    /// With nothing picked the whole file is collected.
+    pub fn confirm_range_picker(&mut self) -> Result<(), AppError> {
+        let Some(mut picker) = self.range_picker.take() else {
+            return Ok(());
+        };
+        if picker.anchor.is_some() {
+            picker.toggle_selection();
+        }
+
+        let display_name = self.get_display_path(&picker.file.path);
+        let mut file = picker.file;
+        let message = if picker.ranges.is_empty() {
+            format!("Collected all of {} ({})", display_name, self.format_tokens(file.token_count))
+        } else {
+            let selected = picker.ranges.iter().map(LineRange::len).sum::<usize>();
+            file.select_ranges(picker.ranges, &self.tokenizer)?;
+            format!(
+                "Collected lines {} of {} ({} of {} lines, {})",
+                ranges_label(&file.ranges),
+                display_name,
+                selected,
+                picker.lines.len(),
+                self.format_tokens(file.token_count)
+            )
+        };
+
+        match self.collected_files.iter().position(|f| f.path == file.path) {
+            Some(index) => {
+                file.pinned = self.collected_files[index].pinned;
+                self.collected_files[index] = file;
+            }
+            None => self.collected_files.push(file),
+        }
+        self.set_success_message(message);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn spans(ranges: &[LineRange]) -> Vec<(usize, usize)> {
+        ranges.iter().map(|range| (range.start, range.end)).collect()
+    }
+
+    #[test]
+    fn parses_sorts_and_merges_ranges() {
+        let ranges = parse_ranges("40-60, 10-20,15-25,26-30,75").unwrap();
+        assert_eq!(spans(&ranges), [(10, 30), (40, 60), (75, 75)]);
+    }
+
+    #[test]
+    fn rejects_bad_ranges() {
+        for spec in ["", ",", "0-3", "5-2", "a-b", "3-"] {
+            assert!(parse_ranges(spec).is_err(), "{:?} should be rejected", spec);
+        }
+    }
+
+    /// An app collecting `a.py` (written with `content`) as `spec`
+    fn collected(dir: &std::path::Path, content: &str, spec: &str) -> App {
+        fs::write(dir.join("a.py"), content).unwrap();
+        let mut app = App::new(dir.to_path_buf()).unwrap();
+        app.collect_inputs(&[format!("a.py{}", spec)]).unwrap();
+        app
+    }
+
+    #[test]
+    fn selected_ranges_keep_source_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let content: String = (1..=10).map(|i| format!("x{} = {}\n", i, i)).collect();
+        let app = collected(dir.path(), &content, ":3-4");
+        let file = &app.collected_files[0];
+
+        assert_eq!(file.content, "# … lines 1-2 elided …\nx3 = 3\nx4 = 4\n# … lines 5-10 elided …\n");
+        assert_eq!(file.source_line(2), 3);
+        assert_eq!(file.source_line(4), 5);
+    }
+
+    #[test]
+    fn ranges_follow_moved_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "a = 1\nb = 2\ndef f():\n    return 1\nc = 3\n";
+        let mut app = collected(dir.path(), content, ":3-4");
+
+        fs::write(dir.path().join("a.py"), format!("import os\nimport sys\n{}", content)).unwrap();
+        app.refresh_all_collected();
+
+        assert_eq!(spans(&app.collected_files[0].ranges), [(5, 6)]);
+        assert!(app.collected_files[0].content.contains("def f():\n    return 1\n"));
+    }
+
+    #[test]
+    fn ranges_picked_by_symbol_follow_the_symbol() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "def f():\n    return 1\n\ndef g():\n    return 2\n";
+        let mut app = collected(dir.path(), content, "#g");
+        assert_eq!(spans(&app.collected_files[0].ranges), [(4, 5)]);
+
+        let changed = "def g():\n    x = 2\n    return x\n\ndef f():\n    return 1\n";
+        fs::write(dir.path().join("a.py"), changed).unwrap();
+        app.refresh_all_collected();
+
+        assert_eq!(spans(&app.collected_files[0].ranges), [(1, 3)]);
+    }
+}
@@ -0,0 +1,450 @@
+//! Recursive directory add for the RepoViewer application.
+//!
+//! `A` only adds the files directly in the current directory, so collecting
+//! `src/` would mean visiting every directory below it. `W` adds everything
+//! below the selected directory instead (or below the current one when a file
+//! is selected), walking it with the same hidden and gitignore settings as the
+//! file list. Include and exclude globs, a maximum depth and a language narrow
+//! down what's added. They're set with `browse --include`, `--exclude`,
+//! `--max-depth` and `--lang`, and depth and language can be cycled in the
+//! preview.
+//!
+//! Nothing is added straight away. Every file is read first and a preview
+//! shows how many would be added, their size and tokens, and what was skipped
+//! and why, so a stray data directory doesn't end up in the collection unseen.
+
+use super::{
+    App, CollectedFile, FileItem,
+    collection::AddOutcome,
+    headless::{SkippedFile, anywhere_matcher},
+};
+use crate::{app_error::AppError, utils::{get_file_type, is_known_language}};
+use globset::GlobSet;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Depth limits `d` cycles through in the preview, `None` is no limit
+const DEPTHS: [Option<usize>; 5] = [None, Some(1), Some(2), Some(3), Some(5)];
+
+/// What a recursive add picks up
+///
+/// Globs without a `/` match file and directory names anywhere (`*.rs`,
+/// `tests`), others match paths relative to the directory being added
+/// (`src/**/*.rs`). Excluded directories aren't entered at all.
+#[derive(Debug, Clone, Default)]
+pub struct DirFilter {
+    /// Only add files matching one of these, everything when empty
+    pub include: Vec<String>,
+    /// Never add files or enter directories matching one of these
+    pub exclude: Vec<String>,
+    /// How deep to go, 1 is only the files directly in the directory
+    pub max_depth: Option<usize>,
+    /// Only add files of this language (as in the export's code fences, e.g. `rust`)
+    pub language: Option<String>,
+}
+
+impl DirFilter {
+    /// Check the globs and language, so mistakes show up at startup rather than as an empty preview
+    pub fn validate(&self) -> Result<(), AppError> {
+        self.matchers()?;
+        if let Some(language) = &self.language
+            && !is_known_language(language)
+        {
+            return Err(AppError::UnknownLanguage(language.clone()));
+        }
+        Ok(())
+    }
+
+    fn matchers(&self) -> Result<(Option<GlobSet>, Option<GlobSet>), AppError> {
+        let compile = |patterns: &[String]| -> Result<Option<GlobSet>, AppError> {
+            if patterns.is_empty() {
+                return Ok(None);
+            }
+            anywhere_matcher(patterns.iter().map(String::as_str)).map(Some)
+        };
+        Ok((compile(&self.include)?, compile(&self.exclude)?))
+    }
+
+    /// Short description of the filters, e.g. `depth 2 · rust · include *.rs`
+    pub fn describe(&self) -> String {
+        let mut parts = vec![match self.max_depth {
+            Some(depth) => format!("depth {}", depth),
+            None => "any depth".to_string(),
+        }];
+        parts.push(self.language.clone().unwrap_or_else(|| "all languages".to_string()));
+        if !self.include.is_empty() {
+            parts.push(format!("include {}", self.include.join(", ")));
+        }
+        if !self.exclude.is_empty() {
+            parts.push(format!("exclude {}", self.exclude.join(", ")));
+        }
+        parts.join(" · ")
+    }
+}
+
+/// What adding a directory recursively would do, shown before anything is added
+#[derive(Debug, Clone)]
+pub struct DirPreview {
+    pub dir: PathBuf,
+    pub filter: DirFilter,
+    /// Files that would be added (or updated), already read
+    pub files: Vec<CollectedFile>,
+    /// How many of those are in the collection already
+    pub already_collected: usize,
+    /// Files that can't be collected, with the reason
+    pub skipped: Vec<SkippedFile>,
+    /// Files the globs or language left out
+    pub filtered: usize,
+    /// Directories not entered because of the depth limit
+    pub too_deep: usize,
+    /// Languages of the files found, for cycling the language filter
+    pub languages: Vec<String>,
+    /// First line of the file list shown
+    pub scroll: usize,
+}
+
+impl DirPreview {
+    pub fn total_size(&self) -> usize {
+        self.files.iter().map(|f| f.content.len()).sum()
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.files.iter().map(|f| f.token_count).sum()
+    }
+
+    pub fn scroll_by(&mut self, delta: isize) {
+        let lines = self.files.len() + self.skipped.len();
+        self.scroll = self.scroll.saturating_add_signed(delta).min(lines.saturating_sub(1));
+    }
+}
+
+/// Everything a walk found before files are read
+#[derive(Default)]
+struct Walk {
+    candidates: Vec<PathBuf>,
+    skipped: Vec<SkippedFile>,
+    filtered: usize,
+    too_deep: usize,
+    languages: BTreeSet<String>,
+}
+
+impl App {
+    /// Preview adding the selected directory (or the current one) recursively
+    pub fn open_dir_preview(&mut self) {
+        let dir = match self.current_selection() {
+            Some(item) if item.is_dir => item.path.clone(),
+            _ => self.current_dir.clone(),
+        };
+
+        match self.build_dir_preview(dir, self.dir_filter.clone()) {
+            Ok(preview) if preview.files.is_empty() && preview.skipped.is_empty() => {
+                let message = format!("No files to add under {}", self.get_display_path(&preview.dir));
+                self.set_error_message(message);
+            }
+            Ok(preview) => self.dir_preview = Some(preview),
+            Err(e) => self.set_error_message(e.to_string()),
+        }
+    }
+
+    /// Walk the directory and read every file that passes the filters
+    fn build_dir_preview(&self, dir: PathBuf, filter: DirFilter) -> Result<DirPreview, AppError> {
+        let (include, exclude) = filter.matchers()?;
+        let mut walk = Walk::default();
+        self.walk_for_add(&dir, &dir, 1, &filter, (include.as_ref(), exclude.as_ref()), &mut walk);
+
+        let mut files = Vec::new();
+        let mut skipped = walk.skipped;
+        for path in walk.candidates {
+            let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let item = FileItem {
+                is_dir: false,
+                is_symlink: path.is_symlink(),
+                is_hidden: self.is_hidden(&path, &name),
+                path,
+                name,
+            };
+            match self.create_collected_file(&item) {
+                Ok(file) => files.push(file),
+                Err(e) => skipped.push(self.skipped_file(&item.path, &e)),
+            }
+        }
+
+        skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let already_collected = files
+            .iter()
+            .filter(|file| self.collected_files.iter().any(|f| f.path == file.path))
+            .count();
+
+        Ok(DirPreview {
+            dir,
+            filter,
+            files,
+            already_collected,
+            skipped,
+            filtered: walk.filtered,
+            too_deep: walk.too_deep,
+            languages: walk.languages.into_iter().collect(),
+            scroll: 0,
+        })
+    }
+
+    /// Find the files below `dir` to add, sorted by path
+    ///
+    /// Hidden and gitignored entries follow the file list's settings and are
+    /// passed over quietly, `.repoviewerignore` paths are reported as skipped.
+    /// Symlinked directories aren't followed.
+    fn walk_for_add(
+        &self,
+        root: &Path,
+        dir: &Path,
+        depth: usize,
+        filter: &DirFilter,
+        (include, exclude): (Option<&GlobSet>, Option<&GlobSet>),
+        walk: &mut Walk,
+    ) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut entries: Vec<_> = entries.filter_map(|entry| entry.ok()).collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let is_dir = file_type.is_dir();
+
+            if name == ".git" || !self.should_include_file(&path, &name, is_dir) {
+                continue;
+            }
+            if self.is_export_ignored(&path, is_dir) {
+                walk.skipped.push(self.skipped_file(&path, &AppError::ExportIgnored));
+                continue;
+            }
+
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+            if exclude.is_some_and(|exclude| exclude.is_match(&relative)) {
+                if !is_dir {
+                    walk.filtered += 1;
+                }
+                continue;
+            }
+
+            if is_dir {
+                if filter.max_depth.is_some_and(|max| depth >= max) {
+                    walk.too_deep += 1;
+                } else {
+                    self.walk_for_add(root, &path, depth + 1, filter, (include, exclude), walk);
+                }
+                continue;
+            }
+            if !path.is_file() {
+                continue;
+            }
+
+            let language = get_file_type(&path);
+            let included = include.is_none_or(|include| include.is_match(&relative));
+            if included && let Some(language) = language {
+                walk.languages.insert(language.to_string());
+            }
+            let language_matches = filter.language.as_deref().is_none_or(|wanted| language == Some(wanted));
+            if included && language_matches {
+                walk.candidates.push(path);
+            } else {
+                walk.filtered += 1;
+            }
+        }
+    }
+
+    /// A skipped file, named the same way as the collected ones
+    fn skipped_file(&self, path: &Path, error: &AppError) -> SkippedFile {
+        SkippedFile {
+            path: self.calculate_relative_path(path).unwrap_or_else(|_| self.get_display_path(path)),
+            reason: self.collect_error_reason(error),
+        }
+    }
+
+    /// Rebuild the preview with the next depth limit
+    pub fn cycle_preview_depth(&mut self) {
+        let Some(preview) = &self.dir_preview else {
+            return;
+        };
+        let mut filter = preview.filter.clone();
+        let index = DEPTHS.iter().position(|depth| *depth == filter.max_depth);
+        filter.max_depth = DEPTHS[index.map_or(0, |i| (i + 1) % DEPTHS.len())];
+        self.rebuild_dir_preview(filter);
+    }
+
+    /// Rebuild the preview with the next language found, or all languages after the last one
+    pub fn cycle_preview_language(&mut self) {
+        let Some(preview) = &self.dir_preview else {
+            return;
+        };
+        let mut filter = preview.filter.clone();
+        filter.language = match &filter.language {
+            None => preview.languages.first().cloned(),
+            Some(current) => preview
+                .languages
+                .iter()
+                .position(|language| language == current)
+                .and_then(|i| preview.languages.get(i + 1).cloned()),
+        };
+        self.rebuild_dir_preview(filter);
+    }
+
+    fn rebuild_dir_preview(&mut self, filter: DirFilter) {
+        let Some(preview) = self.dir_preview.take() else {
+            return;
+        };
+        match self.build_dir_preview(preview.dir.clone(), filter) {
+            Ok(mut rebuilt) => {
+                // Languages come from the whole walk, keep them when one is picked
+                if rebuilt.filter.language.is_some() {
+                    rebuilt.languages = preview.languages;
+                }
+                self.dir_preview = Some(rebuilt);
+            }
+            Err(e) => {
+                self.dir_preview = Some(preview);
+                self.set_error_message(e.to_string());
+            }
+        }
+    }
+
+    /// Add the previewed files to the collection and close the preview
+    ///
+    /// Files already in the collection are updated and keep their settings,
+    /// like they are with `A`.
+    pub fn confirm_dir_preview(&mut self) {
+        let Some(preview) = self.dir_preview.take() else {
+            return;
+        };
+
+        let initial_health = self.collection_health();
+        let (added, updated) = self.add_or_update_files(preview.files);
+
+        let outcome = AddOutcome {
+            added,
+            updated,
+            skipped: preview.skipped.len(),
+            skipped_files: preview.skipped.iter().map(|s| format!("{}: {}", s.path, s.reason)).collect(),
+            errors: Vec::new(),
+        };
+        let source = format!("from {}", self.get_display_path(&preview.dir));
+        self.set_add_message(&source, outcome, initial_health);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A repository with a few files, some hidden and some gitignored
+    fn project() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let files = [
+            (".gitignore", "target/\n"),
+            (".hidden/secret.rs", "fn secret() {}\n"),
+            ("README.md", "# Readme\n"),
+            ("main.rs", "fn main() {}\n"),
+            ("src/deep/mod.rs", "mod deep;\n"),
+            ("src/lib.rs", "pub mod deep;\n"),
+            ("src/util.py", "print('util')\n"),
+            ("target/out.rs", "fn out() {}\n"),
+            ("tests/it.rs", "#[test]\nfn it() {}\n"),
+        ];
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let app = App::new(dir.path().to_path_buf()).unwrap();
+        (dir, app)
+    }
+
+    fn previewed(app: &App, filter: DirFilter) -> Vec<String> {
+        let preview = app.build_dir_preview(app.start_dir.clone(), filter).unwrap();
+        preview.files.iter().map(|f| f.relative_path.clone()).collect()
+    }
+
+    #[test]
+    fn depth_limit_stops_at_directories() {
+        let (_dir, app) = project();
+        let filter = DirFilter { max_depth: Some(1), ..DirFilter::default() };
+        assert_eq!(previewed(&app, filter.clone()), ["README.md", "main.rs"]);
+
+        let preview = app.build_dir_preview(app.start_dir.clone(), filter).unwrap();
+        assert_eq!(preview.too_deep, 2);
+
+        let filter = DirFilter { max_depth: Some(2), ..DirFilter::default() };
+        assert_eq!(previewed(&app, filter), ["README.md", "main.rs", "src/lib.rs", "src/util.py", "tests/it.rs"]);
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let (_dir, app) = project();
+
+        let filter = DirFilter { include: vec!["*.rs".to_string()], ..DirFilter::default() };
+        assert_eq!(previewed(&app, filter), ["main.rs", "src/deep/mod.rs", "src/lib.rs", "tests/it.rs"]);
+
+        // Names match anywhere, paths match from the directory being added
+        let filter = DirFilter {
+            include: vec!["*.rs".to_string()],
+            exclude: vec!["tests".to_string(), "src/deep".to_string()],
+            ..DirFilter::default()
+        };
+        let preview = app.build_dir_preview(app.start_dir.clone(), filter).unwrap();
+        let paths: Vec<&str> = preview.files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, ["main.rs", "src/lib.rs"]);
+        assert_eq!(preview.filtered, 2);
+    }
+
+    #[test]
+    fn language_filter_keeps_the_languages_found() {
+        let (_dir, app) = project();
+        let filter = DirFilter { language: Some("python".to_string()), ..DirFilter::default() };
+        let preview = app.build_dir_preview(app.start_dir.clone(), filter).unwrap();
+
+        let paths: Vec<&str> = preview.files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, ["src/util.py"]);
+        assert_eq!(preview.languages, ["markdown", "python", "rust"]);
+    }
+
+    #[test]
+    fn walk_follows_hidden_and_gitignore_settings() {
+        let (_dir, mut app) = project();
+        let everything = previewed(&app, DirFilter::default());
+        assert!(!everything.iter().any(|p| p.starts_with(".hidden") || p.starts_with("target")), "{:?}", everything);
+
+        app.show_hidden = true;
+        let with_hidden = previewed(&app, DirFilter::default());
+        assert!(with_hidden.contains(&".hidden/secret.rs".to_string()), "{:?}", with_hidden);
+        assert!(!with_hidden.contains(&"target/out.rs".to_string()), "{:?}", with_hidden);
+
+        app.show_gitignored = true;
+        let with_ignored = previewed(&app, DirFilter::default());
+        assert!(with_ignored.contains(&"target/out.rs".to_string()), "{:?}", with_ignored);
+    }
+
+    #[test]
+    fn confirming_adds_the_previewed_files() {
+        let (_dir, mut app) = project();
+        let filter = DirFilter { include: vec!["*.rs".to_string()], ..DirFilter::default() };
+        app.dir_preview = Some(app.build_dir_preview(app.start_dir.clone(), filter).unwrap());
+        app.confirm_dir_preview();
+
+        assert_eq!(app.collected_files.len(), 4);
+        assert!(app.dir_preview.is_none());
+        let message = app.message.expect("adding should say what happened");
+        assert!(message.text.starts_with("Added 4 files from "), "{}", message.text);
+        assert!(message.text.contains(", updated 0, skipped 0 - Total: 4 files"), "{}", message.text);
+    }
+}
@@ -0,0 +1,558 @@
+//! Multi-part exports for the RepoViewer application.
+//!
+//! Chat UIs cap how much text fits in one message, so a big collection can be
+//! exported as several numbered parts instead. Each part starts with a
+//! `Part 2/4` header telling the model more is coming. Saving writes one file
+//! per part, and copying puts part 1 on the clipboard with `n` copying the next.
+//!
+//! XML and JSON can't be cut up as text, so those split between files instead
+//! and every part is a complete document carrying its part number inside.
+
+use super::{App, CollectedFile, ContentMode};
+use super::export::RenderedExport;
+use super::exporter::{ExportDestination, ExportSummary, Exporter};
+use super::ranges::LineRange;
+use crate::app_error::AppError;
+use std::time::{Duration, SystemTime};
+
+/// Part sizes (in bytes) that `P` cycles through
+const PART_SIZE_PRESETS: [usize; 4] = [16_000, 32_000, 64_000, 100_000];
+
+/// Smallest part size we accept, anything less is mostly headers
+pub const MIN_PART_SIZE: usize = 1_000;
+
+/// Parts of a clipboard export that haven't been copied yet
+#[derive(Debug, Clone)]
+pub struct PendingParts {
+    pub parts: Vec<String>,
+    /// Index of the next part to copy
+    pub next: usize,
+}
+
+/// Room kept free in each part of a structured export, for counts in its
+/// header that grow with the files it holds
+const PART_SLACK: usize = 64;
+
+/// How many times to shrink the blocks of a file, or the parts of an export,
+/// that render too big
+const MAX_SPLIT_PASSES: usize = 4;
+
+/// Which part a structured export is rendering
+pub(super) struct ExportPart {
+    pub number: usize,
+    pub total: usize,
+    /// The same text the `Part 2/4` header has
+    pub note: String,
+    /// Position of the part's first file in the whole export, starting at 0
+    pub first_index: usize,
+}
+
+/// Split text into chunks of at most `max_len` bytes
+///
+/// Chunks end on line breaks whenever possible. Only a single line longer than
+/// `max_len` is cut in the middle, and then on a character boundary.
+pub(super) fn split_text(text: &str, max_len: usize) -> Vec<String> {
+    let max_len = max_len.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.split_inclusive('\n') {
+        if current.len() + line.len() > max_len && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        let mut rest = line;
+        while rest.len() > max_len {
+            // Find the last char boundary that fits
+            let mut cut = max_len;
+            while !rest.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            // A single char wider than max_len still has to go somewhere
+            if cut == 0 {
+                cut = rest.chars().next().map_or(rest.len(), |c| c.len_utf8());
+            }
+
+            chunks.push(rest[..cut].to_string());
+            rest = &rest[cut..];
+        }
+        current.push_str(rest);
+    }
+
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Pack pieces of an export into parts of at most `max_len` bytes
+///
+/// Pieces stay whole unless a single piece is bigger than a part, in which
+/// case it's split between lines with `split_text`.
+pub(super) fn pack_parts(pieces: Vec<String>, max_len: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+
+    for piece in pieces {
+        let chunks = if piece.len() > max_len { split_text(&piece, max_len) } else { vec![piece] };
+
+        for chunk in chunks {
+            if current.len() + chunk.len() > max_len && !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            current.push_str(&chunk);
+        }
+    }
+
+    if !current.is_empty() || parts.is_empty() {
+        parts.push(current);
+    }
+
+    parts
+}
+
+/// Cut a file into blocks of whole lines of at most `room` bytes each
+///
+/// Each block is collected as the range of lines it holds, so an export that
+/// can't split a file's content itself still says which lines it has.
+fn split_file(file: &CollectedFile, room: usize, app: &App) -> Vec<CollectedFile> {
+    let mut blocks: Vec<Vec<(usize, &str)>> = Vec::new();
+    let mut current: Vec<(usize, &str)> = Vec::new();
+    let mut current_len = 0;
+
+    for (i, line) in file.content.split_inclusive('\n').enumerate() {
+        if current_len + line.len() > room && !current.is_empty() {
+            blocks.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        current.push((file.source_line(i + 1), line));
+        current_len += line.len();
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks
+        .into_iter()
+        .map(|lines| {
+            let mut block = file.clone();
+            let first = lines.first().map_or(1, |(number, _)| *number);
+            let last = lines.last().map_or(1, |(number, _)| *number);
+
+            block.content = lines.iter().map(|(_, line)| *line).collect();
+            block.source_lines = Some(lines.iter().map(|(number, _)| *number).collect());
+            block.token_count = app.tokenizer.count(&block.content);
+            block.mode = ContentMode::Ranges;
+            block.ranges = vec![LineRange::new(first, last)];
+            block
+        })
+        .collect()
+}
+
+impl App {
+    /// What the model is told about a part, e.g. `Part 2/4 of repo - ...`
+    pub(super) fn part_note(&self, number: usize, total: usize) -> String {
+        let status = if number == total {
+            "this is the last part".to_string()
+        } else {
+            format!("wait for all {} parts before answering", total)
+        };
+
+        format!("Part {}/{} of {} - {}", number, total, self.source_display_name(), status)
+    }
+
+    /// The line put at the top of each part
+    fn part_header(&self, number: usize, total: usize) -> String {
+        format!("{}\n\n", self.part_note(number, total))
+    }
+
+    /// Split a structured export between files, each part a complete document
+    ///
+    /// `render` draws the export of whatever files the app it's given holds,
+    /// with the part's label when there is one. The metadata and tree only go
+    /// in the first part. A file too big for a part on its own is cut into
+    /// blocks of whole lines, collected as the line ranges they hold.
+    pub(super) fn export_file_parts<F>(&self, part_size: usize, render: F) -> Result<Vec<String>, AppError>
+    where
+        F: Fn(&App, Option<&ExportPart>) -> Result<String, AppError>,
+    {
+        let whole = render(self, None)?;
+        if whole.len() <= part_size {
+            return Ok(vec![whole]);
+        }
+
+        // The widest label a part can get (the last part's is shorter), so
+        // measuring leaves room for the real one
+        let placeholder = ExportPart { number: 998, total: 999, note: self.part_note(998, 999), first_index: 0 };
+        let capacity = part_size.saturating_sub(PART_SLACK);
+
+        let mut first = self.clone();
+        first.collected_files.clear();
+        let mut rest = first.clone();
+        rest.export_options.include_metadata = false;
+        rest.export_options.include_tree = false;
+
+        let first_base = render(&first, Some(&placeholder))?.len();
+        let base = render(&rest, Some(&placeholder))?.len();
+        let room = capacity.saturating_sub(base).max(1);
+
+        // What a file adds to a part
+        let mut measure = rest.clone();
+        let mut cost = |file: &CollectedFile| -> Result<usize, AppError> {
+            measure.collected_files = vec![file.clone()];
+            Ok(render(&measure, Some(&placeholder))?.len().saturating_sub(base))
+        };
+
+        let mut pieces: Vec<(CollectedFile, usize)> = Vec::new();
+        for file in &self.collected_files {
+            let file_cost = cost(file)?;
+            if file_cost <= room {
+                pieces.push((file.clone(), file_cost));
+                continue;
+            }
+
+            // Escaping makes content bigger once rendered, shrink the blocks until they fit
+            let wrapper = cost(&CollectedFile { content: String::new(), ..file.clone() })?;
+            let mut block_room = room.saturating_sub(wrapper).max(1);
+            let mut blocks = Vec::new();
+            for _ in 0..MAX_SPLIT_PASSES {
+                blocks = split_file(file, block_room, self)
+                    .into_iter()
+                    .map(|block| cost(&block).map(|block_cost| (block, block_cost)))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let widest = blocks.iter().map(|(_, block_cost)| *block_cost).max().unwrap_or(0);
+                if widest <= room {
+                    break;
+                }
+                block_room = (block_room * room / widest).saturating_sub(1).max(1);
+            }
+            pieces.extend(blocks);
+        }
+
+        // Pack the files into parts in export order
+        let mut groups: Vec<Vec<CollectedFile>> = vec![Vec::new()];
+        let mut used = first_base;
+        for (file, file_cost) in pieces {
+            let group = groups.last_mut().expect("there's always a group");
+            if used + file_cost > capacity && !group.is_empty() {
+                groups.push(vec![file]);
+                used = base + file_cost;
+            } else {
+                group.push(file);
+                used += file_cost;
+            }
+        }
+
+        let total = groups.len();
+        let mut parts = Vec::with_capacity(total);
+        let mut first_index = 0;
+        for (i, files) in groups.into_iter().enumerate() {
+            let mut app = if i == 0 { first.clone() } else { rest.clone() };
+            let count = files.len();
+            app.collected_files = files;
+
+            let part = ExportPart { number: i + 1, total, note: self.part_note(i + 1, total), first_index };
+            parts.push(render(&app, Some(&part))?);
+            first_index += count;
+        }
+
+        Ok(parts)
+    }
+
+    /// Move to the next part size preset, turning splitting off after the largest
+    pub fn cycle_part_size(&mut self) {
+        self.export_options.part_size = match self.export_options.part_size {
+            None => Some(PART_SIZE_PRESETS[0]),
+            Some(current) => PART_SIZE_PRESETS.iter().copied().find(|&s| s > current),
+        };
+
+        let message = match self.export_options.part_size {
+            Some(size) => format!("Exports larger than {} will be split into parts", self.format_size(size)),
+            None => "Exports will not be split".to_string(),
+        };
+        self.set_success_message(message);
+    }
+
+    /// Render the collection as numbered parts of at most `part_size` bytes
+    ///
+    /// An export that fits in one part comes back as a single part without a
+    /// header. The parts are finished (see `finish_output`), ready to write.
+    pub(super) fn render_parts(&self, exporter: &dyn Exporter, part_size: usize) -> Result<Vec<String>, AppError> {
+        // Anonymizing and redacting change the length of text that's already
+        // been packed, so parts that come out too big are packed again tighter
+        let mut room = part_size;
+        let mut parts = Vec::new();
+        for _ in 0..MAX_SPLIT_PASSES {
+            parts = self.pack_export(exporter, room)?
+                .iter()
+                .map(|part| self.finish_output(part))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let widest = parts.iter().map(String::len).max().unwrap_or(0);
+            if widest <= part_size {
+                break;
+            }
+            room = room.saturating_sub(widest - part_size).max(1);
+        }
+
+        Ok(parts)
+    }
+
+    /// Render the export as parts of at most `part_size` bytes, headers included
+    fn pack_export(&self, exporter: &dyn Exporter, part_size: usize) -> Result<Vec<String>, AppError> {
+        // Formats that label their own parts measure the label themselves
+        if exporter.labels_parts() {
+            return exporter.export_parts(self, part_size);
+        }
+
+        // Leave room for the part header in every part (the last part's is shorter)
+        let reserve = self.part_header(998, 999).len();
+        let mut parts = exporter.export_parts(self, part_size.saturating_sub(reserve).max(1))?;
+
+        let total = parts.len();
+        if total > 1 {
+            for (i, part) in parts.iter_mut().enumerate() {
+                part.insert_str(0, &self.part_header(i + 1, total));
+            }
+        }
+
+        Ok(parts)
+    }
+
+    /// Save every part to `code_context_<ts>_partN.<ext>` in the current directory
+    pub(super) fn save_collection_parts(&mut self, export: RenderedExport) -> Result<(), AppError> {
+        let RenderedExport { parts, report } = export;
+        let since_epoch = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::from_secs(0));
+        let extension = self.exporters.active().extension().to_string();
+
+        let mut summary = ExportSummary { bytes: 0, tokens: 0 };
+        let mut first_path = None;
+
+        for (i, part) in parts.iter().enumerate() {
+            let filename = format!("code_context_{}_part{}.{}", since_epoch.as_secs(), i + 1, extension);
+            let output_path = self.current_dir.join(filename);
+
+            let written = self.write_output(part, &ExportDestination::File(output_path.clone()))?;
+            summary.bytes += written.bytes;
+            summary.tokens += written.tokens;
+            first_path.get_or_insert(output_path);
+        }
+
+        let display_path = first_path
+            .map(|p| self.get_display_path(&p))
+            .unwrap_or_default();
+
+        let mut message = format!(
+            "Saved {} files ({}, {}) as {} parts of {}, starting with {}",
+            self.collected_files.len(),
+            self.format_size(summary.bytes),
+            self.format_tokens(summary.tokens),
+            parts.len(),
+            self.exporters.active().name(),
+            display_path
+        );
+        message.push_str(&self.export_notes(report.as_ref()));
+        self.set_success_message(message);
+
+        Ok(())
+    }
+
+    /// Copy part 1 to the clipboard and keep the rest for `n`
+    pub(super) fn copy_collection_parts(&mut self, export: RenderedExport) -> Result<(), AppError> {
+        let RenderedExport { parts, report } = export;
+        self.pending_parts = Some(PendingParts { parts, next: 0 });
+        self.copy_next_part()?;
+
+        let note = self.export_notes(report.as_ref());
+        if let Some(message) = &mut self.message {
+            message.text.push_str(&note);
+        }
+
+        Ok(())
+    }
+
+    /// Copy the next part of a split clipboard export
+    pub fn copy_next_part(&mut self) -> Result<(), AppError> {
+        let Some(pending) = &self.pending_parts else {
+            self.set_error_message("No parts waiting - copy a split collection with 'C' first".to_string());
+            return Ok(());
+        };
+
+        let number = pending.next + 1;
+        let total = pending.parts.len();
+        let summary = self.write_output(&pending.parts[pending.next], &ExportDestination::Clipboard)?;
+
+        let message = if number < total {
+            format!(
+                "Copied part {}/{} ({}, {}) - press 'n' for part {}",
+                number,
+                total,
+                self.format_size(summary.bytes),
+                self.format_tokens(summary.tokens),
+                number + 1
+            )
+        } else {
+            format!(
+                "Copied part {}/{} ({}, {}) - all parts copied",
+                number,
+                total,
+                self.format_size(summary.bytes),
+                self.format_tokens(summary.tokens)
+            )
+        };
+
+        // Only move on once the copy worked, so a failed copy can be retried
+        if number < total {
+            if let Some(pending) = &mut self.pending_parts {
+                pending.next += 1;
+            }
+        } else {
+            self.pending_parts = None;
+        }
+
+        self.set_success_message(message);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Anonymizer;
+    use quick_xml::{Reader, events::Event};
+    use std::fs;
+
+    const PART_SIZE: usize = 1_000;
+
+    /// Parts of a collection with a few small files and one too big for a part
+    fn split_export(format: &str) -> Vec<String> {
+        split_export_with(format, |_| {})
+    }
+
+    fn split_export_with(format: &str, configure: impl Fn(&mut App)) -> Vec<String> {
+        let dir = tempfile::tempdir().unwrap();
+        // Nothing committed, so every file shows up in the diff export as new
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join("a.py"), "print('a')\n").unwrap();
+        let big: String = (1..=120).map(|i| format!("s{} = \"]]> & <tag> {}\"\n", i, i)).collect();
+        fs::write(dir.path().join("big.py"), big).unwrap();
+        fs::write(dir.path().join("c.py"), "print('c')\n").unwrap();
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        app.collect_inputs(&["a.py".to_string(), "big.py".to_string(), "c.py".to_string()]).unwrap();
+        app.exporters.select(format).unwrap();
+        app.export_options.part_size = Some(PART_SIZE);
+        configure(&mut app);
+
+        let parts = app.render_collection(true, &[]).unwrap().parts;
+        assert!(parts.len() > 2, "{:?}", parts);
+        assert!(parts.iter().all(|part| part.len() <= PART_SIZE), "{:?}", parts);
+        parts
+    }
+
+    #[test]
+    fn xml_parts_are_complete_documents() {
+        let mut indexes = Vec::new();
+        let parts = split_export("xml");
+        for (i, part) in parts.iter().enumerate() {
+            let mut reader = Reader::from_str(part);
+            let mut depth = 0;
+            loop {
+                match reader.read_event() {
+                    Ok(Event::Start(element)) => {
+                        depth += 1;
+                        let name = element.name().as_ref().to_vec();
+                        let attribute = |key: &[u8]| {
+                            element.try_get_attribute(key).unwrap().map(|a| a.unescape_value().unwrap().to_string())
+                        };
+                        if name == b"part" {
+                            assert_eq!(attribute(b"number"), Some((i + 1).to_string()));
+                            assert_eq!(attribute(b"total"), Some(parts.len().to_string()));
+                        } else if name == b"document" {
+                            indexes.push(attribute(b"index").unwrap().parse::<usize>().unwrap());
+                        }
+                    }
+                    Ok(Event::End(_)) => depth -= 1,
+                    Ok(Event::Eof) => break,
+                    Ok(_) => {}
+                    Err(e) => panic!("part {} isn't well-formed XML: {}\n{}", i + 1, e, part),
+                }
+            }
+            assert_eq!(depth, 0, "part {} leaves elements open:\n{}", i + 1, part);
+        }
+
+        // Documents keep their place in the whole export
+        assert_eq!(indexes, (1..=indexes.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn json_parts_are_complete_documents() {
+        let parts = split_export("json");
+        let mut big = String::new();
+        let mut lines = Vec::new();
+
+        for (i, part) in parts.iter().enumerate() {
+            let value: serde_json::Value = serde_json::from_str(part)
+                .unwrap_or_else(|e| panic!("part {} isn't valid JSON: {}\n{}", i + 1, e, part));
+            assert_eq!(value["part"]["number"], i + 1);
+            assert_eq!(value["part"]["total"], parts.len());
+
+            for file in value["files"].as_array().unwrap() {
+                if file["relative_path"] == "big.py" {
+                    assert_eq!(file["mode"], "ranges");
+                    big.push_str(file["content"].as_str().unwrap());
+                    lines.push((file["ranges"][0]["start"].as_u64().unwrap(), file["ranges"][0]["end"].as_u64().unwrap()));
+                }
+            }
+        }
+
+        // The blocks of the big file hold all of it, in order
+        let expected: String = (1..=120).map(|i| format!("s{} = \"]]> & <tag> {}\"\n", i, i)).collect();
+        assert_eq!(big, expected);
+        assert_eq!(lines.first().map(|(start, _)| *start), Some(1));
+        assert_eq!(lines.last().map(|(_, end)| *end), Some(120));
+        assert!(lines.windows(2).all(|pair| pair[1].0 == pair[0].1 + 1), "{:?}", lines);
+    }
+
+    #[test]
+    fn diff_parts_close_every_code_block() {
+        let parts = split_export_with("diff", |app| app.export_options.diff_content = true);
+        for (i, part) in parts.iter().enumerate() {
+            let mut open: Option<&str> = None;
+            for line in part.lines() {
+                let run = &line[..line.len() - line.trim_start_matches('`').len()];
+                match open {
+                    None if run.len() >= 3 => open = Some(run),
+                    Some(fence) if line == fence => open = None,
+                    _ => {}
+                }
+            }
+            assert_eq!(open, None, "part {} leaves a code block open:\n{}", i + 1, part);
+        }
+
+        // The big file's diff and content were cut up, not dropped
+        let all = parts.concat();
+        assert!(all.contains("## big.py (new, untracked, continued)"), "{}", all);
+        assert!(all.contains("+s120 = "), "{}", all);
+        assert!(all.contains("\ns120 = "), "{}", all);
+    }
+
+    #[test]
+    fn anonymized_parts_stay_within_the_part_size() {
+        let parts = split_export_with("markdown", |app| {
+            let config_dir = app.start_dir.join(".repoviewer");
+            fs::create_dir(&config_dir).unwrap();
+            fs::write(config_dir.join("anonymize.toml"), "[terms]\nword = [\"tag\"]\n").unwrap();
+            app.anonymizer = Anonymizer::load(&app.start_dir).unwrap();
+            app.export_options.anonymize = true;
+        });
+
+        let all = parts.concat();
+        assert!(all.contains("<WORD_1> 120"), "{}", all);
+        assert!(!all.contains("<tag>"), "{}", all);
+    }
+}
@@ -43,6 +43,16 @@ pub enum RefreshResult {
     Failed,
 }
 
+/// How close the collection is to being too large to use
+///
+/// Measured in tokens when token limits are configured, otherwise in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionHealth {
+    Healthy,
+    Warning,
+    Critical,
+}
+
 /// Summary of refreshing all collected files
 /// Gives the user a complete picture of what changed
 #[derive(Debug, Default)]
@@ -79,10 +89,10 @@ impl App {
     /// Check if current message has timed out and clear it if needed
     /// This should be called in the main event loop
     pub fn update_message(&mut self) {
-        if let Some(message) = &self.message {
-            if message.created_at.elapsed() >= message.timeout {
-                self.message = None;
-            }
+        if let Some(message) = &self.message
+            && message.created_at.elapsed() >= message.timeout
+        {
+            self.message = None;
         }
     }
 
@@ -100,10 +110,11 @@ impl App {
             return Some("Tip: Press '~' to quickly return to the start directory".to_string());
         }
         
-        if let Some(git_root) = &self.git_root {
-            if self.current_dir != *git_root && self.get_current_depth() > 2 {
-                return Some("Tip: Press 'G' to jump to the git repository root".to_string());
-            }
+        if let Some(git_root) = &self.git_root
+            && self.current_dir != *git_root
+            && self.get_current_depth() > 2
+        {
+            return Some("Tip: Press 'G' to jump to the git repository root".to_string());
         }
         
         // New user hint - no files collected yet
@@ -120,12 +131,22 @@ impl App {
             return Some("Navigate to files and press 'a' to start collecting".to_string());
         }
         
+        // Over budget means exports will be cut, make that visible
+        if let Some(budget) = self.token_budget
+            && self.get_collection_tokens() > budget
+        {
+            return Some("Collection is over the token budget - press 'F' to see what exports will cut".to_string());
+        }
+
         // Collection size warnings
-        let size = self.get_collection_size();
-        if size > 50 * MEGABYTE {
-            return Some("Collection is very large! Consider using 'd' to remove files or 'S' to save".to_string());
-        } else if size > 25 * MEGABYTE {
-            return Some("Collection growing large. Ready to export with 'S' or 'C'".to_string());
+        match self.collection_health() {
+            CollectionHealth::Critical => {
+                return Some("Collection is very large! Consider using 'd' to remove files or 'S' to save".to_string());
+            }
+            CollectionHealth::Warning => {
+                return Some("Collection growing large. Ready to export with 'S' or 'C'".to_string());
+            }
+            CollectionHealth::Healthy => {}
         }
         
         // Suggest refresh if files might be stale (collected over 5 minutes ago)
@@ -135,12 +156,11 @@ impl App {
                 .map(|f| f.collected_at)
                 .min();
                 
-            if let Some(oldest) = oldest_collection {
-                if let Ok(elapsed) = SystemTime::now().duration_since(oldest) {
-                    if elapsed.as_secs() > 300 { // 5 minutes
-                        return Some("Files collected a while ago - press 'r' to refresh".to_string());
-                    }
-                }
+            if let Some(oldest) = oldest_collection
+                && let Ok(elapsed) = SystemTime::now().duration_since(oldest)
+                && elapsed.as_secs() > 300 // 5 minutes
+            {
+                return Some("Files collected a while ago - press 'r' to refresh".to_string());
             }
         }
         
@@ -160,7 +180,7 @@ impl App {
         }
         
         // Default hint when collection has some files
-        if self.collected_files.len() > 0 {
+        if !self.collected_files.is_empty() {
             return Some(format!("{} files collected - 'a' to add more, 'S' to save", 
                 self.collected_files.len()));
         }
@@ -192,31 +212,69 @@ impl App {
         self.collected_files.iter().map(|f| f.content.len()).sum()
     }
 
+    /// Get the total number of tokens in the collection
+    pub fn get_collection_tokens(&self) -> usize {
+        self.collected_files.iter().map(|f| f.token_count).sum()
+    }
+
+    /// Format a token count with the active tokenizer (e.g. "~12.4k tokens")
+    pub fn format_tokens(&self, tokens: usize) -> String {
+        self.tokenizer.format(tokens)
+    }
+
+    /// Work out how healthy the collection size is
+    ///
+    /// Token limits replace the byte thresholds (25MB/50MB) when either is set,
+    /// since context windows are what actually matter once you paste.
+    pub fn collection_health(&self) -> CollectionHealth {
+        if self.token_limits.is_set() {
+            let tokens = self.get_collection_tokens();
+            let exceeds = |limit: Option<usize>| limit.is_some_and(|l| tokens > l);
+
+            if exceeds(self.token_limits.critical) {
+                CollectionHealth::Critical
+            } else if exceeds(self.token_limits.warning) {
+                CollectionHealth::Warning
+            } else {
+                CollectionHealth::Healthy
+            }
+        } else {
+            const WARNING_THRESHOLD: usize = 25 * MEGABYTE;
+            const CRITICAL_THRESHOLD: usize = 50 * MEGABYTE;
+
+            match self.get_collection_size() {
+                s if s > CRITICAL_THRESHOLD => CollectionHealth::Critical,
+                s if s > WARNING_THRESHOLD => CollectionHealth::Warning,
+                _ => CollectionHealth::Healthy,
+            }
+        }
+    }
+
     /// Check collection size and return appropriate warning message
     /// This helps users avoid creating collections that are too large
     pub(super) fn get_size_warning(&self) -> Option<String> {
-        
-        let size = self.get_collection_size(); // iterate over vec and get size in bytes
-        
-        // Define our warning thresholds
-        const WARNING_THRESHOLD: usize = 25 * MEGABYTE;
-        const CRITICAL_THRESHOLD: usize = 50 * MEGABYTE;
-        
-        // Match general size and give feedback for how large the collection is becoming
-        match size {
-            s if s > CRITICAL_THRESHOLD => {
+        // Describe the size in whichever unit the thresholds are measured in
+        let amount = if self.token_limits.is_set() {
+            self.format_tokens(self.get_collection_tokens())
+        } else {
+            self.format_size(self.get_collection_size())
+        };
+
+        // Give feedback for how large the collection is becoming
+        match self.collection_health() {
+            CollectionHealth::Critical => {
                 Some(format!(
                     "⚠️ Collection is very large ({}) - Consider removing some files", 
-                    self.format_size(s)
+                    amount
                 ))
             },
-            s if s > WARNING_THRESHOLD => {
+            CollectionHealth::Warning => {
                 Some(format!(
                     "⚠️ Collection is getting large ({})", 
-                    self.format_size(s)
+                    amount
                 ))
             },
-            _ => None
+            CollectionHealth::Healthy => None
         }
     }
 
@@ -259,14 +317,17 @@ impl App {
         self.collected_files.iter().any(|f| f.path == path)
     }
 
-    /// Determine if we should include a file based on hidden and gitignore status
+    /// Determine if we should include a file based on hidden and ignored status
+    ///
+    /// Ignored means anything git would ignore (every `.gitignore` up to the
+    /// repo root, `.git/info/exclude`, the global excludes file) plus `.ignore` files.
     pub(super) fn should_include_file(&self, path: &Path, name: &str, is_dir: bool) -> bool {
         if self.is_hidden(path, name) && !self.show_hidden {
             return false;
         }
 
-        if let Some(ignore) = &self.gitignore {
-            match ignore.matched(path, is_dir) {
+        if let Some(ignores) = &self.ignores {
+            match ignores.matched(path, is_dir) {
                 ignore::Match::Ignore(_) if !self.show_gitignored => return false,
                 ignore::Match::Whitelist(_) => return true,
                 _ => {}
@@ -276,6 +337,15 @@ impl App {
         true
     }
 
+    /// Whether `.repoviewerignore` keeps a path out of exports
+    ///
+    /// Unlike gitignored files these still show up in the file list, `g`
+    /// doesn't affect them. The anonymization config and mapping are always
+    /// kept out, they hold the names being hidden.
+    pub(super) fn is_export_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.export_ignores.matched(path, is_dir).is_ignore() || self.is_anonymize_file(path)
+    }
+
     /// Check if a file is hidden (different on Windows than Linux)
     pub(super) fn is_hidden(&self, path: &Path, name: &str) -> bool {
         // First, check if the file/directory name starts with a dot
@@ -319,10 +389,10 @@ impl App {
         // Try multiple strategies to get a meaningful relative path
         
         // First if we have a git root use that
-        if let Some(git_root) = &self.git_root {
-            if let Ok(rel_path) = path.strip_prefix(git_root) {
-                return Ok(rel_path.to_string_lossy().to_string());
-            }
+        if let Some(git_root) = &self.git_root
+            && let Ok(rel_path) = path.strip_prefix(git_root)
+        {
+            return Ok(rel_path.to_string_lossy().to_string());
         }
         
         // Then try relative to start directory
@@ -377,7 +447,7 @@ impl App {
             .unwrap_or_else(|| {
                 // Last resort: just use the last component of the path
                 path.components()
-                    .last()
+                    .next_back()
                     .map(|c| c.as_os_str().to_string_lossy().to_string())
                     .unwrap_or_else(|| "unknown".to_string())
             });
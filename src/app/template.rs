@@ -0,0 +1,533 @@
+//! User-defined export templates for the RepoViewer application.
+//!
+//! Every model (and every prompt style) wants a slightly different wrapper
+//! around the collected files. Instead of hardcoding that wrapper, an export
+//! template describes it as three pieces of text: a header, a block that is
+//! repeated for every file, and a footer. Placeholders like `{relative_path}`
+//! are filled in when the export is generated.
+//!
+//! Templates are TOML files loaded from two places:
+//! - `<config dir>/repoviewer/templates/*.toml` for personal templates
+//! - `<repo root>/.repoviewer/templates/*.toml` for templates shared with a project
+//!
+//! A repo template with the same name as a user template takes precedence.
+//! The built-in default template produces the classic markdown export, and
+//! templates can't reuse the name of a built-in format.
+
+use super::{App, CollectedFile, ContentMode};
+use super::export::{fence_for, file_modified};
+use super::split::{pack_parts, split_text};
+use super::toc::FileGroup;
+use crate::app_error::AppError;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Name of the built-in template (it's registered as the `markdown` exporter)
+pub const DEFAULT_TEMPLATE_NAME: &str = "markdown";
+
+/// An export template loaded from disk (or the built-in default)
+///
+/// Example template file:
+///
+/// ```toml
+/// extension = "txt"
+/// header = "Files from {repo_name}:\n"
+/// transforms = "(content edited: {transforms})\n"
+/// tree = "Structure:\n{tree}\n"
+/// directory = "--- {directory} ---\n"
+/// file = "=== {relative_path} ({line_count} lines) ===\n{content}\n"
+/// footer = "=== end ===\n"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExportTemplate {
+    /// Template name, taken from the file stem when not set in the file
+    #[serde(default)]
+    pub name: String,
+    /// Extension used when saving with this template
+    #[serde(default = "default_extension")]
+    pub extension: String,
+    /// Text emitted once before any files
+    #[serde(default)]
+    pub header: String,
+    /// Text emitted after the header when exported content is transformed
+    #[serde(default = "default_transforms")]
+    pub transforms: String,
+    /// Text emitted after the header when repository metadata is included
+    #[serde(default = "default_metadata")]
+    pub metadata: String,
+    /// Text emitted after the header when the directory tree is included
+    #[serde(default = "default_tree")]
+    pub tree: String,
+    /// Text emitted before the files when grouping by directory
+    #[serde(default = "default_toc")]
+    pub toc: String,
+    /// Text emitted before each directory's files when grouping by directory
+    #[serde(default = "default_directory")]
+    pub directory: String,
+    /// Text emitted once per collected file
+    pub file: String,
+    /// Text emitted once after all files
+    #[serde(default)]
+    pub footer: String,
+}
+
+fn default_extension() -> String {
+    "md".to_string()
+}
+
+fn default_transforms() -> String {
+    "Content transformed for export: {transforms}\n\n".to_string()
+}
+
+fn default_metadata() -> String {
+    "{metadata}\n".to_string()
+}
+
+fn default_tree() -> String {
+    "{tree}\n".to_string()
+}
+
+fn default_toc() -> String {
+    "{toc}\n".to_string()
+}
+
+fn default_directory() -> String {
+    "\n{directory}\n".to_string()
+}
+
+impl ExportTemplate {
+    /// The built-in template, which reproduces the original markdown export
+    pub fn builtin() -> Self {
+        ExportTemplate {
+            name: DEFAULT_TEMPLATE_NAME.to_string(),
+            extension: default_extension(),
+            header: "# Code Context\n\nGenerated from: {repo_name}\n\n".to_string(),
+            transforms: "> Content transformed for export: {transforms}.\n\n".to_string(),
+            metadata: "## Repository\n\n{metadata}\n".to_string(),
+            tree: "## Directory Structure\n\nFiles marked with [+] are included below.\n\n{fence}text\n{tree}{fence}\n".to_string(),
+            toc: "## Contents\n\n{toc}\n".to_string(),
+            directory: "\n## {directory}\n".to_string(),
+            file: "\n{heading} {relative_path}{mode_label}\n\n{file_info}{fence}{language}\n{content}{fence}\n".to_string(),
+            footer: String::new(),
+        }
+    }
+
+    /// Parse a single template file
+    fn from_file(path: &Path) -> Result<Self, AppError> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| AppError::Io(e).with_path_context(path))?;
+
+        let mut template: ExportTemplate = toml::from_str(&text)
+            .map_err(|e| AppError::TemplateError(format!("{}: {}", path.display(), e.message())))?;
+
+        // Fall back to the file name so most templates don't need a `name` key
+        if template.name.is_empty() {
+            template.name = path.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+        }
+
+        Ok(template)
+    }
+}
+
+/// Directories that templates are loaded from, lowest precedence first
+fn template_dirs(project_root: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    if let Some(config_dir) = dirs::config_dir() {
+        dirs.push(config_dir.join("repoviewer").join("templates"));
+    }
+    dirs.push(project_root.join(".repoviewer").join("templates"));
+
+    dirs
+}
+
+/// Load every template from the user config directory and the project
+///
+/// Broken template files don't stop the others from loading. Their errors are
+/// returned next to the templates so the caller can tell the user about them.
+pub fn load_templates(project_root: &Path) -> (Vec<ExportTemplate>, Vec<String>) {
+    let mut templates: Vec<ExportTemplate> = Vec::new();
+    let mut errors = Vec::new();
+
+    for dir in template_dirs(project_root) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue; // Missing directories are the normal case
+        };
+
+        // Sort so the cycling order in the TUI is stable
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match ExportTemplate::from_file(&path) {
+                Ok(template) => {
+                    // Later directories (the repo) override earlier ones (the user)
+                    if let Some(existing) = templates.iter_mut().find(|t| t.name == template.name) {
+                        *existing = template;
+                    } else {
+                        templates.push(template);
+                    }
+                }
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+    }
+
+    (templates, errors)
+}
+
+/// Substitute `{name}` placeholders in a template string
+///
+/// This is a single pass over the template, so placeholders that show up inside
+/// substituted values (like a `{content}` that contains `{language}`) are left
+/// alone. Unknown placeholders are kept as-is and `{{`/`}}` produce literal braces.
+fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        let tail = &rest[start..];
+
+        if let Some(after) = tail.strip_prefix("{{") {
+            output.push('{');
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("}}") {
+            output.push('}');
+            rest = after;
+        } else if tail.starts_with('{')
+            && let Some(end) = tail.find('}')
+            && let Some((_, value)) = vars.iter().find(|(name, _)| *name == &tail[1..end])
+        {
+            output.push_str(value);
+            rest = &tail[end + 1..];
+        } else {
+            // Lone brace or unknown placeholder, keep the character and move on
+            output.push_str(&tail[..1]);
+            rest = &tail[1..];
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Where a file's block sits in the export
+struct FileBlock<'a> {
+    /// Position in export order, starting at 0
+    index: usize,
+    file: &'a CollectedFile,
+    anchor: &'a str,
+    /// Whether the file is under a directory heading
+    grouped: bool,
+    /// Lines of the file on disk the block holds, when it's split between parts
+    lines: Option<(usize, usize)>,
+}
+
+/// Render the heading of a directory group, nothing when grouping is off
+fn render_directory(template: &ExportTemplate, common: &[(&str, &str)], group: &FileGroup) -> String {
+    let Some(directory) = &group.directory else {
+        return String::new();
+    };
+
+    let directory_files = group.files.len().to_string();
+    let mut vars = common.to_vec();
+    vars.extend([
+        ("directory", directory.as_str()),
+        ("anchor", group.anchor.as_str()),
+        ("directory_files", directory_files.as_str()),
+    ]);
+    render(&template.directory, &vars)
+}
+
+impl App {
+    /// Generate an export by filling in a template with the collected files
+    ///
+    /// Available placeholders:
+    /// - everywhere: `{repo_name}`, `{file_count}`, `{total_size}`, `{total_tokens}`
+    /// - in `transforms`: `{transforms}` (e.g. `comments stripped, blank lines collapsed`)
+    /// - in `metadata`: `{metadata}` (a markdown list of everything below), `{branch}`,
+    ///   `{commit}`, `{commit_subject}`, `{status}`, `{remote_url}`, `{exported_at}`, `{version}`
+    /// - in `tree`: `{tree}`, `{fence}`
+    /// - in `toc`: `{toc}` (a markdown list linking to every file)
+    /// - in `directory`: `{directory}`, `{anchor}`, `{directory_files}`
+    /// - in `file`: `{index}`, `{relative_path}`, `{path}`, `{language}`,
+    ///   `{content}`, `{line_count}`, `{size}`, `{token_count}`, `{modified}`,
+    ///   `{fence}`, `{file_info}`, `{anchor}`, `{heading}`, `{mode}`, `{mode_label}`
+    ///
+    /// `{content}` and `{tree}` always end with a newline so templates can put
+    /// a closing fence right after them. `{fence}` is a run of backticks longer
+    /// than any inside the content, so files containing fences can't break out.
+    /// `{file_info}` is empty unless per-file info is on, otherwise it's the
+    /// info line followed by a blank line. `{mode}` is `full`, `outline` or
+    /// `ranges`, and `{mode_label}` is ` (outline)` or ` (lines 10-20, 40-60)`
+    /// for those and empty for whole files.
+    ///
+    /// The `transforms` section is only used when transforms are on, so an
+    /// export always says when its content isn't exactly what's on disk.
+    ///
+    /// The `toc` and `directory` sections are only used when grouping by
+    /// directory. `{heading}` is `##`, or `###` when files sit under directory
+    /// headings, and `{anchor}` is the heading's link target in the contents.
+    pub fn generate_from_template(&self, template: &ExportTemplate) -> Result<String, AppError> {
+        let common = self.template_vars();
+        let common: Vec<(&str, &str)> = common.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+        let groups = self.export_groups();
+        let mut output = self.render_template_header(template, &common, &groups)?;
+
+        let mut index = 0;
+        for group in &groups {
+            output.push_str(&render_directory(template, &common, group));
+            for (file, anchor) in &group.files {
+                let content = self.export_content(file);
+                let block = FileBlock { index, file, anchor, grouped: group.directory.is_some(), lines: None };
+                output.push_str(&self.render_template_file(template, &common, &block, &content));
+                index += 1;
+            }
+        }
+
+        output.push_str(&render(&template.footer, &common));
+
+        Ok(output)
+    }
+
+    /// Generate a template export split into parts of at most `part_size` bytes
+    ///
+    /// Parts break between files where possible. A file that doesn't fit in a
+    /// part on its own is rendered as several blocks of whole lines, each
+    /// labelled with the line range it holds, so fences are closed and reopened
+    /// properly instead of being cut in half.
+    pub fn generate_parts_from_template(
+        &self,
+        template: &ExportTemplate,
+        part_size: usize,
+    ) -> Result<Vec<String>, AppError> {
+        let common = self.template_vars();
+        let common: Vec<(&str, &str)> = common.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+        let groups = self.export_groups();
+        let mut pieces = vec![self.render_template_header(template, &common, &groups)?];
+
+        let mut index = 0;
+        for group in &groups {
+            // Directory headings are tiny, keep them with the first file below them
+            let mut heading = render_directory(template, &common, group);
+
+            for (file, anchor) in &group.files {
+                let content = self.export_content(file);
+                let block = FileBlock { index, file, anchor, grouped: group.directory.is_some(), lines: None };
+                index += 1;
+
+                let rendered = self.render_template_file(template, &common, &block, &content);
+                if heading.len() + rendered.len() <= part_size {
+                    pieces.push(std::mem::take(&mut heading) + &rendered);
+                    continue;
+                }
+
+                // Room left for content once the block's own wrapper is paid for,
+                // with some slack for the line range in its label
+                let wrapper = self.render_template_file(template, &common, &block, "");
+                let room = part_size.saturating_sub(heading.len() + wrapper.len() + 32).max(1);
+
+                // Label each block with lines of the file on disk, not of the
+                // rendered content, so `file:line` citations hold up
+                let source_lines = self.export_source_lines(file);
+                let source_line = |line: usize| source_lines.get(line - 1).copied().unwrap_or(line);
+
+                let mut first_line = 1;
+                for chunk in split_text(&content, room) {
+                    // A chunk cut in the middle of a long line ends on that line,
+                    // and the next chunk picks up on it
+                    let newlines = chunk.matches('\n').count();
+                    let last_line = if chunk.ends_with('\n') { first_line + newlines - 1 } else { first_line + newlines };
+                    let lines = Some((source_line(first_line), source_line(last_line)));
+                    let rendered = self.render_template_file(template, &common, &FileBlock { lines, ..block }, &chunk);
+                    pieces.push(std::mem::take(&mut heading) + &rendered);
+                    first_line += newlines;
+                }
+            }
+        }
+
+        pieces.push(render(&template.footer, &common));
+
+        Ok(pack_parts(pieces, part_size))
+    }
+
+    /// Values for the placeholders that work everywhere in a template
+    fn template_vars(&self) -> [(&'static str, String); 4] {
+        [
+            ("repo_name", self.source_display_name()),
+            ("file_count", self.collected_files.len().to_string()),
+            ("total_size", self.format_size(self.get_collection_size())),
+            ("total_tokens", self.format_tokens(self.get_collection_tokens())),
+        ]
+    }
+
+    /// Render the header, plus the transforms, metadata, tree and contents sections when they're included
+    fn render_template_header(
+        &self,
+        template: &ExportTemplate,
+        common: &[(&str, &str)],
+        groups: &[FileGroup],
+    ) -> Result<String, AppError> {
+        let mut output = render(&template.header, common);
+
+        if self.export_options.transforms.any() {
+            let transforms = self.export_options.transforms.describe();
+            let mut vars = common.to_vec();
+            vars.push(("transforms", transforms.as_str()));
+            output.push_str(&render(&template.transforms, &vars));
+        }
+
+        if let Some(metadata) = self.export_metadata()? {
+            // Missing git fields render as empty strings
+            let text = metadata.to_markdown();
+            let commit = metadata.commit.clone().unwrap_or_default();
+            let commit_subject = metadata.commit_subject.clone().unwrap_or_default();
+            let status = metadata.status().unwrap_or_default();
+            let branch = metadata.branch.clone().unwrap_or_default();
+            let remote_url = metadata.remote_url.clone().unwrap_or_default();
+
+            let mut vars = common.to_vec();
+            vars.extend([
+                ("metadata", text.as_str()),
+                ("branch", branch.as_str()),
+                ("commit", commit.as_str()),
+                ("commit_subject", commit_subject.as_str()),
+                ("status", status.as_str()),
+                ("remote_url", remote_url.as_str()),
+                ("exported_at", metadata.exported_at.as_str()),
+                ("version", metadata.version.as_str()),
+            ]);
+            output.push_str(&render(&template.metadata, &vars));
+        }
+
+        // The tree section is only rendered when the user asked for it
+        if let Some(tree) = self.export_tree()? {
+            let fence = fence_for(&tree);
+            let mut vars = common.to_vec();
+            vars.extend([("tree", tree.as_str()), ("fence", fence.as_str())]);
+            output.push_str(&render(&template.tree, &vars));
+        }
+
+        // Contents only make sense once files are grouped
+        if self.export_options.group_by_directory {
+            let toc = self.table_of_contents(groups);
+            let mut vars = common.to_vec();
+            vars.push(("toc", toc.as_str()));
+            output.push_str(&render(&template.toc, &vars));
+        }
+
+        Ok(output)
+    }
+
+    /// Render the per-file block for (part of) a collected file
+    ///
+    /// A block of a split file says which lines it holds in `{mode_label}`,
+    /// in place of the file's own label, or after the path for templates that
+    /// don't show the mode.
+    fn render_template_file(
+        &self,
+        template: &ExportTemplate,
+        common: &[(&str, &str)],
+        block: &FileBlock,
+        content: &str,
+    ) -> String {
+        let file = block.file;
+        let index = (block.index + 1).to_string();
+        let heading = if block.grouped { "###" } else { "##" };
+        let path = file.path.to_string_lossy();
+        let line_count = file.content.lines().count().to_string();
+        let size = self.format_size(file.content.len());
+        let token_count = self.format_tokens(file.token_count);
+        let modified = file_modified(file);
+        let fence = fence_for(content);
+        let mode_label = match (block.lines, file.mode) {
+            (Some((first, last)), ContentMode::Outline) => format!(" (outline, lines {}-{})", first, last),
+            (Some((first, last)), _) => format!(" (lines {}-{})", first, last),
+            (None, _) => file.mode_label(),
+        };
+        let relative_path = match block.lines {
+            Some(_) if !template.file.contains("{mode_label}") => format!("{}{}", file.relative_path, mode_label),
+            _ => file.relative_path.clone(),
+        };
+        let file_info = if self.export_options.file_info {
+            format!("{}\n\n", self.file_info_line(file))
+        } else {
+            String::new()
+        };
+
+        // Ensure the content is newline terminated even if the file isn't
+        let mut content = content.to_string();
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+
+        let mut vars = common.to_vec();
+        vars.extend([
+            ("index", index.as_str()),
+            ("relative_path", relative_path.as_str()),
+            ("path", path.as_ref()),
+            ("language", file.language.as_str()),
+            ("content", content.as_str()),
+            ("line_count", line_count.as_str()),
+            ("size", size.as_str()),
+            ("token_count", token_count.as_str()),
+            ("modified", modified.as_str()),
+            ("fence", fence.as_str()),
+            ("file_info", file_info.as_str()),
+            ("anchor", block.anchor),
+            ("heading", heading),
+            ("mode", file.mode.name()),
+            ("mode_label", mode_label.as_str()),
+        ]);
+
+        render(&template.file, &vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Line ranges named in the `(lines a-b)` labels of a split export
+    fn part_labels(parts: &[String]) -> Vec<(usize, usize)> {
+        let label = regex::Regex::new(r"a\.py \(lines (\d+)-(\d+)\)").unwrap();
+        parts
+            .iter()
+            .flat_map(|part| label.captures_iter(part).map(|c| (c[1].parse().unwrap(), c[2].parse().unwrap())).collect::<Vec<_>>())
+            .collect()
+    }
+
+    #[test]
+    fn split_file_labels_use_lines_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let content: String = (1..=200).map(|i| format!("value_{:03} = {}\n", i, i)).collect();
+        fs::write(dir.path().join("a.py"), content).unwrap();
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        app.collect_inputs(&["a.py:101-200".to_string()]).unwrap();
+        app.export_options.line_numbers = true;
+        let parts = app.generate_parts_from_template(&ExportTemplate::builtin(), 1_000).unwrap();
+
+        let labels = part_labels(&parts);
+        assert!(labels.len() > 1, "{:?}", parts);
+        // The first line is the marker standing in for lines 1-100
+        assert_eq!(labels.first().map(|(start, _)| *start), Some(1));
+        assert_eq!(labels.get(1).map(|(start, _)| *start), labels.first().map(|(_, end)| end + 1));
+        assert_eq!(labels.last().map(|(_, end)| *end), Some(200));
+
+        // The block's lines replace the file's own range, they aren't added to it
+        assert!(!parts.concat().contains("(lines 101-200)"), "{:?}", parts);
+        assert!(parts.iter().all(|part| !part.contains(") (lines")), "{:?}", parts);
+    }
+}
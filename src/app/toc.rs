@@ -0,0 +1,232 @@
+//! Directory grouping and the table of contents for the RepoViewer application.
+//!
+//! A flat list of forty `## path` sections is hard to find your way around.
+//! With grouping turned on, markdown and template exports sort files by
+//! directory (then name) and put each directory under its own heading, and a
+//! table of contents up front lists every file with its line and token count,
+//! linking to its section. The other formats keep collection order and say
+//! grouping isn't supported for them.
+//!
+//! Anchors follow GitHub's heading rules (lowercase, punctuation dropped,
+//! spaces turned into dashes, `-1`, `-2`... for repeats), so the links work in
+//! rendered markdown and still read fine as plain text.
+
+use super::{App, CollectedFile};
+use std::collections::HashMap;
+
+/// What the group of files directly in the project root is called
+const ROOT_DIRECTORY: &str = "(root)";
+
+/// Files from one directory, in export order
+pub(super) struct FileGroup<'a> {
+    /// Directory relative to the project root with a trailing `/`, `(root)` for the root.
+    /// `None` when grouping is off and the group holds the whole collection.
+    pub directory: Option<String>,
+    /// Anchor of the directory heading
+    pub anchor: String,
+    /// Each file with the anchor of its heading
+    pub files: Vec<(&'a CollectedFile, String)>,
+}
+
+/// Split a relative path into its directory (with trailing `/`) and file name
+fn split_directory(relative_path: &str) -> (String, &str) {
+    match relative_path.rfind(['/', '\\']) {
+        Some(i) => (relative_path[..=i].replace('\\', "/"), &relative_path[i + 1..]),
+        None => (ROOT_DIRECTORY.to_string(), relative_path),
+    }
+}
+
+/// GitHub-style anchor for a heading
+fn heading_slug(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Text of a file's heading, what its anchor is made from
+///
+/// Has to match the `{relative_path}{mode_label}` of the default template's
+/// headings, or links to outlines and ranges would go nowhere.
+fn file_heading(file: &CollectedFile) -> String {
+    format!("{}{}", file.relative_path, file.mode_label())
+}
+
+/// Hands out anchors, numbering repeats the way GitHub does
+#[derive(Default)]
+struct Anchors {
+    seen: HashMap<String, usize>,
+}
+
+impl Anchors {
+    fn next(&mut self, heading: &str) -> String {
+        let slug = heading_slug(heading);
+        let count = self.seen.entry(slug.clone()).or_insert(0);
+        let anchor = if *count == 0 { slug } else { format!("{}-{}", slug, count) };
+        *count += 1;
+        anchor
+    }
+}
+
+impl App {
+    /// The collected files in the order they're exported
+    ///
+    /// Without grouping this is a single group in collection order. With it,
+    /// there's one group per directory, directories sorted by path (the root
+    /// first) and files sorted by name inside each one, so the same collection
+    /// always exports the same way.
+    pub(super) fn export_groups(&self) -> Vec<FileGroup<'_>> {
+        let mut anchors = Anchors::default();
+
+        if !self.export_options.group_by_directory {
+            let files = self.collected_files
+                .iter()
+                .map(|file| (file, anchors.next(&file_heading(file))))
+                .collect();
+            return vec![FileGroup { directory: None, anchor: String::new(), files }];
+        }
+
+        let mut sorted: Vec<(String, &str, &CollectedFile)> = self.collected_files
+            .iter()
+            .map(|file| {
+                let (directory, name) = split_directory(&file.relative_path);
+                (directory, name, file)
+            })
+            .collect();
+        sorted.sort_by(|a, b| {
+            let root_first = (a.0 != ROOT_DIRECTORY).cmp(&(b.0 != ROOT_DIRECTORY));
+            root_first.then_with(|| a.0.cmp(&b.0)).then_with(|| a.1.cmp(b.1))
+        });
+
+        let mut groups: Vec<FileGroup> = Vec::new();
+        for (directory, _, file) in sorted {
+            if groups.last().and_then(|g| g.directory.as_deref()) != Some(directory.as_str()) {
+                let anchor = anchors.next(&directory);
+                groups.push(FileGroup { directory: Some(directory), anchor, files: Vec::new() });
+            }
+            if let Some(group) = groups.last_mut() {
+                group.files.push((file, anchors.next(&file_heading(file))));
+            }
+        }
+
+        groups
+    }
+
+    /// Says so when grouping is on but the active format doesn't group, e.g.
+    /// `grouping not supported for xml exports`
+    pub fn grouping_note(&self) -> Option<String> {
+        let exporter = self.exporters.active();
+        (self.export_options.group_by_directory && !exporter.groups_by_directory())
+            .then(|| format!("grouping not supported for {} exports", exporter.name()))
+    }
+
+    /// Markdown table of contents for the given groups
+    ///
+    /// e.g. `- [main.rs](#srcmainrs) - 120 lines, ~1.2k tokens`, nested under
+    /// a link to its directory.
+    pub(super) fn table_of_contents(&self, groups: &[FileGroup]) -> String {
+        let mut output = String::new();
+
+        for group in groups {
+            let indent = match &group.directory {
+                Some(directory) => {
+                    output.push_str(&format!("- [{}](#{})\n", directory, group.anchor));
+                    "  "
+                }
+                None => "",
+            };
+
+            for (file, anchor) in &group.files {
+                let name = match group.directory {
+                    Some(_) => split_directory(&file.relative_path).1,
+                    None => file.relative_path.as_str(),
+                };
+                let lines = file.content.lines().count();
+                output.push_str(&format!(
+                    "{}- [{}](#{}) - {} line{}, {}\n",
+                    indent,
+                    name,
+                    anchor,
+                    lines,
+                    if lines == 1 { "" } else { "s" },
+                    self.format_tokens(file.token_count)
+                ));
+            }
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn links_reach_outline_and_range_headings() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        let content = "def f():\n    return 1\n\ndef g():\n    return 2\n";
+        fs::write(dir.path().join("src/a.py"), content).unwrap();
+        fs::write(dir.path().join("src/b.py"), content).unwrap();
+        fs::write(dir.path().join("c.py"), content).unwrap();
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        let inputs = ["src/a.py", "src/b.py:1-2", "c.py"].map(String::from);
+        app.collect_inputs(&inputs).unwrap();
+        app.outline_matching("src/a.py").unwrap();
+        app.export_options.group_by_directory = true;
+        let markdown = app.generate_markdown().unwrap();
+
+        let headings: Vec<String> = markdown
+            .lines()
+            .filter_map(|line| line.strip_prefix("### "))
+            .map(heading_slug)
+            .collect();
+        assert!(headings.contains(&"srcapy-outline".to_string()), "{:?}", headings);
+        assert!(headings.contains(&"srcbpy-lines-1-2".to_string()), "{:?}", headings);
+
+        let links: Vec<&str> = markdown
+            .lines()
+            .filter_map(|line| line.strip_prefix("  - [")?.split_once("](#"))
+            .filter_map(|(_, rest)| rest.split_once(')'))
+            .map(|(anchor, _)| anchor)
+            .collect();
+        assert_eq!(links.len(), 3, "{}", markdown);
+        for anchor in links {
+            assert!(headings.iter().any(|heading| heading == anchor), "dead link #{} in\n{}", anchor, markdown);
+        }
+    }
+
+    #[test]
+    fn formats_that_dont_group_say_so() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.py"), "print('hi')\n").unwrap();
+
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        app.collect_inputs(&["main.py".to_string()]).unwrap();
+        app.export_options.group_by_directory = true;
+        assert_eq!(app.grouping_note(), None);
+
+        for format in ["xml", "json", "diff"] {
+            app.exporters.select(format).unwrap();
+            let note = format!("grouping not supported for {} exports", format);
+            assert_eq!(app.grouping_note().as_deref(), Some(note.as_str()));
+        }
+
+        app.exporters.select("xml").unwrap();
+        app.save_collection_to_file(Some("export".to_string())).unwrap();
+        let message = app.message.clone().unwrap();
+        assert!(message.text.ends_with(" | grouping not supported for xml exports"), "{}", message.text);
+
+        app.export_options.group_by_directory = false;
+        assert_eq!(app.grouping_note(), None);
+    }
+}
@@ -0,0 +1,850 @@
+//! Export transforms for the RepoViewer application.
+//!
+//! Comments, license headers and runs of blank lines cost tokens without
+//! telling a model much. Transforms rewrite each file's content on its way
+//! into a text export (markdown, XML, diff and templates), never on disk and
+//! never in the JSON export:
+//!
+//! - strip comments, including doc comments and Python docstrings
+//! - strip the license header at the top of a file
+//! - collapse runs of blank lines into one
+//! - normalize line endings to `\n`
+//! - expand tabs to spaces
+//!
+//! Comment syntax is picked from the file's language (see `get_file_type`).
+//! The scanner knows each language's string literals, including raw strings
+//! (`r#"…"#`, `R"(…)"`, `@"…"`, backticks, triple quotes) and JavaScript's
+//! regex literals, so a `//` inside a URL string or pattern or a `#` in a
+//! shell parameter expansion is left alone. Languages it doesn't know keep
+//! their comments. Line numbers still refer to the file on disk, so
+//! `file:line` citations stay valid after lines are removed.
+
+use super::{App, ranges::is_elision_marker};
+use std::ops::Range;
+
+/// Which transforms are applied to exported content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportTransforms {
+    /// Remove comments (and docstrings) in languages whose syntax we know
+    pub strip_comments: bool,
+    /// Remove a leading comment block that mentions a copyright or license
+    pub strip_license: bool,
+    /// Collapse runs of blank lines into one and drop blank lines at the start
+    pub collapse_blank_lines: bool,
+    /// Turn `\r\n` and lone `\r` into `\n`
+    pub normalize_line_endings: bool,
+    /// Replace tabs with spaces up to the next multiple of this width
+    pub expand_tabs: Option<usize>,
+}
+
+/// Tab width used when `tabs` is given without one
+const DEFAULT_TAB_WIDTH: usize = 4;
+
+impl ExportTransforms {
+    /// Only whitespace changes, the code itself is left alone
+    pub const WHITESPACE: ExportTransforms = ExportTransforms {
+        strip_comments: false,
+        strip_license: false,
+        collapse_blank_lines: true,
+        normalize_line_endings: true,
+        expand_tabs: Some(DEFAULT_TAB_WIDTH),
+    };
+
+    /// Everything on
+    pub const COMPACT: ExportTransforms = ExportTransforms {
+        strip_comments: true,
+        strip_license: true,
+        ..ExportTransforms::WHITESPACE
+    };
+
+    /// Presets `X` cycles through in the TUI, in order
+    pub const PRESETS: [(&'static str, ExportTransforms); 3] = [
+        ("off", ExportTransforms {
+            strip_comments: false,
+            strip_license: false,
+            collapse_blank_lines: false,
+            normalize_line_endings: false,
+            expand_tabs: None,
+        }),
+        ("whitespace", ExportTransforms::WHITESPACE),
+        ("compact", ExportTransforms::COMPACT),
+    ];
+
+    /// Whether any transform is on
+    pub fn any(&self) -> bool {
+        *self != ExportTransforms::default()
+    }
+
+    /// Name of the preset these transforms match, `custom` otherwise
+    pub fn name(&self) -> &'static str {
+        ExportTransforms::PRESETS
+            .iter()
+            .find(|(_, preset)| preset == self)
+            .map(|(name, _)| *name)
+            .unwrap_or("custom")
+    }
+
+    /// The preset after this one (custom settings go back to off)
+    pub fn next_preset(&self) -> ExportTransforms {
+        let presets = ExportTransforms::PRESETS;
+        match presets.iter().position(|(_, preset)| preset == self) {
+            Some(i) => presets[(i + 1) % presets.len()].1,
+            None => presets[0].1,
+        }
+    }
+
+    /// What was done to the content, for the note in the export
+    ///
+    /// e.g. `comments stripped, blank lines collapsed, tabs expanded to 4 spaces`
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.strip_comments {
+            parts.push("comments stripped".to_string());
+        }
+        if self.strip_license {
+            parts.push("license headers stripped".to_string());
+        }
+        if self.collapse_blank_lines {
+            parts.push("blank lines collapsed".to_string());
+        }
+        if self.normalize_line_endings {
+            parts.push("line endings normalized".to_string());
+        }
+        if let Some(width) = self.expand_tabs {
+            parts.push(format!("tabs expanded to {} spaces", width));
+        }
+        parts.join(", ")
+    }
+
+    /// Parse a comma-separated list of transforms and presets
+    ///
+    /// Transforms are `comments`, `license`, `blank-lines`, `line-endings` and
+    /// `tabs` (or `tabs=N`); presets are `whitespace`, `compact` and `off`.
+    pub fn parse(list: &str) -> Result<ExportTransforms, String> {
+        let mut transforms = ExportTransforms::default();
+
+        for item in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (name, value) = match item.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (item, None),
+            };
+            if value.is_some() && name != "tabs" {
+                return Err(format!("'{}' doesn't take a value", name));
+            }
+
+            match name {
+                "comments" => transforms.strip_comments = true,
+                "license" => transforms.strip_license = true,
+                "blank-lines" => transforms.collapse_blank_lines = true,
+                "line-endings" => transforms.normalize_line_endings = true,
+                "tabs" => {
+                    let width = match value {
+                        Some(value) => value.parse::<usize>()
+                            .ok()
+                            .filter(|w| *w > 0)
+                            .ok_or_else(|| format!("invalid tab width '{}'", value))?,
+                        None => DEFAULT_TAB_WIDTH,
+                    };
+                    transforms.expand_tabs = Some(width);
+                }
+                _ => {
+                    let Some((_, preset)) = ExportTransforms::PRESETS.iter().find(|(n, _)| *n == name) else {
+                        return Err(format!(
+                            "unknown transform '{}' (expected comments, license, blank-lines, \
+                             line-endings, tabs[=N], whitespace, compact or off)",
+                            name
+                        ));
+                    };
+                    transforms = merge(transforms, *preset);
+                }
+            }
+        }
+
+        Ok(transforms)
+    }
+}
+
+/// Both sets of transforms together
+fn merge(a: ExportTransforms, b: ExportTransforms) -> ExportTransforms {
+    ExportTransforms {
+        strip_comments: a.strip_comments || b.strip_comments,
+        strip_license: a.strip_license || b.strip_license,
+        collapse_blank_lines: a.collapse_blank_lines || b.collapse_blank_lines,
+        normalize_line_endings: a.normalize_line_endings || b.normalize_line_endings,
+        expand_tabs: a.expand_tabs.or(b.expand_tabs),
+    }
+}
+
+/// A kind of string literal
+#[derive(Clone, Copy)]
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    /// Whether a backslash escapes the next character
+    escapes: bool,
+    /// Whether the literal can span lines (otherwise a newline ends it)
+    multiline: bool,
+}
+
+impl Quote {
+    const fn new(open: &'static str, close: &'static str, escapes: bool, multiline: bool) -> Self {
+        Quote { open, close, escapes, multiline }
+    }
+}
+
+const DOUBLE: Quote = Quote::new("\"", "\"", true, false);
+const SINGLE: Quote = Quote::new("'", "'", true, false);
+const TRIPLE_DOUBLE: Quote = Quote::new("\"\"\"", "\"\"\"", true, true);
+const TRIPLE_SINGLE: Quote = Quote::new("'''", "'''", true, true);
+/// JavaScript template literals
+const TEMPLATE: Quote = Quote::new("`", "`", true, true);
+/// Go raw strings
+const BACKTICK_RAW: Quote = Quote::new("`", "`", false, true);
+/// Shell and SQL strings: no escapes, `''` is just two strings in a row
+const SINGLE_RAW: Quote = Quote::new("'", "'", false, true);
+/// Strings that can hold line breaks as they are (Rust, PHP, Ruby...)
+const DOUBLE_MULTILINE: Quote = Quote::new("\"", "\"", true, true);
+const SINGLE_MULTILINE: Quote = Quote::new("'", "'", true, true);
+
+/// Literals that need more than an open and close delimiter
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Flavor {
+    Plain,
+    /// `r#"…"#` raw strings, and `'a` lifetimes that aren't char literals
+    Rust,
+    /// `R"delim(…)delim"` raw strings
+    Cpp,
+    /// `@"…"` verbatim strings, where `""` is an escaped quote
+    CSharp,
+    /// Docstrings count as comments
+    Python,
+    /// `/…/flags` regex literals wherever an expression can start
+    JavaScript,
+}
+
+/// How comments and strings look in a language
+struct Syntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+    /// Whether block comments nest (`/* /* */ */` is one comment)
+    nested: bool,
+    /// Line comments only start at the start of a word, so `${#var}` and
+    /// `a#b` aren't comments in shell scripts
+    word_start: bool,
+    /// String literals, longest opener first
+    quotes: &'static [Quote],
+    flavor: Flavor,
+}
+
+impl Syntax {
+    const fn new(line: &'static [&'static str], block: Option<(&'static str, &'static str)>, quotes: &'static [Quote]) -> Self {
+        Syntax { line, block, nested: false, word_start: false, quotes, flavor: Flavor::Plain }
+    }
+
+    const fn nested(mut self) -> Self {
+        self.nested = true;
+        self
+    }
+
+    const fn word_start(mut self) -> Self {
+        self.word_start = true;
+        self
+    }
+
+    const fn flavor(mut self, flavor: Flavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+}
+
+const C_BLOCK: Option<(&str, &str)> = Some(("/*", "*/"));
+const SLASHES: &[&str] = &["//"];
+const HASH: &[&str] = &["#"];
+
+/// Comment syntax for a language from `get_file_type`, `None` when we don't know it
+fn syntax_for(language: &str) -> Option<Syntax> {
+    let syntax = match language {
+        "rust" => Syntax::new(SLASHES, C_BLOCK, &[DOUBLE_MULTILINE]).nested().flavor(Flavor::Rust),
+        "c" | "cpp" => Syntax::new(SLASHES, C_BLOCK, &[DOUBLE, SINGLE]).flavor(Flavor::Cpp),
+        "csharp" => Syntax::new(SLASHES, C_BLOCK, &[TRIPLE_DOUBLE, DOUBLE, SINGLE]).flavor(Flavor::CSharp),
+        "java" | "gradle" | "dart" | "groovy" => {
+            Syntax::new(SLASHES, C_BLOCK, &[TRIPLE_DOUBLE, TRIPLE_SINGLE, DOUBLE, SINGLE])
+        }
+        "kotlin" | "scala" | "swift" => {
+            Syntax::new(SLASHES, C_BLOCK, &[TRIPLE_DOUBLE, DOUBLE, SINGLE]).nested()
+        }
+        "go" => Syntax::new(SLASHES, C_BLOCK, &[DOUBLE, SINGLE, BACKTICK_RAW]),
+        "javascript" | "typescript" | "jsx" => {
+            Syntax::new(SLASHES, C_BLOCK, &[DOUBLE, SINGLE, TEMPLATE]).flavor(Flavor::JavaScript)
+        }
+        "php" => Syntax::new(SLASHES, C_BLOCK, &[DOUBLE_MULTILINE, SINGLE_MULTILINE]),
+        "solidity" | "v" | "protobuf" | "scss" | "less" | "kdl" => {
+            Syntax::new(SLASHES, C_BLOCK, &[DOUBLE, SINGLE])
+        }
+        "json" => Syntax::new(SLASHES, C_BLOCK, &[DOUBLE]),
+        "zig" => Syntax::new(SLASHES, None, &[DOUBLE, SINGLE]),
+        "css" => Syntax::new(&[], C_BLOCK, &[DOUBLE, SINGLE]),
+        "terraform" | "hcl" => Syntax::new(&["#", "//"], C_BLOCK, &[DOUBLE]),
+        "python" => Syntax::new(HASH, None, &[TRIPLE_DOUBLE, TRIPLE_SINGLE, DOUBLE, SINGLE]).flavor(Flavor::Python),
+        "ruby" | "perl" | "r" | "elixir" | "crystal" | "julia" => {
+            Syntax::new(HASH, None, &[TRIPLE_DOUBLE, DOUBLE_MULTILINE, SINGLE_MULTILINE])
+        }
+        "nim" | "graphql" => Syntax::new(HASH, None, &[TRIPLE_DOUBLE, DOUBLE, SINGLE]),
+        "toml" => Syntax::new(HASH, None, &[TRIPLE_DOUBLE, TRIPLE_SINGLE, DOUBLE, SINGLE_RAW]),
+        "bash" => Syntax::new(HASH, None, &[DOUBLE_MULTILINE, SINGLE_RAW]).word_start(),
+        "powershell" => Syntax::new(HASH, Some(("<#", "#>")), &[DOUBLE_MULTILINE, SINGLE_RAW]).word_start(),
+        "yaml" | "makefile" | "dockerfile" | "cmake" | "properties" => {
+            Syntax::new(HASH, None, &[DOUBLE, SINGLE_RAW]).word_start()
+        }
+        "ini" => Syntax::new(&[";", "#"], None, &[]).word_start(),
+        "sql" => Syntax::new(&["--"], C_BLOCK, &[SINGLE_RAW, DOUBLE]),
+        "lua" => Syntax::new(&["--"], Some(("--[[", "]]")), &[DOUBLE, SINGLE]),
+        "haskell" => Syntax::new(&["--"], Some(("{-", "-}")), &[DOUBLE]).nested(),
+        "ocaml" => Syntax::new(&[], Some(("(*", "*)")), &[DOUBLE_MULTILINE]).nested(),
+        "fsharp" => Syntax::new(SLASHES, Some(("(*", "*)")), &[TRIPLE_DOUBLE, DOUBLE_MULTILINE]),
+        "clojure" => Syntax::new(&[";"], None, &[DOUBLE_MULTILINE]),
+        "erlang" => Syntax::new(&["%"], None, &[DOUBLE_MULTILINE]),
+        "html" | "xml" | "vue" | "svelte" | "astro" | "markdown" => {
+            Syntax::new(&[], Some(("<!--", "-->")), &[])
+        }
+        _ => return None,
+    };
+
+    Some(syntax)
+}
+
+/// How to write a one-line comment in a language, as an opening and closing
+/// delimiter (the closing one is empty for line comments)
+pub(super) fn comment_delimiters(language: &str) -> Option<(&'static str, &'static str)> {
+    let syntax = syntax_for(language)?;
+    match (syntax.line.first(), syntax.block) {
+        (Some(line), _) => Some((line, "")),
+        (None, Some(block)) => Some(block),
+        (None, None) => None,
+    }
+}
+
+/// Words that make a leading comment a license header
+const LICENSE_WORDS: &[&str] = &[
+    "copyright",
+    "license",
+    "licence",
+    "spdx-license-identifier",
+    "all rights reserved",
+    "permission is hereby granted",
+];
+
+/// Apply transforms to a file's content
+///
+/// Returns the lines that are left, each with its line terminator and its
+/// 1-based line number in the (line-ending normalized) original.
+pub fn transform_lines(content: &str, language: &str, transforms: &ExportTransforms) -> Vec<(usize, String)> {
+    let text = if transforms.normalize_line_endings {
+        content.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        content.to_string()
+    };
+
+    let mut removed = Vec::new();
+    if (transforms.strip_comments || transforms.strip_license)
+        && let Some(syntax) = syntax_for(language)
+    {
+        let mut comments = scan(&text, &syntax).comments;
+        // Markers for lines left out of a range collection aren't the file's comments
+        comments.retain(|span| !is_elision_marker(&text[span.clone()]));
+        removed = if transforms.strip_comments {
+            comments
+        } else {
+            license_spans(&text, &comments)
+        };
+    }
+    let stripped = remove_spans(&text, &removed);
+
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut dropped_any = false;
+    for (i, (original, line)) in text.split_inclusive('\n').zip(stripped.split_inclusive('\n')).enumerate() {
+        let (body, ending) = split_ending(line);
+        let touched = original != line;
+
+        // Trailing whitespace left in front of a removed comment goes too
+        let body = if touched { body.trim_end() } else { body };
+        let blank = body.trim().is_empty();
+
+        // Lines that held nothing but a comment disappear entirely
+        if touched && blank {
+            dropped_any = true;
+            continue;
+        }
+        // Blank lines at the start (once something above them is gone) and
+        // repeated blank lines go when collapsing
+        let redundant = match lines.last() {
+            None => transforms.collapse_blank_lines || dropped_any,
+            Some((_, last)) => transforms.collapse_blank_lines && last.trim().is_empty(),
+        };
+        if blank && redundant {
+            continue;
+        }
+
+        let body = match transforms.expand_tabs {
+            Some(width) => expand_tabs(body, width),
+            None => body.to_string(),
+        };
+        lines.push((i + 1, body + ending));
+    }
+
+    lines
+}
+
+/// Split a line into its content and its terminator (`\n`, `\r\n` or nothing)
+fn split_ending(line: &str) -> (&str, &str) {
+    if let Some(body) = line.strip_suffix("\r\n") {
+        (body, "\r\n")
+    } else if let Some(body) = line.strip_suffix('\n') {
+        (body, "\n")
+    } else {
+        (line, "")
+    }
+}
+
+/// Replace tabs with spaces up to the next tab stop
+fn expand_tabs(line: &str, width: usize) -> String {
+    let mut output = String::with_capacity(line.len());
+    let mut column = 0;
+
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = width - column % width;
+            output.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        } else {
+            output.push(c);
+            column += 1;
+        }
+    }
+
+    output
+}
+
+/// Copy `text` without the given spans, keeping the newlines inside them so
+/// every line stays where it was
+fn remove_spans(text: &str, spans: &[Range<usize>]) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut copied = 0;
+
+    for span in spans {
+        output.push_str(&text[copied..span.start]);
+        for _ in text[span.clone()].matches('\n') {
+            output.push('\n');
+        }
+        copied = span.end;
+    }
+    output.push_str(&text[copied..]);
+
+    output
+}
+
+/// The comments that make up the license header, if the file starts with one
+///
+/// The header is the first run of comments on consecutive lines (or one
+/// block comment), after an optional shebang.
+fn license_spans(text: &str, comments: &[Range<usize>]) -> Vec<Range<usize>> {
+    let Some(first) = comments.first() else {
+        return Vec::new();
+    };
+    let before = &text[..first.start];
+    let before = if before.starts_with("#!") {
+        before.split_once('\n').map(|(_, rest)| rest).unwrap_or_default()
+    } else {
+        before
+    };
+    if !before.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut header = vec![first.clone()];
+    for pair in comments.windows(2) {
+        let gap = &text[pair[0].end..pair[1].start];
+        if !gap.trim().is_empty() || gap.matches('\n').count() > 1 {
+            break;
+        }
+        header.push(pair[1].clone());
+    }
+
+    let header_text = text[header[0].start..header[header.len() - 1].end].to_lowercase();
+    if LICENSE_WORDS.iter().any(|word| header_text.contains(word)) {
+        header
+    } else {
+        Vec::new()
+    }
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// Index of the end of the line `start` is on, before any `\r\n`
+fn line_end(bytes: &[u8], start: usize) -> usize {
+    let end = bytes[start..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |p| start + p);
+    if end > start && bytes[end - 1] == b'\r' { end - 1 } else { end }
+}
+
+/// Where the comments and literals are in a file
+#[derive(Debug, Default)]
+pub(super) struct CodeSpans {
+    /// Comments, plus docstrings in Python
+    pub comments: Vec<Range<usize>>,
+    /// String and character literals
+    pub literals: Vec<Range<usize>>,
+}
+
+/// Find the comments and literals in a file, `None` if we don't know its language
+pub(super) fn scan_code(text: &str, language: &str) -> Option<CodeSpans> {
+    syntax_for(language).map(|syntax| scan(text, &syntax))
+}
+
+/// Byte ranges of every comment and literal in `text`
+///
+/// Everything the scanner looks for is ASCII, so it works on bytes and every
+/// range it returns starts and ends on a character boundary.
+fn scan(text: &str, syntax: &Syntax) -> CodeSpans {
+    let bytes = text.as_bytes();
+    let mut spans = CodeSpans::default();
+    let mut i = 0;
+
+    // A shebang is for the OS, it stays even where `#` starts a comment
+    if bytes.starts_with(b"#!") && syntax.line.contains(&"#") {
+        i = line_end(bytes, 0);
+    }
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+
+        // Block comments first, `--[[` has to win over `--`
+        if let Some((open, close)) = syntax.block
+            && rest.starts_with(open.as_bytes())
+        {
+            let end = block_end(bytes, i + open.len(), open, close, syntax.nested);
+            spans.comments.push(i..end);
+            i = end;
+            continue;
+        }
+
+        if syntax.line.iter().any(|marker| rest.starts_with(marker.as_bytes()))
+            && (!syntax.word_start || i == 0 || bytes[i - 1].is_ascii_whitespace())
+        {
+            let end = line_end(bytes, i);
+            spans.comments.push(i..end);
+            i = end;
+            continue;
+        }
+
+        if let Some(end) = special_literal(bytes, i, syntax.flavor) {
+            spans.literals.push(i..end);
+            i = end;
+            continue;
+        }
+
+        if let Some(quote) = syntax.quotes.iter().find(|q| rest.starts_with(q.open.as_bytes())) {
+            let end = string_end(bytes, i + quote.open.len(), quote);
+            if syntax.flavor == Flavor::Python && quote.multiline && is_docstring(bytes, i, end) {
+                spans.comments.push(i..end);
+            } else {
+                spans.literals.push(i..end);
+            }
+            i = end;
+            continue;
+        }
+
+        i += 1;
+    }
+
+    spans
+}
+
+/// End of a block comment whose opener ends at `start`
+fn block_end(bytes: &[u8], start: usize, open: &str, close: &str, nested: bool) -> usize {
+    let mut depth = 1;
+    let mut i = start;
+
+    while i < bytes.len() {
+        if bytes[i..].starts_with(close.as_bytes()) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return i;
+            }
+        } else if nested && bytes[i..].starts_with(open.as_bytes()) {
+            depth += 1;
+            i += open.len();
+        } else {
+            i += 1;
+        }
+    }
+
+    bytes.len()
+}
+
+/// End of a string literal whose opener ends at `start`
+fn string_end(bytes: &[u8], start: usize, quote: &Quote) -> usize {
+    let mut i = start;
+
+    while i < bytes.len() {
+        if quote.escapes && bytes[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if bytes[i..].starts_with(quote.close.as_bytes()) {
+            return i + quote.close.len();
+        }
+        // An unterminated literal shouldn't swallow the rest of the file
+        if !quote.multiline && bytes[i] == b'\n' {
+            return i;
+        }
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+/// Whether a triple-quoted string is a docstring: alone on its lines, and
+/// either first in the file (after any comments) or right after a line
+/// ending in `:`
+fn is_docstring(bytes: &[u8], start: usize, end: usize) -> bool {
+    let line_start = bytes[..start].iter().rposition(|&b| b == b'\n').map_or(0, |p| p + 1);
+    if !bytes[line_start..start].iter().all(u8::is_ascii_whitespace) {
+        return false;
+    }
+    let after = &bytes[end..line_end(bytes, end)];
+    if !after.iter().all(u8::is_ascii_whitespace) {
+        return false;
+    }
+
+    let before = &bytes[..line_start];
+    let module_start = before
+        .split(|&b| b == b'\n')
+        .map(<[u8]>::trim_ascii)
+        .all(|line| line.is_empty() || line.starts_with(b"#"));
+    module_start || before.trim_ascii_end().ends_with(b":")
+}
+
+/// Bytes after which a `/` starts a regex rather than dividing. `<` is left
+/// out so a JSX closing tag like `</div>` isn't read as one, and `}` because
+/// it ends object literals as often as blocks. A regex right after a block is
+/// rare, and missing it costs less than reading a division as a regex.
+const REGEX_PRECEDERS: &[u8] = b"(,=:[!&|?{;+-*%>~^";
+
+/// Keywords after which a `/` starts a regex (`return /x/.test(s)`)
+const REGEX_KEYWORDS: &[&[u8]] = &[
+    b"return", b"typeof", b"instanceof", b"in", b"of", b"new", b"delete", b"void", b"throw",
+    b"case", b"do", b"else", b"yield", b"await",
+];
+
+/// Whether a `/` at `i` is where an expression starts, so it opens a regex literal
+///
+/// After a value (a name, a number, `)` or `]`) it's a division instead.
+fn regex_allowed(bytes: &[u8], i: usize) -> bool {
+    let before = bytes[..i].trim_ascii_end();
+    match before.last() {
+        None => true,
+        Some(byte) if REGEX_PRECEDERS.contains(byte) => true,
+        Some(&byte) if is_ident_byte(byte) => {
+            let word_start = before.iter().rposition(|&b| !is_ident_byte(b)).map_or(0, |p| p + 1);
+            REGEX_KEYWORDS.contains(&&before[word_start..])
+        }
+        _ => false,
+    }
+}
+
+/// End of a regex literal whose opening `/` is just before `start`, flags included
+///
+/// A `/` inside a character class doesn't close it. Regex literals can't span
+/// lines, so `None` when the line ends first.
+fn regex_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start;
+    let mut in_class = false;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'\n' | b'\r' => return None,
+            b'[' => in_class = true,
+            b']' => in_class = false,
+            b'/' if !in_class => {
+                let flags = bytes[i + 1..].iter().take_while(|b| b.is_ascii_alphabetic()).count();
+                return Some(i + 1 + flags);
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Literals with unusual delimiters, returns where the literal ends
+fn special_literal(bytes: &[u8], i: usize, flavor: Flavor) -> Option<usize> {
+    let rest = &bytes[i..];
+    let after_ident = i > 0 && is_ident_byte(bytes[i - 1]);
+
+    match flavor {
+        Flavor::Rust if !after_ident && (rest.starts_with(b"r") || rest.starts_with(b"br")) => {
+            // r"…", r#"…"#, br##"…"##
+            let start = i + if rest[0] == b'b' { 2 } else { 1 };
+            let hashes = bytes[start..].iter().take_while(|&&b| b == b'#').count();
+            if bytes.get(start + hashes) != Some(&b'"') {
+                return None;
+            }
+            let mut close = vec![b'"'];
+            close.extend(std::iter::repeat_n(b'#', hashes));
+            let body = start + hashes + 1;
+            let end = bytes[body..]
+                .windows(close.len())
+                .position(|w| w == close.as_slice())
+                .map_or(bytes.len(), |p| body + p + close.len());
+            Some(end)
+        }
+        Flavor::Rust if rest.starts_with(b"'") => {
+            // 'x' and '\n' are chars, 'a on its own is a lifetime or label
+            if rest.get(1) == Some(&b'\\') {
+                let close = rest[2..].iter().take(10).position(|&b| b == b'\'')?;
+                return Some(i + 2 + close + 1);
+            }
+            let c = std::str::from_utf8(&rest[1..rest.len().min(5)])
+                .or_else(|e| std::str::from_utf8(&rest[1..1 + e.valid_up_to()]))
+                .ok()?
+                .chars()
+                .next()?;
+            let after = 1 + c.len_utf8();
+            (rest.get(after) == Some(&b'\'')).then_some(i + after + 1)
+        }
+        Flavor::Cpp if rest.starts_with(b"R\"") => {
+            // R"delim( … )delim"
+            let open = rest[2..].iter().position(|&b| b == b'(')?;
+            let delimiter = &rest[2..2 + open];
+            if delimiter.len() > 16 || delimiter.iter().any(|b| b.is_ascii_whitespace()) {
+                return None;
+            }
+            let mut close = vec![b')'];
+            close.extend_from_slice(delimiter);
+            close.push(b'"');
+            let body = i + 2 + open + 1;
+            let end = bytes[body..]
+                .windows(close.len())
+                .position(|w| w == close.as_slice())
+                .map_or(bytes.len(), |p| body + p + close.len());
+            Some(end)
+        }
+        Flavor::CSharp if rest.starts_with(b"@\"") => {
+            let mut j = i + 2;
+            while j < bytes.len() {
+                if bytes[j] == b'"' {
+                    if bytes.get(j + 1) == Some(&b'"') {
+                        j += 2;
+                        continue;
+                    }
+                    return Some(j + 1);
+                }
+                j += 1;
+            }
+            Some(bytes.len())
+        }
+        Flavor::JavaScript if rest.starts_with(b"/") && regex_allowed(bytes, i) => regex_end(bytes, i + 1),
+        _ => None,
+    }
+}
+
+impl App {
+    /// Move on to the next transform preset (off, whitespace, compact)
+    pub fn cycle_transforms(&mut self) {
+        let transforms = self.export_options.transforms.next_preset();
+        self.export_options.transforms = transforms;
+
+        let message = if transforms.any() {
+            format!("Export transforms: {}", transforms.describe())
+        } else {
+            "Export transforms off, content is exported as-is".to_string()
+        };
+        self.set_success_message(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(content: &str, language: &str) -> String {
+        let transforms = ExportTransforms { strip_comments: true, ..Default::default() };
+        transform_lines(content, language, &transforms).into_iter().map(|(_, line)| line).collect()
+    }
+
+    #[test]
+    fn strips_comments_but_not_strings() {
+        let rust = "// header\nlet url = \"https://example.com\"; // trailing\n/* block /* nested */ */\nlet raw = r#\"// kept\"#;\n";
+        assert_eq!(strip(rust, "rust"), "let url = \"https://example.com\";\nlet raw = r#\"// kept\"#;\n");
+
+        let shell = "#!/bin/sh\n# comment\necho ${#list} a#b\n";
+        assert_eq!(strip(shell, "bash"), "#!/bin/sh\necho ${#list} a#b\n");
+    }
+
+    #[test]
+    fn multiline_strings_keep_their_slashes() {
+        let rust = "let usage = \"Usage:\nsee https://example.com/docs for more\"; // why\n";
+        assert_eq!(strip(rust, "rust"), "let usage = \"Usage:\nsee https://example.com/docs for more\";\n");
+
+        let ruby = "text = 'first\n# not a comment'\n# comment\n";
+        assert_eq!(strip(ruby, "ruby"), "text = 'first\n# not a comment'\n");
+
+        let csharp = "var s = @\"a\n// kept\"; // gone\n";
+        assert_eq!(strip(csharp, "csharp"), "var s = @\"a\n// kept\";\n");
+    }
+
+    #[test]
+    fn strips_python_docstrings_only() {
+        let python = "\"\"\"Module.\"\"\"\n\ndef f():\n    \"\"\"Doc.\"\"\"\n    return \"\"\"text\"\"\"  # why\n";
+        assert_eq!(strip(python, "python"), "def f():\n    return \"\"\"text\"\"\"\n");
+    }
+
+    #[test]
+    fn javascript_regex_literals_are_not_comments() {
+        let js = "const url = /https?:\\/\\//; // scheme\nif (/[/]/.test(s)) return /a\\/b/gi;\n";
+        assert_eq!(strip(js, "javascript"), "const url = /https?:\\/\\//;\nif (/[/]/.test(s)) return /a\\/b/gi;\n");
+    }
+
+    #[test]
+    fn javascript_division_is_not_a_regex() {
+        let js = "const half = total / 2; // comment\nconst ratio = (a) / b / c; // another\n";
+        assert_eq!(strip(js, "typescript"), "const half = total / 2;\nconst ratio = (a) / b / c;\n");
+    }
+
+    #[test]
+    fn javascript_division_after_a_brace_is_not_a_regex() {
+        let js = "x = {a:1}.a / 2 // note\ny = {a:1} / 2; z = 1 // note\n";
+        assert_eq!(strip(js, "javascript"), "x = {a:1}.a / 2\ny = {a:1} / 2; z = 1\n");
+    }
+
+    #[test]
+    fn elision_markers_survive_comment_stripping() {
+        let content = "// … lines 1-9 elided …\nfn f() {} // gone\n";
+        assert_eq!(strip(content, "rust"), "// … lines 1-9 elided …\nfn f() {}\n");
+    }
+
+    #[test]
+    fn strips_only_the_license_header() {
+        let transforms = ExportTransforms { strip_license: true, ..Default::default() };
+        let content = "// Copyright 2024 Acme\n// SPDX-License-Identifier: MIT\n\n// Explains f\nfn f() {}\n";
+        let lines = transform_lines(content, "rust", &transforms);
+        assert_eq!(lines, [(4, "// Explains f\n".to_string()), (5, "fn f() {}\n".to_string())]);
+    }
+
+    #[test]
+    fn whitespace_transforms_keep_line_numbers() {
+        let transforms = ExportTransforms {
+            collapse_blank_lines: true,
+            normalize_line_endings: true,
+            expand_tabs: Some(4),
+            ..Default::default()
+        };
+        let lines = transform_lines("\na\r\n\r\n\r\n\tb\n", "rust", &transforms);
+        assert_eq!(lines, [(2, "a\n".to_string()), (3, "\n".to_string()), (5, "    b\n".to_string())]);
+    }
+}
@@ -0,0 +1,728 @@
+//! Directory tree output for the RepoViewer application.
+//!
+//! The tree is built in two steps. First the directory is walked into a
+//! `TreeNode` model, using the same visibility rules as the file list and
+//! gathering whatever annotations were asked for. Then the model is rendered
+//! in one of the `TreeFormat`s: box-drawing text, plain ASCII, an indented
+//! list, a nested markdown list, or JSON for scripts.
+
+use super::App;
+use crate::{
+    app_error::AppError,
+    utils::{format_thousands, format_utc_timestamp, read_file_safely, MEGABYTE},
+};
+use serde::Serialize;
+use std::{
+    fs,
+    path::Path,
+    time::SystemTime,
+};
+
+/// How the tree is written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TreeFormat {
+    /// Box-drawing characters (`├──`), the classic look
+    #[default]
+    Unicode,
+    /// The same layout with ASCII only (`|--`), for places that mangle Unicode
+    Ascii,
+    /// Plain text indented by two spaces per level
+    Indented,
+    /// A nested markdown list
+    Markdown,
+    /// Nested JSON objects for scripts
+    Json,
+}
+
+impl TreeFormat {
+    /// Every format, in the order they're listed in help text
+    pub const ALL: [TreeFormat; 5] = [
+        TreeFormat::Unicode,
+        TreeFormat::Ascii,
+        TreeFormat::Indented,
+        TreeFormat::Markdown,
+        TreeFormat::Json,
+    ];
+
+    /// Look up a format by the name used on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.name() == name.to_ascii_lowercase())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TreeFormat::Unicode => "unicode",
+            TreeFormat::Ascii => "ascii",
+            TreeFormat::Indented => "indented",
+            TreeFormat::Markdown => "markdown",
+            TreeFormat::Json => "json",
+        }
+    }
+
+    /// File extension used when saving a tree in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TreeFormat::Markdown => "md",
+            TreeFormat::Json => "json",
+            _ => "txt",
+        }
+    }
+}
+
+/// Extra information shown next to each entry
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeAnnotations {
+    /// File sizes
+    pub size: bool,
+    /// Line counts of text files (needs every file to be read)
+    pub lines: bool,
+    /// File count and total size (and lines, if on) of every directory
+    pub dir_totals: bool,
+    /// Last-modified time of files
+    pub modified: bool,
+}
+
+/// Dependency and build directories, what `repoviewer tree --summarize-deps` summarizes
+///
+/// They're usually gitignored, but with gitignored files shown (`g`, `--all`)
+/// a single `node_modules` would bury the rest of the tree. Nothing is
+/// summarized unless asked for, a source directory that happens to be called
+/// `target` is listed like any other.
+pub const DEPENDENCY_DIRS: [&str; 5] = ["node_modules", "target", "__pycache__", ".venv", "venv"];
+
+/// Options for tree output (`t`, `c` and `repoviewer tree`)
+#[derive(Debug, Clone, Default)]
+pub struct TreeOptions {
+    pub format: TreeFormat,
+    pub annotations: TreeAnnotations,
+    /// List at most this many entries of any one directory
+    pub max_per_dir: Option<usize>,
+    /// List at most this many entries in the whole tree
+    pub max_entries: Option<usize>,
+    /// Directories (by name or path relative to the tree root) whose contents
+    /// are only counted, never listed
+    pub summarize: Vec<String>,
+}
+
+impl TreeOptions {
+    /// Whether a directory's contents should be summarized instead of listed
+    fn summarizes(&self, name: &str, rel_path: &str) -> bool {
+        self.summarize
+            .iter()
+            .map(|d| d.trim_end_matches('/'))
+            .any(|d| d == name || d == rel_path)
+    }
+}
+
+/// Entries of a directory that were counted but not listed
+#[derive(Debug, Clone, Copy, Default)]
+struct Omitted {
+    files: usize,
+    dirs: usize,
+    size: u64,
+}
+
+impl Omitted {
+    fn add(&mut self, other: Omitted) {
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.size += other.size;
+    }
+
+    fn is_empty(&self) -> bool {
+        self.files == 0 && self.dirs == 0
+    }
+
+    /// e.g. `… 1,204 more files (37 dirs)`, without "more" when nothing was listed
+    fn label(&self, more: bool) -> String {
+        let more = if more { "more " } else { "" };
+        let count = |n: usize, more: &str, noun: &str| {
+            format!("{} {}{}{}", format_thousands(n), more, noun, if n == 1 { "" } else { "s" })
+        };
+
+        match (self.files, self.dirs) {
+            (0, dirs) => format!("… {}", count(dirs, more, "dir")),
+            (files, 0) => format!("… {}", count(files, more, "file")),
+            (files, dirs) => format!("… {} ({})", count(files, more, "file"), count(dirs, "", "dir")),
+        }
+    }
+}
+
+/// State carried through one walk of the tree
+struct TreeWalk<'a> {
+    options: &'a TreeOptions,
+    max_depth: Option<usize>,
+    mark_collected: bool,
+    /// Entries listed so far, for `max_entries`
+    listed: usize,
+}
+
+/// One entry of the walked tree
+///
+/// Directory sizes, line counts and file counts are totals over the entries
+/// below them that made it into the tree (so a depth limit cuts them short too).
+struct TreeNode {
+    name: String,
+    /// Path relative to the tree root, always with `/`
+    path: String,
+    is_dir: bool,
+    collected: bool,
+    size: u64,
+    /// `None` for files that aren't text, or when line counts are off
+    lines: Option<usize>,
+    modified: Option<SystemTime>,
+    /// Files at or below this entry
+    files: usize,
+    children: Vec<TreeNode>,
+    /// What was left out below this directory because of the caps or summarizing
+    omitted: Omitted,
+}
+
+/// The characters a text format draws a tree with
+struct TreeStyle {
+    /// Before an entry that has siblings below it
+    branch: &'static str,
+    /// Before the last entry of a directory
+    last: &'static str,
+    /// Carried down below an entry that has siblings below it
+    pipe: &'static str,
+    /// Carried down below the last entry
+    blank: &'static str,
+    /// Before the root line
+    root: &'static str,
+    /// Wrap names in backticks
+    code_names: bool,
+}
+
+impl TreeStyle {
+    fn for_format(format: TreeFormat) -> Self {
+        let (branch, last, pipe, blank, root, code_names) = match format {
+            TreeFormat::Ascii => ("|-- ", "`-- ", "|   ", "    ", "", false),
+            TreeFormat::Indented => ("  ", "  ", "  ", "  ", "", false),
+            TreeFormat::Markdown => ("  - ", "  - ", "  ", "  ", "- ", true),
+            _ => ("├── ", "└── ", "│   ", "    ", "", false),
+        };
+        TreeStyle { branch, last, pipe, blank, root, code_names }
+    }
+}
+
+impl App {
+    /// Generate a tree of the current directory in the configured tree format
+    ///
+    /// This creates a visual representation of the directory structure which is
+    /// one of the major features of this program. Having the ability to easily get
+    /// the tree structure of repos is super helpful when providing context/metadata
+    /// about projects and was techincally the reason I made this! 🐈🐈🐈
+    pub fn generate_tree(&self, max_depth: Option<usize>) -> Result<String, AppError> {
+        // Start with a friendly display path instead of absolute path
+        // This makes the tree output cleaner and more focused on structure
+        // rather than system-specific paths
+        let root_name = self.get_display_path(&self.current_dir);
+        let options = &self.tree_options;
+        let mut walk = TreeWalk { options, max_depth, mark_collected: false, listed: 0 };
+        let root = self.build_tree_root(&self.current_dir, root_name, &mut walk)?;
+
+        self.render_tree(&root, options)
+    }
+
+    /// Generate the tree of the whole project for embedding in a collection export
+    ///
+    /// Unlike `generate_tree` this doesn't depend on where the user is browsing:
+    /// it's rooted at the git root (or the start directory) so it lines up with
+    /// the relative paths of the collected files, which are marked with `[+]`.
+    /// It's always plain box-drawing text without annotations, since it ends up
+    /// inside another document, but the caps and summarized directories apply.
+    pub fn generate_project_tree(&self) -> Result<String, AppError> {
+        let root_dir = self.git_root.as_ref().unwrap_or(&self.start_dir);
+        let root_name = format!("{}/", self.source_display_name());
+        let options = TreeOptions {
+            format: TreeFormat::Unicode,
+            annotations: TreeAnnotations::default(),
+            ..self.tree_options.clone()
+        };
+        let mut walk = TreeWalk { options: &options, max_depth: None, mark_collected: true, listed: 0 };
+        let root = self.build_tree_root(root_dir, root_name, &mut walk)?;
+
+        self.render_tree(&root, &options)
+    }
+
+    /// Walk `dir` into a root node named `name`
+    fn build_tree_root(&self, dir: &Path, name: String, walk: &mut TreeWalk) -> Result<TreeNode, AppError> {
+        let mut root = TreeNode {
+            name,
+            path: String::new(),
+            is_dir: true,
+            collected: false,
+            size: 0,
+            lines: None,
+            modified: None,
+            files: 0,
+            children: Vec::new(),
+            omitted: Omitted::default(),
+        };
+        self.build_tree_recursive(&mut root, dir, 0, walk)?;
+
+        Ok(root)
+    }
+
+    /// Visible entries of a directory, directories first and then by name
+    fn tree_entries(&self, dir: &Path) -> Result<Vec<fs::DirEntry>, AppError> {
+        // Read directory entries and filter based on visibility settings
+        let mut entries = fs::read_dir(dir)?
+            .filter_map(|entry_result| entry_result.ok())
+            .filter(|entry| {
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                let is_dir = path.is_dir();
+                // Respect the same visibility rules as the main file list, and
+                // leave out whatever .repoviewerignore keeps out of exports
+                name != ".git"
+                    && self.should_include_file(&path, &name, is_dir)
+                    && !self.is_export_ignored(&path, is_dir)
+            })
+            .collect::<Vec<fs::DirEntry>>();
+
+        // Sort entries: directories first, then alphabetically by name
+        // This consistent ordering makes the tree easier to read
+        entries.sort_by_key(|entry| (!entry.path().is_dir(), entry.file_name()));
+
+        Ok(entries)
+    }
+
+    /// Recursive helper that walks a directory into the children of `node`
+    ///
+    /// It handles:
+    /// - Respecting visibility settings (hidden files, gitignored files)
+    /// - Depth limiting to prevent extremely deep trees
+    /// - Sorting entries (directories first, then alphabetically)
+    /// - Optionally marking collected files with `[+]` like the file list does
+    /// - Gathering sizes, line counts and modified times for annotations
+    /// - Counting instead of listing entries past the caps and inside summarized directories
+    fn build_tree_recursive(
+        &self,
+        node: &mut TreeNode,
+        dir: &Path,
+        depth: usize,
+        walk: &mut TreeWalk,
+    ) -> Result<(), AppError> {
+        // Check if we've reached the maximum depth
+        if let Some(max) = walk.max_depth
+            && depth >= max
+        {
+            return Ok(());
+        }
+
+        let options = walk.options;
+        let rel_dir = if node.path.is_empty() { String::new() } else { format!("{}/", node.path) };
+
+        for (i, entry) in self.tree_entries(dir)?.into_iter().enumerate() {
+            let path = entry.path();
+            let is_dir = path.is_dir();
+
+            // Past a cap, entries are only counted
+            let over_dir_cap = options.max_per_dir.is_some_and(|max| i >= max);
+            let over_total_cap = options.max_entries.is_some_and(|max| walk.listed >= max);
+            if over_dir_cap || over_total_cap {
+                node.omitted.add(self.count_tree_entry(&path, is_dir));
+                continue;
+            }
+            walk.listed += 1;
+
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel_path = format!("{}{}", rel_dir, name);
+
+            if is_dir {
+                let summarize = options.summarizes(&name, &rel_path);
+                let mut child = TreeNode {
+                    name,
+                    path: rel_path,
+                    is_dir: true,
+                    collected: false,
+                    size: 0,
+                    lines: None,
+                    modified: None,
+                    files: 0,
+                    children: Vec::new(),
+                    omitted: Omitted::default(),
+                };
+
+                if summarize {
+                    child.omitted = self.count_tree_dir(&path);
+                    sum_children(&mut child);
+                } else {
+                    self.build_tree_recursive(&mut child, &path, depth + 1, walk)?;
+                }
+                node.children.push(child);
+            } else {
+                let metadata = entry.metadata().ok();
+
+                // Only text files RepoViewer could collect get a line count
+                let lines = options.annotations.lines
+                    .then(|| read_file_safely(&path, 10 * MEGABYTE).ok())
+                    .flatten()
+                    .map(|content| content.lines().count());
+
+                node.children.push(TreeNode {
+                    name,
+                    path: rel_path,
+                    is_dir: false,
+                    collected: walk.mark_collected && self.is_collected(&path),
+                    size: metadata.as_ref().map_or(0, |m| m.len()),
+                    lines,
+                    modified: metadata.and_then(|m| m.modified().ok()),
+                    files: 1,
+                    children: Vec::new(),
+                    omitted: Omitted::default(),
+                });
+            }
+        }
+
+        sum_children(node);
+        Ok(())
+    }
+
+    /// Count one entry that won't be listed (a directory counts with everything in it)
+    fn count_tree_entry(&self, path: &Path, is_dir: bool) -> Omitted {
+        if is_dir {
+            let mut counts = self.count_tree_dir(path);
+            counts.dirs += 1;
+            counts
+        } else {
+            let size = fs::metadata(path).map_or(0, |m| m.len());
+            Omitted { files: 1, dirs: 0, size }
+        }
+    }
+
+    /// Count the visible files and directories below `dir`
+    ///
+    /// Symlinked directories aren't followed, so a link loop can't hang the walk.
+    fn count_tree_dir(&self, dir: &Path) -> Omitted {
+        let mut counts = Omitted::default();
+        let Ok(entries) = self.tree_entries(dir) else {
+            return counts;
+        };
+
+        for entry in entries {
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            counts.add(self.count_tree_entry(&entry.path(), is_dir));
+        }
+
+        counts
+    }
+
+    /// Render a walked tree in the given format
+    fn render_tree(&self, root: &TreeNode, options: &TreeOptions) -> Result<String, AppError> {
+        if options.format == TreeFormat::Json {
+            let mut output = serde_json::to_string_pretty(&tree_json(root, &options.annotations))?;
+            output.push('\n');
+            return Ok(output);
+        }
+
+        let style = TreeStyle::for_format(options.format);
+        let mut output = String::new();
+        output.push_str(style.root);
+        output.push_str(&self.tree_label(root, &style, &options.annotations));
+        output.push('\n');
+        self.render_tree_recursive(root, &style, &options.annotations, "", &mut output);
+
+        Ok(output)
+    }
+
+    /// Draw the children of `node` with the prefix built up so far
+    fn render_tree_recursive(
+        &self,
+        node: &TreeNode,
+        style: &TreeStyle,
+        annotations: &TreeAnnotations,
+        prefix: &str,
+        output: &mut String,
+    ) {
+        let has_omitted = !node.omitted.is_empty();
+        let total_entries = node.children.len() + usize::from(has_omitted);
+
+        for (i, child) in node.children.iter().enumerate() {
+            let is_last_entry = i == total_entries - 1;
+            let connector = if is_last_entry { style.last } else { style.branch };
+
+            output.push_str(prefix);
+            output.push_str(connector);
+            output.push_str(&self.tree_label(child, style, annotations));
+            output.push('\n');
+
+            if child.is_dir {
+                // The prefix maintains the tree structure visually
+                let extension = if is_last_entry { style.blank } else { style.pipe };
+                let new_prefix = format!("{}{}", prefix, extension);
+                self.render_tree_recursive(child, style, annotations, &new_prefix, output);
+            }
+        }
+
+        // Whatever was only counted goes last, as a single summary line
+        if has_omitted {
+            output.push_str(prefix);
+            output.push_str(style.last);
+            output.push_str(&node.omitted.label(!node.children.is_empty()));
+            output.push('\n');
+        }
+    }
+
+    /// An entry's line without the tree drawing, e.g. `[+] main.rs (1.20 KB, 40 lines)`
+    fn tree_label(&self, node: &TreeNode, style: &TreeStyle, annotations: &TreeAnnotations) -> String {
+        let mut name = node.name.clone();
+        if node.is_dir && !name.ends_with('/') {
+            name.push('/');
+        }
+        if style.code_names {
+            name = format!("`{}`", name);
+        }
+
+        let mut label = if node.collected { format!("[+] {}", name) } else { name };
+
+        let details = self.tree_details(node, annotations);
+        if !details.is_empty() {
+            label.push_str(&format!(" ({})", details.join(", ")));
+        }
+
+        label
+    }
+
+    /// The annotations for an entry as display strings
+    fn tree_details(&self, node: &TreeNode, annotations: &TreeAnnotations) -> Vec<String> {
+        let mut details = Vec::new();
+        let show = if node.is_dir { annotations.dir_totals } else { true };
+        if !show {
+            return details;
+        }
+
+        if node.is_dir {
+            details.push(plural(node.files, "file"));
+            details.push(self.format_size(node.size as usize));
+        } else if annotations.size {
+            details.push(self.format_size(node.size as usize));
+        }
+
+        if annotations.lines
+            && let Some(lines) = node.lines
+        {
+            details.push(plural(lines, "line"));
+        }
+
+        if annotations.modified
+            && !node.is_dir
+            && let Some(modified) = node.modified
+        {
+            details.push(format!("modified {}", format_modified(modified)));
+        }
+
+        details
+    }
+}
+
+/// Add up the totals of a directory's children, including the ones only counted
+fn sum_children(node: &mut TreeNode) {
+    node.size = node.children.iter().map(|c| c.size).sum::<u64>() + node.omitted.size;
+    node.files = node.children.iter().map(|c| c.files).sum::<usize>() + node.omitted.files;
+    node.lines = node.children
+        .iter()
+        .filter_map(|c| c.lines)
+        .reduce(|a, b| a + b);
+}
+
+/// `1 file`, `3 files`
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+fn format_modified(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_utc_timestamp(secs)
+}
+
+/// One node of the JSON tree
+///
+/// Annotation fields only show up when they're turned on, sizes are in bytes
+/// and times are unix seconds so scripts don't have to parse anything.
+#[derive(Serialize)]
+struct JsonTreeNode<'a> {
+    name: &'a str,
+    path: &'a str,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    collected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<JsonTreeNode<'a>>>,
+    /// Entries below this directory that were counted but not listed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    omitted: Option<JsonOmitted>,
+}
+
+#[derive(Serialize)]
+struct JsonOmitted {
+    files: usize,
+    dirs: usize,
+}
+
+/// JSON for a node and everything below it
+fn tree_json<'a>(node: &'a TreeNode, annotations: &TreeAnnotations) -> JsonTreeNode<'a> {
+    // Directories only carry numbers when totals are on
+    let show_numbers = !node.is_dir || annotations.dir_totals;
+
+    JsonTreeNode {
+        name: node.name.trim_end_matches('/'),
+        path: &node.path,
+        kind: if node.is_dir { "dir" } else { "file" },
+        collected: node.collected,
+        files: (node.is_dir && annotations.dir_totals).then_some(node.files),
+        size: (show_numbers && (annotations.size || node.is_dir)).then_some(node.size),
+        lines: node.lines.filter(|_| show_numbers && annotations.lines),
+        modified: node.modified
+            .filter(|_| annotations.modified && !node.is_dir)
+            .map(|time| time.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)),
+        children: node.is_dir.then(|| node.children.iter().map(|c| tree_json(c, annotations)).collect()),
+        omitted: (!node.omitted.is_empty()).then_some(JsonOmitted {
+            files: node.omitted.files,
+            dirs: node.omitted.dirs,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> (tempfile::TempDir, App) {
+        let dir = tempfile::tempdir().unwrap();
+        let files = [
+            ("src/main.rs", "fn main() {}\n"),
+            ("src/lib.rs", "pub mod app;\npub mod ui;\n"),
+            ("vendor/zlib/inflate.c", "int inflate;\n"),
+            ("README.md", "# Project\n"),
+        ];
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let app = App::new(dir.path().to_path_buf()).unwrap();
+        (dir, app)
+    }
+
+    fn tree(app: &mut App, format: TreeFormat) -> String {
+        app.tree_options.format = format;
+        app.generate_tree(None).unwrap()
+    }
+
+    #[test]
+    fn text_formats() {
+        let (_dir, mut app) = project();
+        let root = app.get_display_path(&app.current_dir);
+
+        assert_eq!(
+            tree(&mut app, TreeFormat::Unicode),
+            format!("{root}\n├── src/\n│   ├── lib.rs\n│   └── main.rs\n├── vendor/\n│   └── zlib/\n│       └── inflate.c\n└── README.md\n")
+        );
+        assert_eq!(
+            tree(&mut app, TreeFormat::Ascii),
+            format!("{root}\n|-- src/\n|   |-- lib.rs\n|   `-- main.rs\n|-- vendor/\n|   `-- zlib/\n|       `-- inflate.c\n`-- README.md\n")
+        );
+        assert_eq!(
+            tree(&mut app, TreeFormat::Indented),
+            format!("{root}\n  src/\n    lib.rs\n    main.rs\n  vendor/\n    zlib/\n      inflate.c\n  README.md\n")
+        );
+        assert_eq!(
+            tree(&mut app, TreeFormat::Markdown),
+            format!("- `{root}`\n  - `src/`\n    - `lib.rs`\n    - `main.rs`\n  - `vendor/`\n    - `zlib/`\n      - `inflate.c`\n  - `README.md`\n")
+        );
+    }
+
+    #[test]
+    fn json_format() {
+        let (_dir, mut app) = project();
+        app.tree_options.annotations = TreeAnnotations { size: true, lines: true, dir_totals: true, modified: false };
+        let json: serde_json::Value = serde_json::from_str(&tree(&mut app, TreeFormat::Json)).unwrap();
+
+        assert_eq!(json["type"], "dir");
+        assert_eq!(json["files"], 4);
+        assert_eq!(json["lines"], 5);
+        let src = &json["children"][0];
+        assert_eq!(src["path"], "src");
+        assert_eq!(src["children"][1]["path"], "src/main.rs");
+        assert_eq!(src["children"][1]["type"], "file");
+        assert_eq!(src["children"][1]["size"], 13);
+        assert!(src["children"][1].get("modified").is_none());
+        assert!(json.get("omitted").is_none());
+    }
+
+    #[test]
+    fn annotations_follow_the_options() {
+        let (_dir, mut app) = project();
+        app.tree_options.annotations = TreeAnnotations { size: false, lines: true, dir_totals: true, modified: false };
+        let output = tree(&mut app, TreeFormat::Unicode);
+
+        assert!(output.contains("├── src/ (2 files, "), "{}", output);
+        assert!(output.contains("│   ├── lib.rs (2 lines)\n"), "{}", output);
+        assert!(output.contains("└── README.md (1 line)\n"), "{}", output);
+    }
+
+    #[test]
+    fn capped_and_summarized_entries_get_one_label() {
+        let (_dir, mut app) = project();
+        app.tree_options.max_per_dir = Some(1);
+        let output = tree(&mut app, TreeFormat::Unicode);
+        assert!(output.contains("│   ├── lib.rs\n│   └── … 1 more file\n"), "{}", output);
+        assert!(output.ends_with("└── … 2 more files (2 dirs)\n"), "{}", output);
+
+        app.tree_options.max_per_dir = None;
+        app.tree_options.summarize = vec!["vendor".to_string()];
+        let output = tree(&mut app, TreeFormat::Unicode);
+        assert!(output.contains("├── vendor/\n│   └── … 1 file (1 dir)\n"), "{}", output);
+
+        app.tree_options.summarize.clear();
+        app.tree_options.max_entries = Some(2);
+        let json: serde_json::Value = serde_json::from_str(&tree(&mut app, TreeFormat::Json)).unwrap();
+        assert_eq!(json["children"][0]["children"][0]["path"], "src/lib.rs");
+        assert_eq!(json["children"][0]["omitted"]["files"], 1);
+        assert_eq!(json["omitted"]["files"], 2);
+        assert_eq!(json["omitted"]["dirs"], 2);
+    }
+
+    #[test]
+    fn dependency_dirs_are_only_summarized_when_asked() {
+        let (dir, mut app) = project();
+        fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        fs::write(dir.path().join("target/debug/app"), "").unwrap();
+
+        let output = tree(&mut app, TreeFormat::Unicode);
+        assert!(output.contains("├── target/\n│   └── debug/\n│       └── app\n"), "{}", output);
+
+        app.tree_options.summarize = DEPENDENCY_DIRS.iter().map(|d| d.to_string()).collect();
+        let output = tree(&mut app, TreeFormat::Unicode);
+        assert!(output.contains("├── target/\n│   └── … 1 file (1 dir)\n"), "{}", output);
+    }
+
+    #[test]
+    fn omitted_label_counts() {
+        let omitted = |files, dirs| Omitted { files, dirs, size: 0 };
+        assert_eq!(omitted(1204, 37).label(true), "… 1,204 more files (37 dirs)");
+        assert_eq!(omitted(1, 1).label(false), "… 1 file (1 dir)");
+        assert_eq!(omitted(0, 3).label(true), "… 3 more dirs");
+        assert_eq!(omitted(2, 0).label(false), "… 2 files");
+    }
+}
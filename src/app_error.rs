@@ -22,6 +22,10 @@ pub enum AppError {
     #[error("Ignore pattern error: {0}")]
     Ignore(#[from] ignore::Error),
 
+    /// JSON serialization errors (used by the JSON export)
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
     /// Clipboard operation errors
     #[cfg(feature = "clipboard")]
     #[error("Clipboard error: {0}")]
@@ -61,6 +65,66 @@ pub enum AppError {
     #[error("File encoding error: too many invalid UTF-8 sequences")]
     EncodingError,
 
+    /// The file matches a `.repoviewerignore` pattern
+    #[error("Excluded from exports by .repoviewerignore")]
+    ExportIgnored,
+
+    /// Outlines can't be built for the file's language
+    #[error("Outlines aren't supported for {0} files")]
+    OutlineUnsupported(String),
+
+    /// A line range like `10-20` couldn't be parsed or doesn't fit the file
+    #[error("Invalid line range: {0}")]
+    InvalidRange(String),
+
+    /// Symbols can't be looked up in the file's language
+    #[error("Finding symbols isn't supported for {0} files")]
+    SymbolsUnsupported(String),
+
+    /// No definition with the given name was found in the file
+    #[error("No definition of `{0}` found")]
+    SymbolNotFound(String),
+
+    /// An export template file could not be parsed
+    #[error("Template error: {0}")]
+    TemplateError(String),
+
+    /// The requested export format isn't registered
+    #[error("Unknown export format '{name}' (available: {available})")]
+    UnknownExportFormat { name: String, available: String },
+
+    /// The requested tokenizer isn't known or wasn't compiled in
+    #[error("Tokenizer unavailable: {0}")]
+    TokenizerUnavailable(String),
+
+    /// `anonymize.toml` or the anonymization mapping couldn't be used
+    #[error("Anonymization error: {0}")]
+    AnonymizeError(String),
+
+    /// A glob pattern given on the command line couldn't be parsed
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+
+    /// A language name that no file type maps to
+    #[error("Unknown language '{0}'")]
+    UnknownLanguage(String),
+
+    /// Files couldn't be collected and the caller asked for that to be an error
+    #[error("{0} file(s) were skipped")]
+    FilesSkipped(usize),
+
+    /// A headless collection ended up empty
+    #[error("No files were collected")]
+    NothingCollected,
+
+    /// Possible secrets were found and the policy is to block the export
+    #[error("Export blocked: {0} possible secret(s) found")]
+    SecretsFound(usize),
+
+    /// An operation that needs git was used outside a repository
+    #[error("Not in a git repository: {0}")]
+    NotInGitRepo(String),
+
     /// Invalid path provided or determined
     #[error("Invalid path: {0}")]
     InvalidPath(String),
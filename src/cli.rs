@@ -0,0 +1,512 @@
+//! Command line interface
+//!
+//! RepoViewer has a handful of modes (the TUI, tree output, headless
+//! collection, self-updating), and each one gets its own subcommand with only
+//! the options that make sense for it. Running without a subcommand opens the
+//! TUI, so `repoviewer ./project` keeps working.
+//!
+//! The flags from before subcommands existed (`--tree`, `--update`,
+//! `--check-update`, `--version`...) are still accepted as hidden aliases.
+//! They print a deprecation warning and are turned into the matching
+//! subcommand by `Args::into_command`.
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    app::{App, DiffBase, DirFilter, ExportTransforms, SecretPolicy, TreeAnnotations, DEPENDENCY_DIRS, TreeFormat, TreeOptions, MIN_PART_SIZE},
+    app_error::AppError,
+    tokens::Tokenizer,
+};
+
+/// How deep `repoviewer tree` goes unless told otherwise
+pub const DEFAULT_TREE_DEPTH: usize = 10;
+
+/// RepoViewer - A TUI file explorer for generating directory trees for LLMs
+#[derive(Parser, Debug)]
+#[command(name = "repoviewer")]
+#[command(about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Options for the TUI when no subcommand is given
+    #[command(flatten)]
+    pub browse: BrowseArgs,
+
+    #[command(flatten)]
+    pub legacy: LegacyArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Browse a directory and collect files in the TUI (the default)
+    Browse(BrowseArgs),
+    /// Print the directory tree and exit
+    Tree(TreeArgs),
+    /// Collect files without the TUI and write the export to stdout or a file
+    Collect(CollectArgs),
+    /// Put the original names back into anonymized text, such as a model's answer
+    Deanonymize(DeanonymizeArgs),
+    /// Update to the latest version
+    Update(UpdateArgs),
+    /// Check for updates without installing
+    CheckUpdate,
+    /// Show the current version
+    Version,
+}
+
+/// Options for `repoviewer browse`
+#[derive(clap::Args, Debug)]
+pub struct BrowseArgs {
+    /// Target directory (default: current directory)
+    pub path: Option<String>,
+
+    /// Start with hidden files shown (toggle with 'h')
+    #[arg(long)]
+    pub hidden: bool,
+
+    /// Start with gitignored files shown (toggle with 'g')
+    #[arg(long)]
+    pub all: bool,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+
+    /// Warn once the collection grows past this many tokens (replaces the 25MB warning)
+    #[arg(long, value_name = "TOKENS")]
+    pub warn_tokens: Option<usize>,
+
+    /// Show a critical warning past this many tokens (replaces the 50MB warning)
+    #[arg(long, value_name = "TOKENS")]
+    pub critical_tokens: Option<usize>,
+
+    /// Split collection exports bigger than this many bytes into numbered parts
+    /// (cycle presets with 'P')
+    #[arg(long, value_name = "BYTES", value_parser = parse_part_size)]
+    pub part_size: Option<usize>,
+
+    /// Only add files matching this glob when adding a directory recursively
+    /// with 'W' (repeatable, e.g. '*.rs' or 'src/**/*.ts')
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+
+    /// Leave out files and directories matching this glob when adding a
+    /// directory recursively (repeatable, e.g. 'tests' or '*.min.js')
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+
+    /// How deep adding a directory recursively goes, 1 is only its own files
+    /// (cycle with 'd' in the preview)
+    #[arg(long, value_name = "N", value_parser = parse_depth)]
+    pub max_depth: Option<usize>,
+
+    /// Only add files of this language when adding a directory recursively,
+    /// e.g. rust or python (cycle with 'l' in the preview)
+    #[arg(long, value_name = "LANGUAGE")]
+    pub lang: Option<String>,
+}
+
+impl BrowseArgs {
+    /// What adding a directory recursively picks up
+    pub fn dir_filter(&self) -> Result<DirFilter, AppError> {
+        let filter = DirFilter {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            max_depth: self.max_depth,
+            language: self.lang.clone(),
+        };
+        filter.validate()?;
+        Ok(filter)
+    }
+}
+
+/// Options for `repoviewer tree`
+#[derive(clap::Args, Debug)]
+pub struct TreeArgs {
+    /// Target directory (default: current directory)
+    pub path: Option<String>,
+
+    /// Maximum depth of the tree
+    #[arg(short, long, default_value_t = DEFAULT_TREE_DEPTH, value_parser = parse_depth)]
+    pub depth: usize,
+
+    /// Show hidden files
+    #[arg(long)]
+    pub hidden: bool,
+
+    /// Show gitignored files
+    #[arg(long)]
+    pub all: bool,
+
+    /// Output format: unicode, ascii, indented, markdown or json
+    #[arg(short, long, default_value = "unicode", value_parser = parse_tree_format)]
+    pub format: TreeFormat,
+
+    /// Show file sizes
+    #[arg(short, long)]
+    pub size: bool,
+
+    /// Show line counts of text files
+    #[arg(short, long)]
+    pub lines: bool,
+
+    /// Show the file count and total size of every directory
+    #[arg(long)]
+    pub totals: bool,
+
+    /// Show when files were last modified
+    #[arg(short, long)]
+    pub modified: bool,
+
+    /// List at most this many entries per directory, summarizing the rest
+    #[arg(long, value_name = "N", value_parser = parse_limit)]
+    pub max_per_dir: Option<usize>,
+
+    /// List at most this many entries in total, summarizing the rest
+    #[arg(long, value_name = "N", value_parser = parse_limit)]
+    pub max_entries: Option<usize>,
+
+    /// Only count the contents of this directory (name or relative path, repeatable)
+    #[arg(long, value_name = "DIR")]
+    pub summarize: Vec<String>,
+
+    /// Only count the contents of dependency and build directories:
+    /// node_modules, target, __pycache__, .venv, venv
+    #[arg(long)]
+    pub summarize_deps: bool,
+
+    /// Write the tree to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+impl TreeArgs {
+    /// The tree options these arguments ask for
+    pub fn tree_options(&self) -> TreeOptions {
+        let mut summarize = if self.summarize_deps {
+            DEPENDENCY_DIRS.iter().map(|d| d.to_string()).collect()
+        } else {
+            Vec::new()
+        };
+        summarize.extend(self.summarize.iter().cloned());
+
+        TreeOptions {
+            format: self.format,
+            annotations: TreeAnnotations {
+                size: self.size,
+                lines: self.lines,
+                dir_totals: self.totals,
+                modified: self.modified,
+            },
+            max_per_dir: self.max_per_dir,
+            max_entries: self.max_entries,
+            summarize,
+        }
+    }
+}
+
+/// Options for `repoviewer collect`
+#[derive(clap::Args, Debug)]
+pub struct CollectArgs {
+    /// Files, directories or glob patterns (quote them, e.g. 'src/**/*.rs') to collect.
+    /// Collect part of a file with 'src/lib.rs:10-40,90-120' or 'src/lib.rs#Config::load'
+    #[arg(required_unless_present = "stdin")]
+    pub inputs: Vec<String>,
+
+    /// Also read newline-separated paths from stdin (e.g. piped from `git ls-files`)
+    #[arg(long)]
+    pub stdin: bool,
+
+    /// Directory that relative paths and globs are resolved against (default: current directory)
+    #[arg(short = 'C', long, value_name = "DIR")]
+    pub root: Option<String>,
+
+    /// Write the export to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Include hidden files when expanding directories and globs
+    #[arg(long)]
+    pub hidden: bool,
+
+    /// Include gitignored files when expanding directories and globs
+    #[arg(long)]
+    pub all: bool,
+
+    /// Collect files as outlines (signatures with bodies elided), all of them or
+    /// only those matching a glob: --outline='src/**/*.rs'
+    #[arg(long, value_name = "GLOB", num_args = 0..=1, require_equals = true, default_missing_value = "**")]
+    pub outline: Option<String>,
+
+    /// Exit with an error if any file was skipped (the export is still written)
+    #[arg(long)]
+    pub fail_on_skip: bool,
+
+    /// Don't print the summary and skipped files to stderr
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    #[command(flatten)]
+    pub export: ExportArgs,
+}
+
+/// Options for `repoviewer deanonymize`
+#[derive(clap::Args, Debug)]
+pub struct DeanonymizeArgs {
+    /// File with the text to restore (default: stdin)
+    pub input: Option<PathBuf>,
+
+    /// Project whose anonymization mapping to use (default: current directory)
+    #[arg(short = 'C', long, value_name = "DIR")]
+    pub root: Option<String>,
+
+    /// Write the restored text to this file instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// Options for `repoviewer update`
+#[derive(clap::Args, Debug)]
+pub struct UpdateArgs {
+    /// Don't prompt for confirmation
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// Export options shared by the TUI and `collect`
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    /// Format used when exporting the collection: markdown, xml, json, diff or the
+    /// name of a user template (can be cycled with 'f' in the TUI)
+    #[arg(short, long, alias = "template", default_value = "markdown")]
+    pub format: String,
+
+    /// Include the project tree at the top of collection exports (toggle with 'T')
+    #[arg(long)]
+    pub include_tree: bool,
+
+    /// How to count tokens: heuristic (fast estimate), or cl100k / o200k when
+    /// built with the `tokenizer` feature
+    #[arg(long, default_value = "heuristic")]
+    pub tokenizer: String,
+
+    /// Fit collection exports into this many tokens, keeping pinned files whole
+    /// and truncating or dropping the rest (cycle presets with 'b'). The diff
+    /// export isn't fitted
+    #[arg(long, value_name = "TOKENS")]
+    pub budget: Option<usize>,
+
+    /// What the diff export (`--format diff`) compares against: HEAD (default),
+    /// index, or any ref such as main or HEAD~3
+    #[arg(long, value_name = "BASE", default_value = "HEAD")]
+    pub diff_base: String,
+
+    /// Include each file's full content after its diff in the diff export
+    #[arg(long)]
+    pub diff_content: bool,
+
+    /// Include repository metadata (branch, commit, status, remote) in exports
+    /// (toggle with 'M')
+    #[arg(long)]
+    pub metadata: bool,
+
+    /// Prefix exported lines with line numbers (toggle with 'L')
+    #[arg(long)]
+    pub line_numbers: bool,
+
+    /// Show size, line count, language and modified time above each exported file
+    /// (toggle with 'I')
+    #[arg(long)]
+    pub file_info: bool,
+
+    /// Group exported files under directory headings with a table of contents
+    /// (toggle with 'O'). Markdown and templates only, not xml, json or diff
+    #[arg(long)]
+    pub group_by_dir: bool,
+
+    /// What to do when an export contains possible secrets: ask (the TUI default),
+    /// block (the collect default), redact or proceed
+    #[arg(long, value_name = "POLICY", value_parser = parse_secret_policy)]
+    pub secrets: Option<SecretPolicy>,
+
+    /// Replace the terms from anonymize.toml with placeholders in exports (toggle with 'N')
+    #[arg(long)]
+    pub anonymize: bool,
+
+    /// Transform exported content, a comma-separated list of comments, license,
+    /// blank-lines, line-endings and tabs[=N], or a preset: whitespace, compact
+    /// (cycle presets with 'X')
+    #[arg(long, value_name = "LIST", value_parser = ExportTransforms::parse)]
+    pub transform: Option<ExportTransforms>,
+}
+
+/// The flags from before subcommands, kept so old scripts don't break
+#[derive(clap::Args, Debug)]
+pub struct LegacyArgs {
+    /// Deprecated: use `repoviewer tree`
+    #[arg(short, long, hide = true)]
+    pub tree: bool,
+
+    /// Deprecated: use `repoviewer tree --depth`
+    #[arg(short, long, hide = true)]
+    pub depth: Option<usize>,
+
+    /// Deprecated: use `repoviewer check-update`
+    #[arg(long, hide = true)]
+    pub check_update: bool,
+
+    /// Deprecated: use `repoviewer update`
+    #[arg(long, hide = true)]
+    pub update: bool,
+
+    /// Deprecated: use `repoviewer update --yes`
+    #[arg(long, hide = true, requires = "update")]
+    pub yes: bool,
+
+    /// Deprecated: use `repoviewer version`
+    #[arg(short = 'V', long, hide = true)]
+    pub version: bool,
+}
+
+impl Args {
+    /// Work out which command to run, translating the deprecated flags
+    ///
+    /// The flags keep their old precedence: version, then check-update, then
+    /// update, then tree.
+    pub fn into_command(self) -> Command {
+        if let Some(command) = self.command {
+            return command;
+        }
+
+        let Args { browse, legacy, .. } = self;
+
+        if legacy.version {
+            deprecated("--version", "repoviewer version");
+            return Command::Version;
+        }
+
+        if legacy.check_update {
+            deprecated("--check-update", "repoviewer check-update");
+            return Command::CheckUpdate;
+        }
+
+        if legacy.update {
+            deprecated("--update", "repoviewer update");
+            return Command::Update(UpdateArgs { yes: legacy.yes });
+        }
+
+        if legacy.tree {
+            deprecated("--tree", "repoviewer tree");
+            return Command::Tree(TreeArgs {
+                path: browse.path,
+                depth: legacy.depth.unwrap_or(DEFAULT_TREE_DEPTH),
+                hidden: browse.hidden,
+                all: browse.all,
+                format: TreeFormat::default(),
+                size: false,
+                lines: false,
+                totals: false,
+                modified: false,
+                max_per_dir: None,
+                max_entries: None,
+                summarize: Vec::new(),
+                summarize_deps: false,
+                output: None,
+            });
+        }
+
+        if legacy.depth.is_some() {
+            eprintln!("Warning: --depth only applies to `repoviewer tree`, ignoring it");
+        }
+
+        Command::Browse(browse)
+    }
+}
+
+/// Tell the user a flag is on its way out
+fn deprecated(flag: &str, replacement: &str) {
+    eprintln!("Warning: {} is deprecated, use `{}` instead", flag, replacement);
+}
+
+impl ExportArgs {
+    /// Set up the app's exporter, export options and token counting
+    pub fn apply(&self, app: &mut App) -> Result<(), AppError> {
+        app.exporters.select(&self.format)?;
+        app.export_options.include_tree = self.include_tree;
+        app.export_options.diff_base = DiffBase::parse(&self.diff_base);
+        app.export_options.diff_content = self.diff_content;
+        app.export_options.include_metadata = self.metadata;
+        app.export_options.line_numbers = self.line_numbers;
+        app.export_options.file_info = self.file_info;
+        app.export_options.group_by_directory = self.group_by_dir;
+        if let Some(policy) = self.secrets {
+            app.secret_policy = policy;
+        }
+        if self.anonymize {
+            if let Some(e) = &app.anonymize_error {
+                return Err(AppError::AnonymizeError(format!(
+                    "exports would NOT be anonymized, anonymize.toml failed to load: {}",
+                    e
+                )));
+            }
+            if !app.anonymizer.as_ref().is_some_and(|a| a.has_terms()) {
+                return Err(AppError::AnonymizeError(
+                    "--anonymize needs terms in .repoviewer/anonymize.toml".to_string(),
+                ));
+            }
+            app.export_options.anonymize = true;
+        }
+        if let Some(transforms) = self.transform {
+            app.export_options.transforms = transforms;
+        }
+        app.tokenizer = Tokenizer::from_name(&self.tokenizer)?;
+        app.token_budget = self.budget;
+        Ok(())
+    }
+}
+
+/// Validate `--part-size` so parts have room for more than their header
+fn parse_part_size(value: &str) -> Result<usize, String> {
+    let size: usize = value.parse().map_err(|e| format!("{}", e))?;
+    if size < MIN_PART_SIZE {
+        return Err(format!("part size must be at least {} bytes", MIN_PART_SIZE));
+    }
+    Ok(size)
+}
+
+/// Parse `tree --format`, listing the formats when the name is unknown
+fn parse_tree_format(value: &str) -> Result<TreeFormat, String> {
+    TreeFormat::from_name(value).ok_or_else(|| {
+        let names: Vec<&str> = TreeFormat::ALL.iter().map(|f| f.name()).collect();
+        format!("unknown tree format (available: {})", names.join(", "))
+    })
+}
+
+/// Parse `--secrets`, listing the policies when the name is unknown
+fn parse_secret_policy(value: &str) -> Result<SecretPolicy, String> {
+    SecretPolicy::from_name(value).ok_or_else(|| {
+        let names: Vec<&str> = SecretPolicy::ALL.iter().map(|p| p.name()).collect();
+        format!("unknown secrets policy (available: {})", names.join(", "))
+    })
+}
+
+/// Validate `--max-per-dir` and `--max-entries`, a limit of 0 would list nothing
+fn parse_limit(value: &str) -> Result<usize, String> {
+    let limit: usize = value.parse().map_err(|e| format!("{}", e))?;
+    if limit == 0 {
+        return Err("limit must be at least 1".to_string());
+    }
+    Ok(limit)
+}
+
+/// Validate `tree --depth`, a depth of 0 would print nothing but the root
+fn parse_depth(value: &str) -> Result<usize, String> {
+    let depth: usize = value.parse().map_err(|e| format!("{}", e))?;
+    if depth == 0 {
+        return Err("depth must be at least 1".to_string());
+    }
+    Ok(depth)
+}
@@ -54,6 +54,13 @@ pub mod enabled {
             }
         }
         
+        /// Read the text currently on the clipboard
+        pub fn get_text() -> Result<String, AppError> {
+            let mut clipboard = Clipboard::new()?;
+
+            Ok(clipboard.get_text()?)
+        }
+
         /// Direct clipboard write for platforms with centralized clipboard
         fn set_text_direct(text: String) -> Result<(), AppError> {
 
@@ -111,6 +118,8 @@ pub mod enabled {
 /// Fallback implementation when clipboard feature is disabled
 #[cfg(not(feature = "clipboard"))]
 pub mod disabled {
+    use crate::app_error::AppError;
+
     pub struct ClipboardManager;
 
     impl ClipboardManager {
@@ -119,6 +128,12 @@ pub mod disabled {
                 "Clipboard support not compiled. Use --features clipboard".to_string(),
             ))
         }
+
+        pub fn get_text() -> Result<String, AppError> {
+            Err(AppError::UnsupportedOperation(
+                "Clipboard support not compiled. Use --features clipboard".to_string(),
+            ))
+        }
     }
 
 }
@@ -0,0 +1,316 @@
+//! The ignore rules git applies to a repository.
+//!
+//! Git doesn't just read `<root>/.gitignore`: every directory can have its own
+//! `.gitignore`, `.git/info/exclude` holds per-clone patterns and
+//! `core.excludesFile` (usually `~/.config/git/ignore`) holds per-user ones.
+//! `IgnoreStack` layers all of them, plus the `.ignore` files ripgrep and
+//! friends read, the same way the `ignore` crate's directory walker does:
+//!
+//! 1. `.ignore` files, the closest directory first
+//! 2. `.gitignore` files, the closest directory first
+//! 3. `.git/info/exclude`
+//! 4. the global excludes file
+//!
+//! The first layer with an opinion decides, so `!keep.log` in `src/.gitignore`
+//! re-includes a file the root `.gitignore` ignores. Like git, nothing inside
+//! an ignored directory can be re-included.
+//!
+//! Per-directory files are read the first time a path below them is checked
+//! and cached after that, so browsing a big repo doesn't read every ignore
+//! file up front.
+//!
+//! The same machinery reads `.repoviewerignore` files, RepoViewer's own list
+//! of paths that stay visible in the browser but never get exported.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use ignore::{
+    Match,
+    gitignore::{Gitignore, GitignoreBuilder},
+};
+
+/// The ignore files git tools read in any directory, highest precedence first
+const GIT_IGNORE_FILES: &[&str] = &[".ignore", ".gitignore"];
+
+/// RepoViewer's "never export" files, same syntax as `.gitignore`
+pub const EXPORT_IGNORE_FILE: &str = ".repoviewerignore";
+
+/// Ignore matchers for every layer of a repository
+#[derive(Debug, Clone)]
+pub struct IgnoreStack {
+    root: PathBuf,
+    /// `.git/info/exclude`
+    exclude: Gitignore,
+    /// `core.excludesFile`, matched against paths relative to the root
+    global: Gitignore,
+    /// Ignore files read in every directory, highest precedence first
+    file_names: &'static [&'static str],
+    /// The matchers for `file_names` in each directory checked so far
+    directories: RefCell<HashMap<PathBuf, Vec<Gitignore>>>,
+}
+
+impl IgnoreStack {
+    /// Load the repository-wide layers for the work tree at `root`
+    ///
+    /// `git_dir` is where `info/exclude` lives (the common git directory for
+    /// worktrees). Problems with the repository-wide files are returned as
+    /// warnings rather than failing, the matchers still hold every pattern
+    /// that parsed.
+    pub fn new(root: PathBuf, git_dir: &Path) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        // git2 hands out work trees with a trailing slash
+        let root: PathBuf = root.components().collect();
+
+        let exclude_path = git_dir.join("info").join("exclude");
+        let exclude = if exclude_path.is_file() {
+            let mut builder = GitignoreBuilder::new(&root);
+            if let Some(err) = builder.add(&exclude_path) {
+                warnings.push(format!("Error parsing {}: {}", exclude_path.display(), err));
+            }
+            builder.build().unwrap_or_else(|err| {
+                warnings.push(format!("Error parsing {}: {}", exclude_path.display(), err));
+                Gitignore::empty()
+            })
+        } else {
+            Gitignore::empty()
+        };
+
+        let (global, err) = Gitignore::global();
+        if let Some(err) = err {
+            warnings.push(format!("Error parsing the global git excludes file: {}", err));
+        }
+
+        let stack = IgnoreStack {
+            exclude,
+            global,
+            ..IgnoreStack::empty(root, GIT_IGNORE_FILES)
+        };
+        warnings.extend(stack.root_warnings());
+
+        (stack, warnings)
+    }
+
+    /// Load the `.repoviewerignore` rules for the project at `root`
+    ///
+    /// These work the same with or without git, there's no exclude or global file.
+    pub fn export_rules(root: PathBuf) -> (Self, Vec<String>) {
+        let stack = IgnoreStack::empty(root.components().collect(), &[EXPORT_IGNORE_FILE]);
+        let warnings = stack.root_warnings();
+
+        (stack, warnings)
+    }
+
+    fn empty(root: PathBuf, file_names: &'static [&'static str]) -> Self {
+        IgnoreStack {
+            root,
+            exclude: Gitignore::empty(),
+            global: Gitignore::empty(),
+            file_names,
+            directories: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Problems with the ignore files in the root
+    ///
+    /// The root files are the ones people edit most, so they're reported up
+    /// front rather than silently when they're first used.
+    fn root_warnings(&self) -> Vec<String> {
+        self.file_names
+            .iter()
+            .filter_map(|name| {
+                let path = self.root.join(name);
+                if !path.is_file() {
+                    return None;
+                }
+                Gitignore::new(&path).1.map(|err| format!("Error parsing {}: {}", name, err))
+            })
+            .collect()
+    }
+
+    /// How the ignore rules treat a path (absolute, or relative to the root)
+    ///
+    /// Paths outside the repository never match.
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Match<()> {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) if path.is_relative() => path,
+            Err(_) => return Match::None,
+        };
+        if relative.as_os_str().is_empty() {
+            return Match::None;
+        }
+
+        // An ignored directory hides everything below it, whatever its
+        // contents' own patterns say
+        let mut ancestor = PathBuf::new();
+        let parents: Vec<_> = relative.components().collect();
+        for component in parents.iter().take(parents.len().saturating_sub(1)) {
+            ancestor.push(component);
+            if self.matched_one(&ancestor, true).is_ignore() {
+                return Match::Ignore(());
+            }
+        }
+
+        self.matched_one(relative, is_dir)
+    }
+
+    /// Match a single path (relative to the root) against every layer,
+    /// ignoring what its parent directories matched
+    fn matched_one(&self, relative: &Path, is_dir: bool) -> Match<()> {
+        let absolute = self.root.join(relative);
+
+        // Directories the per-directory files could come from, closest first
+        let dirs: Vec<PathBuf> = relative
+            .ancestors()
+            .skip(1)
+            .map(|dir| if dir.as_os_str().is_empty() { self.root.clone() } else { self.root.join(dir) })
+            .collect();
+
+        for layer in 0..self.file_names.len() {
+            for dir in &dirs {
+                let found = self.with_directory(dir, |matchers| {
+                    matchers[layer].matched(&absolute, is_dir).map(|_| ())
+                });
+                if !found.is_none() {
+                    return found;
+                }
+            }
+        }
+
+        let found = self.exclude.matched(&absolute, is_dir).map(|_| ());
+        if !found.is_none() {
+            return found;
+        }
+
+        self.global.matched(relative, is_dir).map(|_| ())
+    }
+
+    /// Run `f` with the matchers of `dir`, reading its ignore files if needed
+    fn with_directory<T>(&self, dir: &Path, f: impl FnOnce(&[Gitignore]) -> T) -> T {
+        if let Some(matchers) = self.directories.borrow().get(dir) {
+            return f(matchers);
+        }
+
+        // Parse errors in nested files are skipped over: the patterns that did
+        // parse still apply, and there's no good place to report them mid-walk
+        let matchers: Vec<Gitignore> = self.file_names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                if path.is_file() { Gitignore::new(&path).0 } else { Gitignore::empty() }
+            })
+            .collect();
+        let result = f(&matchers);
+        self.directories.borrow_mut().insert(dir.to_path_buf(), matchers);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A work tree with the given files, and its ignore stack
+    fn repo(files: &[(&str, &str)]) -> (tempfile::TempDir, IgnoreStack) {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let (stack, warnings) = IgnoreStack::new(dir.path().to_path_buf(), &dir.path().join(".git"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        (dir, stack)
+    }
+
+    /// Whether a relative path is ignored, directories are written with a trailing `/`
+    fn ignored(stack: &IgnoreStack, path: &str) -> bool {
+        let is_dir = path.ends_with('/');
+        stack.matched(Path::new(path.trim_end_matches('/')), is_dir).is_ignore()
+    }
+
+    #[test]
+    fn nested_gitignores_apply_below_their_directory() {
+        let (_dir, stack) = repo(&[(".gitignore", "*.log\n"), ("src/.gitignore", "generated.rs\n!keep.log\n")]);
+
+        assert!(ignored(&stack, "debug.log"));
+        assert!(ignored(&stack, "src/debug.log"));
+        assert!(!ignored(&stack, "src/keep.log"));
+        assert!(ignored(&stack, "src/generated.rs"));
+        assert!(!ignored(&stack, "generated.rs"));
+    }
+
+    #[test]
+    fn ignore_files_win_over_gitignore() {
+        let (_dir, stack) = repo(&[(".gitignore", "*.tmp\n"), (".ignore", "!cache.tmp\nvendor/\n")]);
+
+        assert!(ignored(&stack, "a.tmp"));
+        assert!(!ignored(&stack, "cache.tmp"));
+        assert!(ignored(&stack, "vendor/"));
+    }
+
+    #[test]
+    fn nothing_inside_an_ignored_directory_comes_back() {
+        let (_dir, stack) = repo(&[(".gitignore", "build/\n"), ("build/.gitignore", "!*\n")]);
+        assert!(ignored(&stack, "build/out.txt"));
+    }
+
+    #[test]
+    fn info_exclude_applies_below_the_ignore_files() {
+        let (_dir, stack) = repo(&[(".git/info/exclude", "*.local\nnotes.md\n"), (".gitignore", "!notes.md\n")]);
+
+        assert!(ignored(&stack, "settings.local"));
+        assert!(!ignored(&stack, "notes.md"));
+    }
+
+    #[test]
+    fn export_rules_only_read_repoviewerignore() {
+        let (dir, _) = repo(&[
+            (".gitignore", "*.log\n"),
+            (EXPORT_IGNORE_FILE, "secrets/\n*.pem\n"),
+            ("deploy/.repoviewerignore", "prod.yaml\n"),
+        ]);
+        let (rules, warnings) = IgnoreStack::export_rules(dir.path().to_path_buf());
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        assert!(ignored(&rules, "secrets/"));
+        assert!(ignored(&rules, "secrets/api.txt"));
+        assert!(ignored(&rules, "certs/server.pem"));
+        assert!(ignored(&rules, "deploy/prod.yaml"));
+        assert!(!ignored(&rules, "prod.yaml"));
+        assert!(!ignored(&rules, "debug.log"));
+    }
+
+    #[test]
+    fn repoviewerignore_keeps_files_out_of_collections() {
+        let (dir, _) = repo(&[(EXPORT_IGNORE_FILE, "*.pem\n"), ("key.pem", "key\n"), ("main.rs", "fn main() {}\n")]);
+        let mut app = crate::app::App::new(dir.path().to_path_buf()).unwrap();
+
+        let report = app.collect_inputs(&["key.pem".to_string(), "main.rs".to_string()]).unwrap();
+        assert_eq!(report.collected, 1);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(app.collected_files[0].relative_path, "main.rs");
+    }
+
+    #[test]
+    fn broken_ignore_files_are_reported_in_the_app() {
+        let (dir, _) = repo(&[(".gitignore", "*.log\n"), (EXPORT_IGNORE_FILE, "src/[\n")]);
+        let app = crate::app::App::new(dir.path().to_path_buf()).unwrap();
+
+        let message = app.message.expect("the broken file should be reported");
+        assert!(!message.success);
+        assert!(message.text.contains(EXPORT_IGNORE_FILE), "{}", message.text);
+    }
+
+    #[test]
+    fn paths_outside_the_root_never_match() {
+        let (_dir, stack) = repo(&[(".gitignore", "*\n")]);
+        assert!(!ignored(&stack, "/somewhere/else.txt"));
+    }
+}
@@ -4,118 +4,248 @@ mod app_error;
 mod ui;
 mod clipboard;
 mod update;
+mod tokens;
+mod cli;
+mod ignores;
 
 use std::{
-    io::stdout,
-    path::PathBuf,
+    fs,
+    io::{self, stdout, Read, Write},
     time::Duration,
 };
 
-use app::App;
+use app::{read_input_list, App, ExportDestination, SecretPolicy, TreeExporter};
 use app_error::AppError;
 use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
+use tui_input::backend::crossterm::EventHandler;
 
-use crate::{ui::UI, utils::parse_target_dir};
+use crate::{
+    cli::{Args, BrowseArgs, CollectArgs, Command, DeanonymizeArgs, TreeArgs},
+    tokens::TokenLimits,
+    ui::UI,
+    utils::parse_target_dir,
+};
 
-/// RepoViewer - A TUI file explorer for generating directory trees for LLMs
-#[derive(Parser, Debug)]
-#[command(name = "repoviewer")]
-#[command(about, long_about = None)]
-struct Args {
-    /// Target directory (default: current directory)
-    path: Option<String>,
+fn main() {
+    let result = run();
+    if let Err(e) = result {
+        eprintln!("Error: {}", e.user_friendly_message());
+        std::process::exit(1);
+    }
+}
 
-    /// Generate tree output immediately and exit
-    #[arg(short, long)]
-    tree: bool,
 
-    /// Maximum depth for tree generation
-    #[arg(short, long, default_value = "10")]
-    depth: Option<usize>,
+fn run() -> Result<(), AppError> {
+    match Args::parse().into_command() {
+        Command::Browse(args) => run_browse(args),
+        Command::Tree(args) => run_tree(args),
+        Command::Collect(args) => run_collect(args),
+        Command::Deanonymize(args) => run_deanonymize(args),
+        Command::Update(args) => update::perform_update(args.yes),
+        Command::CheckUpdate => update::check_for_updates(),
+        Command::Version => {
+            update::show_version_info();
+            Ok(())
+        }
+    }
+}
 
-    /// Show hidden files in tree output
-    #[arg(long)]
-    hidden: bool,
+/// Open the TUI
+fn run_browse(args: BrowseArgs) -> Result<(), AppError> {
+    let dir_filter = args.dir_filter()?;
+    let target_dir = parse_target_dir(args.path)?;
 
-    /// Show gitignored files in tree output
-    #[arg(long)]
-    all: bool,
+    // Initialize the app before touching the terminal so a bad --format
+    // is reported as a normal error instead of leaving the terminal in raw mode
+    let mut app = App::new(target_dir)?;
+    args.export.apply(&mut app)?;
+    app.export_options.part_size = args.part_size;
+    app.token_limits = TokenLimits {
+        warning: args.warn_tokens,
+        critical: args.critical_tokens,
+    };
+    app.dir_filter = dir_filter;
+
+    if args.hidden || args.all {
+        app.show_hidden = args.hidden;
+        app.show_gitignored = args.all;
+        app.refresh_files()?;
+    }
 
-    /// Check for updates without installing
-    #[arg(long)]
-    check_update: bool,
-    
-    /// Update to the latest version
-    #[arg(long)]
-    update: bool,
-    
-    /// Don't prompt for confirmation during update
-    #[arg(long, requires = "update")]
-    yes: bool,
-    
-    /// Show current version and exit
-    #[arg(short = 'V', long)]
-    version: bool,
+    run_tui(app)
 }
 
-fn main() {
-    let result = run();
-    if let Err(e) = result {
-        eprintln!("Error: {}", e.user_friendly_message());
-        std::process::exit(1);
+/// Print the directory tree and exit
+fn run_tree(args: TreeArgs) -> Result<(), AppError> {
+    let tree_options = args.tree_options();
+    let target_dir = parse_target_dir(args.path)?;
+    let mut app = App::new(target_dir)?;
+    warn_startup_problems(&app);
+    app.show_hidden = args.hidden;
+    app.show_gitignored = args.all;
+    app.tree_options = tree_options;
+    app.refresh_files()?;
+
+    let destination = match args.output {
+        Some(path) => ExportDestination::File(path),
+        None => ExportDestination::Stdout,
+    };
+    app.export_to(&TreeExporter { max_depth: Some(args.depth) }, &destination)?;
+    Ok(())
+}
+
+/// Print what went wrong loading templates and ignore files
+///
+/// The TUI shows these as its first message, without it they go to stderr.
+fn warn_startup_problems(app: &App) {
+    if let Some(message) = &app.message
+        && !message.success
+    {
+        eprintln!("Warning: {}", message.text);
     }
 }
 
+/// Collect files without the TUI and write the export
+///
+/// Progress and skipped files go to stderr so stdout only ever holds the export.
+fn run_collect(args: CollectArgs) -> Result<(), AppError> {
+    let target_dir = parse_target_dir(args.root)?;
+    let mut app = App::new(target_dir)?;
+    app.show_hidden = args.hidden;
+    app.show_gitignored = args.all;
+    args.export.apply(&mut app)?;
 
-fn run() -> Result<(), AppError> {
-    let args = Args::parse();
+    if !args.quiet {
+        warn_startup_problems(&app);
+    }
 
-    // Verioning/updating flags !!!!
-    if args.version {
-        update::show_version_info();
-        return Ok(());
+    let mut inputs = args.inputs;
+    if args.stdin {
+        inputs.extend(read_input_list(io::stdin().lock())?);
     }
 
-    if args.check_update {
-        return update::check_for_updates();
+    let report = app.collect_inputs(&inputs)?;
+    if !args.quiet {
+        for skipped in &report.skipped {
+            eprintln!("Skipped {}: {}", skipped.path, skipped.reason);
+        }
     }
 
-    if args.update {
-        return update::perform_update(args.yes);
+    if app.collected_files.is_empty() {
+        return Err(AppError::NothingCollected);
     }
 
-    
-    // If no update related flags proceed with normal operation
-    let target_dir = parse_target_dir(args.path)?;
+    if let Some(pattern) = &args.outline {
+        let unsupported = app.outline_matching(pattern)?;
+        if !args.quiet {
+            for path in unsupported {
+                eprintln!("Collected {} in full: no outline support for its language", path);
+            }
+        }
+    }
 
-    // If tree flag is set generate and exit
-    if args.tree {
-        let mut app = App::new(target_dir)?;
-        if args.hidden {
-            app.show_hidden = true;
+    // There's nobody to ask without the TUI, so asking means blocking
+    let mut export = app.render_collection(false, &[])?;
+    let findings = app.scan_export(&export)?;
+    if !findings.is_empty() {
+        let policy = match app.secret_policy {
+            SecretPolicy::Ask => SecretPolicy::Block,
+            policy => policy,
+        };
+        if !args.quiet || policy == SecretPolicy::Block {
+            for finding in &findings {
+                eprintln!(
+                    "Possible secret {}:{}: {} ({})",
+                    finding.relative_path,
+                    finding.line,
+                    finding.kind.label(),
+                    finding.preview
+                );
+            }
         }
 
-        if args.all {
-            app.show_gitignored = true;
+        match policy {
+            SecretPolicy::Redact => {
+                export = app.render_collection(false, &findings)?;
+                if !args.quiet {
+                    eprintln!("Redacted {} possible secret(s)", findings.len());
+                }
+            }
+            SecretPolicy::Proceed => {}
+            _ => return Err(AppError::SecretsFound(findings.len())),
         }
-        app.refresh_files()?;
+    }
 
-        let tree = app.generate_tree(args.depth)?;
-        println!("{}", tree);
-        return Ok(());
+    let destination = match args.output {
+        Some(path) => ExportDestination::File(path),
+        None => ExportDestination::Stdout,
+    };
+    let (summary, fit_report) = app.write_export(export, &destination)?;
+
+    if !args.quiet {
+        let mut line = format!(
+            "Collected {} files ({}, {}) as {}",
+            report.collected,
+            app.format_size(summary.bytes),
+            app.format_tokens(summary.tokens),
+            app.exporters.active().name()
+        );
+        if !report.skipped.is_empty() {
+            line.push_str(&format!(", skipped {}", report.skipped.len()));
+        }
+        if let Some(fit_report) = fit_report {
+            line.push_str(&format!(" - {}", fit_report.summary()));
+        } else if app.token_budget.is_some() {
+            line.push_str(&format!(" - token budget not applied to {} exports", app.exporters.active().name()));
+        }
+        if let Some(note) = app.grouping_note() {
+            line.push_str(&format!(" - {}", note));
+        }
+        eprintln!("{}", line);
+    }
+
+    if args.fail_on_skip {
+        report.check_skipped()?;
+    }
+
+    Ok(())
+}
+
+/// Restore original names in anonymized text from a file or stdin
+fn run_deanonymize(args: DeanonymizeArgs) -> Result<(), AppError> {
+    let target_dir = parse_target_dir(args.root)?;
+    let app = App::new(target_dir)?;
+    warn_startup_problems(&app);
+
+    let text = match &args.input {
+        Some(path) => fs::read_to_string(path).map_err(|e| AppError::Io(e).with_path_context(path))?,
+        None => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            text
+        }
+    };
+    let restored = app.deanonymize(&text)?;
+
+    match &args.output {
+        Some(path) => fs::write(path, restored).map_err(|e| AppError::Io(e).with_path_context(path))?,
+        None => {
+            let mut stdout = stdout().lock();
+            stdout.write_all(restored.as_bytes())?;
+            stdout.flush()?;
+        }
     }
 
-    // Otherwise run tui
-    run_tui(target_dir)
+    Ok(())
 }
 
-fn run_tui(target_dir: PathBuf) -> Result<(), AppError> {
+fn run_tui(mut app: App) -> Result<(), AppError> {
     // Setup terminal 
     enable_raw_mode()?;
 
@@ -126,9 +256,6 @@ fn run_tui(target_dir: PathBuf) -> Result<(), AppError> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Initialize the app - no need for the extra import
-    let mut app = App::new(target_dir)?;
-
     // Run the app
     let result = run_app(&mut terminal, &mut app);
 
@@ -153,213 +280,380 @@ fn run_app<B: ratatui::backend::Backend>(
         terminal.draw(|frame| UI::render(frame, app))?;
 
         // Handle events
-        if event::poll(Duration::from_millis(16))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    // If help is shown, only handle help-closing keys
-                    if app.show_help {
-                        match key.code {
-                            KeyCode::Char('?') | KeyCode::Esc => {
-                                app.show_help = false;
-                            }
-                            _ => {} // Ignore all other keys when help is shown
-                        }
-                        continue; // Skip the rest of the event handling
+        if event::poll(Duration::from_millis(16))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            // If help is shown, only handle help-closing keys
+            if app.show_help {
+                match key.code {
+                    KeyCode::Char('?') | KeyCode::Esc => {
+                        app.show_help = false;
                     }
-                    
-                    // Normal key handling when help is not shown
-                    match key.code {
-                        // Quit
-                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
-
-                        // Show help overlay
-                        KeyCode::Char('?') => {
-                            app.show_help = true;
-                        }
+                    _ => {} // Ignore all other keys when help is shown
+                }
+                continue; // Skip the rest of the event handling
+            }
 
-                        // Toggle hidden files
-                        KeyCode::Char('h') => {
-                            app.show_hidden = !app.show_hidden;
-                            app.refresh_files()?;
-                        }
+            // Possible secrets hold the export until the user picks what to do
+            if app.secret_review.is_some() {
+                let decision = match key.code {
+                    KeyCode::Char('b') | KeyCode::Esc => Some(SecretPolicy::Block),
+                    KeyCode::Char('r') => Some(SecretPolicy::Redact),
+                    KeyCode::Char('p') => Some(SecretPolicy::Proceed),
+                    _ => None,
+                };
+                if let Some(decision) = decision
+                    && let Err(e) = app.resolve_secret_review(decision)
+                {
+                    app.set_error_message(e.user_friendly_message());
+                }
+                continue;
+            }
 
-                        // Toggle gitignored files (only if in git repo)
-                        KeyCode::Char('g') if app.git_root.is_some() => {
-                            app.show_gitignored = !app.show_gitignored;
-                            app.refresh_files()?;
-                        }
+            // Same for the fit report
+            if app.show_fit_report {
+                match key.code {
+                    KeyCode::Char('F') | KeyCode::Esc => {
+                        app.show_fit_report = false;
+                    }
+                    _ => {}
+                }
+                continue;
+            }
 
-                        // Quick navigation shortcuts
-                        KeyCode::Char('~') => {
-                            if app.current_dir != app.start_dir {
-                                if let Err(e) = app.navigate_to_start() {
-                                    app.set_error_message(format!("Failed to navigate to start: {}", e));
-                                }
-                            }
+            // The line picker takes every key while it's open
+            if let Some(picker) = app.range_picker.as_mut() {
+                match key.code {
+                    KeyCode::Esc => app.range_picker = None,
+                    KeyCode::Enter => {
+                        if let Err(e) = app.confirm_range_picker() {
+                            app.set_error_message(e.to_string());
                         }
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => picker.move_cursor(-1),
+                    KeyCode::Down | KeyCode::Char('j') => picker.move_cursor(1),
+                    KeyCode::PageUp => picker.page(-1),
+                    KeyCode::PageDown => picker.page(1),
+                    KeyCode::Home | KeyCode::Char('g') => picker.move_cursor(isize::MIN),
+                    KeyCode::End | KeyCode::Char('G') => picker.move_cursor(isize::MAX),
+                    KeyCode::Char(' ') => picker.toggle_selection(),
+                    KeyCode::Char('x') => picker.remove_at_cursor(),
+                    KeyCode::Char('s') => app.pick_symbol_at_cursor(),
+                    _ => {}
+                }
+                continue;
+            }
 
-                        KeyCode::Char('G') => {
-                            if app.git_root.is_some() && app.git_root != Some(app.current_dir.clone()) {
-                                if let Err(e) = app.navigate_to_git_root() {
-                                    app.set_error_message(format!("Failed to navigate to git root: {}", e));
-                                }
-                            }
+            // The pattern prompt takes typing, so it gets keys before any shortcut
+            if let Some(prompt) = app.pattern_prompt.as_mut() {
+                match key.code {
+                    KeyCode::Esc => app.pattern_prompt = None,
+                    KeyCode::Enter => app.add_pattern_matches(),
+                    KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.remove_pattern_matches()
+                    }
+                    KeyCode::Tab => prompt.toggle_kind(),
+                    KeyCode::Up => prompt.scroll_by(-1),
+                    KeyCode::Down => prompt.scroll_by(1),
+                    KeyCode::PageUp => prompt.scroll_by(-20),
+                    KeyCode::PageDown => prompt.scroll_by(20),
+                    _ => {
+                        if prompt.input.handle_event(&Event::Key(key)).is_some_and(|changed| changed.value) {
+                            prompt.update_matches();
                         }
+                    }
+                }
+                continue;
+            }
 
-                        // Save tree to file
-                        KeyCode::Char('t') => {
-                            let tree = app.generate_tree(None)?;
-                            let output_file = app.current_dir.join("tree.txt");
-                            std::fs::write(&output_file, tree)?;
-                            
-                            // Use display path for cleaner feedback
-                            let display_path = app.get_display_path(&output_file);
-                            app.set_success_message(format!("Tree saved to {}", display_path));
-                        }
-                        
-                        // Copy tree to clipboard
-                        KeyCode::Char('c') => {
-                            match app.copy_tree_to_clipboard() {
-                                Ok(_) => {}, // Success message is set inside the method
-                                Err(e) => app.set_error_message(e.user_friendly_message()),
-                            }
-                        }
+            // As does the recursive add preview, nothing is added until it's confirmed
+            if let Some(preview) = app.dir_preview.as_mut() {
+                match key.code {
+                    KeyCode::Esc | KeyCode::Char('n') => app.dir_preview = None,
+                    KeyCode::Enter | KeyCode::Char('y') => app.confirm_dir_preview(),
+                    KeyCode::Char('d') => app.cycle_preview_depth(),
+                    KeyCode::Char('l') => app.cycle_preview_language(),
+                    KeyCode::Up | KeyCode::Char('k') => preview.scroll_by(-1),
+                    KeyCode::Down | KeyCode::Char('j') => preview.scroll_by(1),
+                    KeyCode::PageUp => preview.scroll_by(-20),
+                    KeyCode::PageDown => preview.scroll_by(20),
+                    _ => {}
+                }
+                continue;
+            }
+            
+            // Normal key handling when help is not shown
+            match key.code {
+                // Quit
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+
+                // Show help overlay
+                KeyCode::Char('?') => {
+                    app.show_help = true;
+                }
 
-                        // --- Collection Controls ---
-                        
-                        // Add current file to collection (enhanced with warnings)
-                        KeyCode::Char('a') => app.add_current_file()?,
+                // Toggle hidden files
+                KeyCode::Char('h') => {
+                    app.show_hidden = !app.show_hidden;
+                    app.refresh_files()?;
+                }
 
-                        // Add all files in current directory to collection (enhanced with warnings)
-                        KeyCode::Char('A') => app.add_all_files_in_dir()?,
+                // Toggle gitignored files (only if in git repo)
+                KeyCode::Char('g') if app.git_root.is_some() => {
+                    app.show_gitignored = !app.show_gitignored;
+                    app.refresh_files()?;
+                }
 
-                        // Remove current file from collection
-                        KeyCode::Char('d') => app.remove_current_file()?,
-                        
-                        // Clear entire collection
-                        KeyCode::Char('D') => app.clear_collection()?,
+                // Quick navigation shortcuts
+                KeyCode::Char('~') if app.current_dir != app.start_dir => {
+                    if let Err(e) = app.navigate_to_start() {
+                        app.set_error_message(format!("Failed to navigate to start: {}", e));
+                    }
+                }
 
-                        // Save collection to markdown file
-                        KeyCode::Char('S') => {
-                            if let Err(e) = app.save_collection_to_file(None) {
-                                app.set_error_message(format!("Failed to save file: {}", e));
-                            }
-                        },
+                KeyCode::Char('G') if app.git_root.is_some() && app.git_root != Some(app.current_dir.clone()) => {
+                    if let Err(e) = app.navigate_to_git_root() {
+                        app.set_error_message(format!("Failed to navigate to git root: {}", e));
+                    }
+                }
 
-                        // Refresh collected files to sync with filesystem changes
-                        KeyCode::Char('r') => {
-                            // First, check if we even have files to refresh
-                            if app.collected_files.is_empty() {
-                                app.set_error_message("No files in collection to refresh".to_string());
-                            } else {
-                                // Store the initial count to detect net changes
-                                let initial_count = app.collected_files.len();
-                                
-                                // Perform the refresh operation
-                                let summary = app.refresh_all_collected();
-                                
-                                // Case 1: Nothing changed at all
-                                if summary.updated == 0 && summary.deleted == 0 && 
-                                summary.failed == 0 && summary.inaccessible == 0 {
-                                    app.set_success_message(format!(
-                                        "✓ Collection is up to date ({} files checked)", 
-                                        summary.unchanged
-                                    ));
-                                } 
-                                // Case 2: Changes occurred
-                                else {
-                                    // Build a list of what changed
-                                    let mut changes = Vec::new();
-                                    
-                                    if summary.updated > 0 {
-                                        changes.push(format!("{} updated", summary.updated));
-                                    }
-                                    if summary.deleted > 0 {
-                                        changes.push(format!("{} deleted", summary.deleted));
-                                    }
-                                    if summary.inaccessible > 0 {
-                                        changes.push(format!("{} inaccessible", summary.inaccessible));
-                                    }
-                                    if summary.failed > 0 {
-                                        changes.push(format!("{} failed", summary.failed));
-                                    }
-                                    
-                                    // Determine if this is a success or partial failure
-                                    let has_problems = summary.failed > 0 || summary.inaccessible > 0;
-                                    let final_count = app.collected_files.len();
-                                    
-                                    // Craft the message with appropriate tone
-                                    let message = format!(
-                                        "Refresh complete: {} | {} → {} files",
-                                        changes.join(", "),
-                                        initial_count,
-                                        final_count
-                                    );
-                                    
-                                    if has_problems {
-                                        app.set_error_message(message);
-                                    } else {
-                                        app.set_success_message(message);
-                                    }
-                                }
-                            }
+                // Save tree to file
+                KeyCode::Char('t') => {
+                    if let Err(e) = app.save_tree_to_file() {
+                        app.set_error_message(format!("Failed to save tree: {}", e));
+                    }
+                }
+                
+                // Copy tree to clipboard
+                KeyCode::Char('c') => {
+                    match app.copy_tree_to_clipboard() {
+                        Ok(_) => {}, // Success message is set inside the method
+                        Err(e) => app.set_error_message(e.user_friendly_message()),
+                    }
+                }
 
-                            app.refresh_files()?;
-                        }
+                // --- Collection Controls ---
+                
+                // Add current file to collection (enhanced with warnings)
+                KeyCode::Char('a') => app.add_current_file()?,
+
+                // Collect the selected file as an outline (or back in full)
+                KeyCode::Char('o') => app.toggle_outline_current_file()?,
+
+                // Pick lines of the selected file to collect
+                KeyCode::Char('R') => app.open_range_picker(),
+
+                // Add all files in current directory to collection (enhanced with warnings)
+                KeyCode::Char('A') => app.add_all_files_in_dir()?,
+
+                // Add everything below the selected directory, after a preview
+                KeyCode::Char('W') => app.open_dir_preview(),
 
-                        // Copy collection to clipboard
-                        KeyCode::Char('C') => {
-                            if let Err(e) = app.copy_collection_to_clipboard() {
-                                app.set_error_message(e.user_friendly_message());
+                // Add or remove every file matching a glob or regex
+                KeyCode::Char('/') => app.open_pattern_prompt(),
+
+                // Remove current file from collection
+                KeyCode::Char('d') => app.remove_current_file()?,
+                
+                // Clear entire collection
+                KeyCode::Char('D') => app.clear_collection()?,
+
+                // Save collection to markdown file
+                KeyCode::Char('S') => {
+                    if let Err(e) = app.save_collection_to_file(None) {
+                        app.set_error_message(format!("Failed to save file: {}", e));
+                    }
+                },
+
+                // Refresh collected files to sync with filesystem changes
+                KeyCode::Char('r') => {
+                    // First, check if we even have files to refresh
+                    if app.collected_files.is_empty() {
+                        app.set_error_message("No files in collection to refresh".to_string());
+                    } else {
+                        // Store the initial count to detect net changes
+                        let initial_count = app.collected_files.len();
+                        
+                        // Perform the refresh operation
+                        let summary = app.refresh_all_collected();
+                        
+                        // Case 1: Nothing changed at all
+                        if summary.updated == 0 && summary.deleted == 0 && 
+                        summary.failed == 0 && summary.inaccessible == 0 {
+                            app.set_success_message(format!(
+                                "✓ Collection is up to date ({} files checked)", 
+                                summary.unchanged
+                            ));
+                        } 
+                        // Case 2: Changes occurred
+                        else {
+                            // Build a list of what changed
+                            let mut changes = Vec::new();
+                            
+                            if summary.updated > 0 {
+                                changes.push(format!("{} updated", summary.updated));
                             }
-                        },
-
-                        // Navigation
-                        KeyCode::Up => {
-                            if let Some(selected) = app.state.selected() {
-                                if selected > 0 {
-                                    app.state.select(Some(selected - 1));
-                                }
+                            if summary.deleted > 0 {
+                                changes.push(format!("{} deleted", summary.deleted));
                             }
-                        }
-
-                        KeyCode::Down => {
-                            if let Some(selected) = app.state.selected() {
-                                if selected < app.items.len().saturating_sub(1) {
-                                    app.state.select(Some(selected + 1));
-                                }
+                            if summary.inaccessible > 0 {
+                                changes.push(format!("{} inaccessible", summary.inaccessible));
                             }
-                        }
-
-                        KeyCode::Left => {
-                            if app.can_navigate_up() {
-                                app.navigate_up()?;
+                            if summary.failed > 0 {
+                                changes.push(format!("{} failed", summary.failed));
                             }
-                        }
-                        
-                        KeyCode::Right | KeyCode::Enter => {
-                            if app.can_navigate_into_selection() {
-                                app.navigate_into()?;
+                            
+                            // Determine if this is a success or partial failure
+                            let has_problems = summary.failed > 0 || summary.inaccessible > 0;
+                            let final_count = app.collected_files.len();
+                            
+                            // Craft the message with appropriate tone
+                            let message = format!(
+                                "Refresh complete: {} | {} → {} files",
+                                changes.join(", "),
+                                initial_count,
+                                final_count
+                            );
+                            
+                            if has_problems {
+                                app.set_error_message(message);
+                            } else {
+                                app.set_success_message(message);
                             }
                         }
+                    }
 
-                        // Home/PageUp - go to the first item
-                        KeyCode::Home | KeyCode::PageUp => {
-                            if !app.items.is_empty() {
-                                app.state.select(Some(0));
-                            }
-                        }
+                    app.refresh_files()?;
+                }
 
-                        // End/PageDown - go to last item
-                        KeyCode::End | KeyCode::PageDown => {
-                            if !app.items.is_empty() {
-                                app.state.select(Some(app.items.len() - 1));
-                            }
-                        }
+                // Copy collection to clipboard
+                KeyCode::Char('C') => {
+                    if let Err(e) = app.copy_collection_to_clipboard() {
+                        app.set_error_message(e.user_friendly_message());
+                    }
+                },
+
+                // Cycle the export format used by 'S' and 'C'
+                KeyCode::Char('f') => {
+                    app.exporters.cycle();
+                    app.set_success_message(format!("Export format: {}", app.exporters.active().name()));
+                }
+
+                // Toggle embedding the project tree in collection exports
+                KeyCode::Char('T') => {
+                    app.export_options.include_tree = !app.export_options.include_tree;
+                    let state = if app.export_options.include_tree { "included in" } else { "left out of" };
+                    app.set_success_message(format!("Project tree will be {} exports", state));
+                }
+
+                // Toggle the repository metadata section in collection exports
+                KeyCode::Char('M') => {
+                    app.export_options.include_metadata = !app.export_options.include_metadata;
+                    let state = if app.export_options.include_metadata { "included in" } else { "left out of" };
+                    app.set_success_message(format!("Repository metadata will be {} exports", state));
+                }
+
+                // Toggle line numbers in exported content
+                KeyCode::Char('L') => {
+                    app.export_options.line_numbers = !app.export_options.line_numbers;
+                    let state = if app.export_options.line_numbers { "on" } else { "off" };
+                    app.set_success_message(format!("Line numbers in exports {}", state));
+                }
 
-                        _ => {}
+                // Toggle the per-file info line in exports
+                KeyCode::Char('I') => {
+                    app.export_options.file_info = !app.export_options.file_info;
+                    let state = if app.export_options.file_info { "on" } else { "off" };
+                    app.set_success_message(format!("Per-file info in exports {}", state));
+                }
+
+                // Toggle grouping exported files by directory
+                KeyCode::Char('O') => {
+                    app.export_options.group_by_directory = !app.export_options.group_by_directory;
+                    let mut message = if app.export_options.group_by_directory {
+                        "Exports grouped by directory with a table of contents".to_string()
+                    } else {
+                        "Exports list files in collection order".to_string()
+                    };
+                    if let Some(note) = app.grouping_note() {
+                        message.push_str(&format!(" ({})", note));
+                    }
+                    app.set_success_message(message);
+                }
+
+                // Toggle anonymization of exports
+                KeyCode::Char('N') => app.toggle_anonymize(),
+
+                // Restore original names in anonymized text on the clipboard
+                KeyCode::Char('U') => {
+                    if let Err(e) = app.deanonymize_clipboard() {
+                        app.set_error_message(e.user_friendly_message());
                     }
                 }
+
+                // Cycle the export transforms (comment and whitespace stripping)
+                KeyCode::Char('X') => app.cycle_transforms(),
+
+                // Cycle the size exports are split at
+                KeyCode::Char('P') => app.cycle_part_size(),
+
+                // Copy the next part of a split clipboard export
+                KeyCode::Char('n') => {
+                    if let Err(e) = app.copy_next_part() {
+                        app.set_error_message(e.user_friendly_message());
+                    }
+                }
+
+                // Cycle the token budget exports are fitted to
+                KeyCode::Char('b') => app.cycle_token_budget(),
+
+                // Pin the current file so fitting always keeps it whole
+                KeyCode::Char('p') => app.toggle_pin_current_file(),
+
+                // Show what fitting to the budget would cut
+                KeyCode::Char('F') => {
+                    if let Err(e) = app.open_fit_report() {
+                        app.set_error_message(format!("Failed to fit collection: {}", e));
+                    }
+                }
+
+                // Navigation
+                KeyCode::Up => {
+                    if let Some(selected) = app.state.selected()
+                        && selected > 0
+                    {
+                        app.state.select(Some(selected - 1));
+                    }
+                }
+
+                KeyCode::Down => {
+                    if let Some(selected) = app.state.selected()
+                        && selected < app.items.len().saturating_sub(1)
+                    {
+                        app.state.select(Some(selected + 1));
+                    }
+                }
+
+                KeyCode::Left if app.can_navigate_up() => {
+                    app.navigate_up()?;
+                }
+                
+                KeyCode::Right | KeyCode::Enter if app.can_navigate_into_selection() => {
+                    app.navigate_into()?;
+                }
+
+                // Home/PageUp - go to the first item
+                KeyCode::Home | KeyCode::PageUp if !app.items.is_empty() => {
+                    app.state.select(Some(0));
+                }
+
+                // End/PageDown - go to last item
+                KeyCode::End | KeyCode::PageDown if !app.items.is_empty() => {
+                    app.state.select(Some(app.items.len() - 1));
+                }
+
+                _ => {}
             }
         }
     }
@@ -0,0 +1,189 @@
+//! Token estimation
+//!
+//! Byte sizes don't say much about whether a collection fits in a model's
+//! context window, so we also count tokens. By default this is a fast
+//! heuristic that needs no data files. Building with the `tokenizer` feature
+//! adds real BPE tokenizers (the OpenAI `cl100k_base` and `o200k_base`
+//! encodings) which are embedded in the binary and work offline.
+
+use crate::app_error::AppError;
+
+/// How tokens are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tokenizer {
+    /// Character-class heuristic, usually within ~15% of a real tokenizer for code
+    #[default]
+    Heuristic,
+    /// `cl100k_base` BPE (GPT-4 / GPT-3.5 era models)
+    #[cfg(feature = "tokenizer")]
+    Cl100k,
+    /// `o200k_base` BPE (GPT-4o era models)
+    #[cfg(feature = "tokenizer")]
+    O200k,
+}
+
+impl Tokenizer {
+    /// Look up a tokenizer by the name used on the command line
+    pub fn from_name(name: &str) -> Result<Self, AppError> {
+        match name.to_ascii_lowercase().as_str() {
+            "heuristic" => Ok(Tokenizer::Heuristic),
+            #[cfg(feature = "tokenizer")]
+            "cl100k" | "cl100k_base" => Ok(Tokenizer::Cl100k),
+            #[cfg(feature = "tokenizer")]
+            "o200k" | "o200k_base" => Ok(Tokenizer::O200k),
+            #[cfg(not(feature = "tokenizer"))]
+            "cl100k" | "cl100k_base" | "o200k" | "o200k_base" => Err(AppError::TokenizerUnavailable(
+                format!("'{}' needs a build with --features tokenizer", name),
+            )),
+            _ => Err(AppError::TokenizerUnavailable(format!("unknown tokenizer '{}'", name))),
+        }
+    }
+
+    /// Name shown in the UI and exports
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tokenizer::Heuristic => "heuristic",
+            #[cfg(feature = "tokenizer")]
+            Tokenizer::Cl100k => "cl100k_base",
+            #[cfg(feature = "tokenizer")]
+            Tokenizer::O200k => "o200k_base",
+        }
+    }
+
+    /// Whether counts from this tokenizer are estimates rather than exact
+    pub fn is_estimate(&self) -> bool {
+        matches!(self, Tokenizer::Heuristic)
+    }
+
+    /// Count the tokens in a piece of text
+    pub fn count(&self, text: &str) -> usize {
+        match self {
+            Tokenizer::Heuristic => estimate_tokens(text),
+            #[cfg(feature = "tokenizer")]
+            Tokenizer::Cl100k => tiktoken_rs::cl100k_base_singleton().encode_ordinary(text).len(),
+            #[cfg(feature = "tokenizer")]
+            Tokenizer::O200k => tiktoken_rs::o200k_base_singleton().encode_ordinary(text).len(),
+        }
+    }
+
+    /// Format a token count for display, with `~` in front of estimates
+    ///
+    /// e.g. `~850 tokens`, `~12.4k tokens`, `1.21M tokens`
+    pub fn format(&self, tokens: usize) -> String {
+        let prefix = if self.is_estimate() { "~" } else { "" };
+        format!("{}{} tokens", prefix, format_count(tokens))
+    }
+}
+
+/// Format a token count compactly, e.g. `850`, `12.4k`, `100k`, `1.21M`
+pub fn format_count(tokens: usize) -> String {
+    let formatted = if tokens >= 1_000_000 {
+        format!("{:.2}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{:.1}k", tokens as f64 / 1_000.0)
+    } else {
+        return tokens.to_string();
+    };
+
+    // Round numbers read better without the zeros ("100k" rather than "100.0k")
+    match formatted.split_once('.') {
+        Some((whole, fraction)) if fraction.trim_end_matches(['k', 'M']).trim_end_matches('0').is_empty() => {
+            format!("{}{}", whole, &fraction[fraction.len() - 1..])
+        }
+        _ => formatted,
+    }
+}
+
+/// Estimate the token count of some text without a vocabulary
+///
+/// BPE tokenizers mostly split text into words, numbers and individual
+/// punctuation marks, with long identifiers broken into several pieces.
+/// We mirror that: every run of letters/digits costs one token per 4
+/// characters (rounded up), every symbol costs one token, and whitespace is
+/// free except for newlines, which usually end up as their own token.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word_len: usize = 0;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word_len += 1;
+            continue;
+        }
+
+        // End of a word, charge for it
+        tokens += word_len.div_ceil(4);
+        word_len = 0;
+
+        if c == '\n' || !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+
+    tokens + word_len.div_ceil(4)
+}
+
+/// Collection warning thresholds measured in tokens
+///
+/// When neither is set the byte thresholds are used instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenLimits {
+    pub warning: Option<usize>,
+    pub critical: Option<usize>,
+}
+
+impl TokenLimits {
+    /// Whether the user configured any token thresholds
+    pub fn is_set(&self) -> bool {
+        self.warning.is_some() || self.critical.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heuristic_counts_words_symbols_and_newlines() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("fn main() {}\n"), 7);
+        // Long identifiers cost one token per 4 characters
+        assert_eq!(estimate_tokens("configuration_loader"), 5);
+        assert_eq!(estimate_tokens("héllo wörld"), 4);
+        // Spaces are free, blank lines aren't
+        assert_eq!(estimate_tokens("a    b"), 2);
+        assert_eq!(estimate_tokens("a\n\n\nb"), 5);
+        assert_eq!(Tokenizer::Heuristic.count("let x = 42;"), 5);
+    }
+
+    #[test]
+    fn counts_are_formatted_compactly() {
+        assert_eq!(format_count(850), "850");
+        assert_eq!(format_count(12_400), "12.4k");
+        assert_eq!(format_count(100_000), "100k");
+        assert_eq!(format_count(1_210_000), "1.21M");
+        assert_eq!(format_count(2_000_000), "2M");
+        assert_eq!(Tokenizer::Heuristic.format(12_400), "~12.4k tokens");
+    }
+
+    #[test]
+    fn tokenizers_are_looked_up_by_name() {
+        assert_eq!(Tokenizer::from_name("Heuristic").unwrap(), Tokenizer::Heuristic);
+        assert!(Tokenizer::from_name("gpt2").is_err());
+        #[cfg(not(feature = "tokenizer"))]
+        assert!(Tokenizer::from_name("cl100k").unwrap_err().to_string().contains("--features tokenizer"));
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn bpe_tokenizers_count_exactly() {
+        for name in ["cl100k_base", "o200k_base"] {
+            let tokenizer = Tokenizer::from_name(name).unwrap();
+            assert_eq!(tokenizer.name(), name);
+            assert!(!tokenizer.is_estimate());
+            assert_eq!(tokenizer.count("hello world"), 2);
+            assert_eq!(tokenizer.count(""), 0);
+            assert_eq!(tokenizer.format(1_000), "1k tokens");
+        }
+    }
+}
@@ -6,7 +6,11 @@ use ratatui::{
     Frame
 };
 
-use crate::{app::App, utils::{get_file_display_info, MEGABYTE}};
+use crate::{
+    app::{App, CollectionHealth, ContentMode, DirPreview, FitOutcome, FitReport, PatternPrompt, RangePicker, SecretReview},
+    tokens::format_count,
+    utils::get_file_display_info,
+};
 
 /// UI is now a stateless renderer - it doesn't hold any data, just contains
 /// methods for drawing different parts of the interface
@@ -28,7 +32,7 @@ impl UI {
                 .constraints([
                     Constraint::Length(5), // Increased for breadcrumbs
                     Constraint::Min(5),
-                    Constraint::Length(7), // Increased for contextual hints
+                    Constraint::Length(8), // Increased for contextual hints and export options
                 ])
                 .split(frame.area());
 
@@ -38,6 +42,86 @@ impl UI {
             
             // Then render the help overlay on top
             Self::render_help_overlay(frame);
+        } else if let Some(review) = &app.secret_review {
+            // An export waiting on a secrets decision
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(5),
+                    Constraint::Min(5),
+                    Constraint::Length(8),
+                ])
+                .split(frame.area());
+
+            Self::render_header_with_breadcrumbs(frame, app, chunks[0]);
+            Self::render_file_list(frame, app, chunks[1]);
+            Self::render_status_bar_with_hints(frame, app, chunks[2]);
+
+            Self::render_secret_review(frame, review);
+        } else if let Some(report) = app.fit_report.as_ref().filter(|_| app.show_fit_report) {
+            // Same idea for the budget report
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(5),
+                    Constraint::Min(5),
+                    Constraint::Length(8),
+                ])
+                .split(frame.area());
+
+            Self::render_header_with_breadcrumbs(frame, app, chunks[0]);
+            Self::render_file_list(frame, app, chunks[1]);
+            Self::render_status_bar_with_hints(frame, app, chunks[2]);
+
+            Self::render_fit_report(frame, app, report);
+        } else if let Some(picker) = &app.range_picker {
+            // Picking lines of a file to collect
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(5),
+                    Constraint::Min(5),
+                    Constraint::Length(8),
+                ])
+                .split(frame.area());
+
+            Self::render_header_with_breadcrumbs(frame, app, chunks[0]);
+            Self::render_file_list(frame, app, chunks[1]);
+            Self::render_status_bar_with_hints(frame, app, chunks[2]);
+
+            Self::render_range_picker(frame, picker);
+        } else if let Some(preview) = &app.dir_preview {
+            // Confirming a recursive add
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(5),
+                    Constraint::Min(5),
+                    Constraint::Length(8),
+                ])
+                .split(frame.area());
+
+            Self::render_header_with_breadcrumbs(frame, app, chunks[0]);
+            Self::render_file_list(frame, app, chunks[1]);
+            Self::render_status_bar_with_hints(frame, app, chunks[2]);
+
+            Self::render_dir_preview(frame, app, preview);
+        } else if let Some(prompt) = &app.pattern_prompt {
+            // Typing a pattern to add or remove files by
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(5),
+                    Constraint::Min(5),
+                    Constraint::Length(8),
+                ])
+                .split(frame.area());
+
+            Self::render_header_with_breadcrumbs(frame, app, chunks[0]);
+            Self::render_file_list(frame, app, chunks[1]);
+            Self::render_status_bar_with_hints(frame, app, chunks[2]);
+
+            Self::render_pattern_prompt(frame, app, prompt);
         } else {
             // Normal rendering
             let chunks = Layout::default()
@@ -45,7 +129,7 @@ impl UI {
                 .constraints([
                     Constraint::Length(5), // Increased for breadcrumbs
                     Constraint::Min(5),
-                    Constraint::Length(7), // Increased for contextual hints
+                    Constraint::Length(8), // Increased for contextual hints and export options
                 ])
                 .split(frame.area());
 
@@ -120,22 +204,26 @@ impl UI {
 
         // Add collection status
         if !app.collected_files.is_empty() {
-            let size = app.get_collection_size();
-            let size_str = app.format_size(size);
+            let size_str = app.format_size(app.get_collection_size());
+            let mut tokens_str = app.format_tokens(app.get_collection_tokens());
+            if let Some(budget) = app.token_budget {
+                tokens_str.push_str(&format!(" / {} budget", format_count(budget)));
+            }
             
             // Determine collection health with visual indicators
-            let (indicator, style) = match size {
-                s if s > 50 * MEGABYTE => ("⚠️ ", Style::default().fg(Color::Red)),
-                s if s > 25 * MEGABYTE => ("⚠️ ", Style::default().fg(Color::Yellow)),
-                _ => ("📦 ", Style::default().fg(Color::Green))
+            let (indicator, style) = match app.collection_health() {
+                CollectionHealth::Critical => ("⚠️ ", Style::default().fg(Color::Red)),
+                CollectionHealth::Warning => ("⚠️ ", Style::default().fg(Color::Yellow)),
+                CollectionHealth::Healthy => ("📦 ", Style::default().fg(Color::Green))
             };
             
             lines.push(
                 Line::from(vec![
                     Span::styled(indicator, style),
-                    Span::raw(format!("Collection: {} files ({})", 
+                    Span::raw(format!("Collection: {} files ({}, {})", 
                         app.collected_files.len(), 
-                        size_str
+                        size_str,
+                        tokens_str
                     )),
                 ])
             );
@@ -157,12 +245,24 @@ impl UI {
             .iter()
             .map(|item| {
                 let (icon, style) = get_file_display_info(item);
-                let is_collected = app.is_collected(&item.path);
+                let collected = app.collected_files.iter().find(|f| f.path == item.path);
+                let is_collected = collected.is_some();
                 
                 // Create the display name with collection indicator
                 // We use [+] for collected files and spaces for alignment
                 let collection_marker = if is_collected { "[+]" } else { "   " };
-                let display_name = format!("{} {} {}", collection_marker, icon, item.name);
+                let mut display_name = format!("{} {} {}", collection_marker, icon, item.name);
+
+                // Show what each collected file costs in tokens
+                if let Some(file) = collected {
+                    let mode = match file.mode {
+                        ContentMode::Full => String::new(),
+                        ContentMode::Outline => ", outline".to_string(),
+                        ContentMode::Ranges => format!(", {} lines", file.ranges.iter().map(|r| r.len()).sum::<usize>()),
+                    };
+                    let pinned = if file.pinned { ", pinned" } else { "" };
+                    display_name.push_str(&format!("  ({}{}{})", app.format_tokens(file.token_count), mode, pinned));
+                }
 
                 // Keep the background color as a secondary indicator
                 // This provides redundancy - users can rely on either visual cue
@@ -215,7 +315,7 @@ impl UI {
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(1), // Contextual hint
-                Constraint::Length(6), // Control information
+                Constraint::Length(7), // Control information
             ])
             .split(area);
         
@@ -244,7 +344,7 @@ impl UI {
         let at_start_dir = app.current_dir == app.start_dir;
         
         // Render the regular status bar in the remaining space
-        let controls = vec![
+        let controls = [
             // Navigation controls - now with smart indicators
             Line::from(vec![
                 Span::styled("Navigate:", Style::default().add_modifier(Modifier::BOLD)),
@@ -307,7 +407,18 @@ impl UI {
                 } else {
                     Span::styled("HIDE", Style::default().fg(Color::Red))
                 },
-                Span::raw("]"),
+                Span::raw("]  "),
+                Span::styled("b", Style::default().fg(Color::Yellow)),
+                Span::raw(" Budget["),
+                match app.token_budget {
+                    Some(budget) => Span::styled(format_count(budget), Style::default().fg(Color::Green)),
+                    None => Span::styled("OFF", Style::default().fg(Color::Red)),
+                },
+                Span::raw("]  "),
+                Span::styled("p", Style::default().fg(Color::Yellow)),
+                Span::raw(" Pin  "),
+                Span::styled("F", Style::default().fg(Color::Yellow)),
+                Span::raw(" Fit report"),
             ]),
             // Tree Export controls
             Line::from(vec![
@@ -316,7 +427,22 @@ impl UI {
                 Span::styled("t", Style::default().fg(Color::Green)),
                 Span::raw(" Save tree  "),
                 Span::styled("c", Style::default().fg(Color::Green)),
-                Span::raw(" Copy tree"),
+                Span::raw(" Copy tree | "),
+                Span::styled("P", Style::default().fg(Color::Yellow)),
+                Span::raw(" Parts["),
+                match app.export_options.part_size {
+                    Some(size) => Span::styled(app.format_size(size), Style::default().fg(Color::Green)),
+                    None => Span::styled("OFF", Style::default().fg(Color::Red)),
+                },
+                Span::raw("]  "),
+                // Next part only lights up while a split clipboard export is in progress
+                match &app.pending_parts {
+                    Some(pending) => Span::styled(
+                        format!("n Next part[{}/{}]", pending.next + 1, pending.parts.len()),
+                        Style::default().fg(Color::Yellow),
+                    ),
+                    None => Span::styled("n Next part", Style::default().fg(Color::DarkGray)),
+                },
             ]),
             // Collection controls
             Line::from(vec![
@@ -325,7 +451,15 @@ impl UI {
                 Span::styled("a", Style::default().fg(Color::Yellow)),
                 Span::raw("/"),
                 Span::styled("A", Style::default().fg(Color::Yellow)),
+                Span::raw("/"),
+                Span::styled("W", Style::default().fg(Color::Yellow)),
+                Span::raw("/"),
+                Span::styled("/", Style::default().fg(Color::Yellow)),
                 Span::raw(" Add, "),
+                Span::styled("o", Style::default().fg(Color::Yellow)),
+                Span::raw(" Outline, "),
+                Span::styled("R", Style::default().fg(Color::Yellow)),
+                Span::raw(" Lines, "),
                 Span::styled("d", Style::default().fg(Color::Red)),
                 Span::raw("/"),
                 Span::styled("D", Style::default().fg(Color::Red)),
@@ -335,7 +469,44 @@ impl UI {
                 Span::styled("S", Style::default().fg(Color::Green)),
                 Span::raw("/"),
                 Span::styled("C", Style::default().fg(Color::Green)),
-                Span::raw(" Export"),
+                Span::raw(" Save/Copy"),
+            ]),
+            // Export options
+            Line::from(vec![
+                Span::styled("Export:", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(" "),
+                Span::styled("f", Style::default().fg(Color::Yellow)),
+                Span::raw(" Format["),
+                Span::styled(app.exporters.active().name(), Style::default().fg(Color::Cyan)),
+                Span::raw("]  "),
+                Span::styled("T", Style::default().fg(Color::Yellow)),
+                Span::raw(" Tree["),
+                Self::on_off(app.export_options.include_tree),
+                Span::raw("]  "),
+                Span::styled("M", Style::default().fg(Color::Yellow)),
+                Span::raw(" Meta["),
+                Self::on_off(app.export_options.include_metadata),
+                Span::raw("]  "),
+                Span::styled("L", Style::default().fg(Color::Yellow)),
+                Span::raw(" Line numbers["),
+                Self::on_off(app.export_options.line_numbers),
+                Span::raw("]  "),
+                Span::styled("I", Style::default().fg(Color::Yellow)),
+                Span::raw(" File info["),
+                Self::on_off(app.export_options.file_info),
+                Span::raw("]  "),
+                Span::styled("O", Style::default().fg(Color::Yellow)),
+                Span::raw(" Group by dir["),
+                Self::on_off(app.export_options.group_by_directory),
+                Span::raw("]  "),
+                Span::styled("N", Style::default().fg(Color::Yellow)),
+                Span::raw(" Anonymize["),
+                Self::on_off(app.export_options.anonymize),
+                Span::raw("]  "),
+                Span::styled("X", Style::default().fg(Color::Yellow)),
+                Span::raw(" Transform["),
+                Span::styled(app.export_options.transforms.name(), Style::default().fg(Color::Cyan)),
+                Span::raw("]"),
             ]),
             // Exit and help control
             Line::from(vec![
@@ -364,6 +535,7 @@ impl UI {
                 Constraint::Length(1), // Line 3
                 Constraint::Length(1), // Line 4
                 Constraint::Length(1), // Line 5
+                Constraint::Length(1), // Line 6
             ])
             .split(chunks[1]);
 
@@ -382,6 +554,15 @@ impl UI {
         }
     }
 
+    /// Green ON / red OFF indicator for export toggles
+    fn on_off(enabled: bool) -> Span<'static> {
+        if enabled {
+            Span::styled("ON", Style::default().fg(Color::Green))
+        } else {
+            Span::styled("OFF", Style::default().fg(Color::Red))
+        }
+    }
+
     /// Renders a popup message (success or error)
     fn render_message(frame: &mut Frame, message: &crate::app::Message) {
         let width = message.text.len().min(50) as u16 + 4; // Max width of 50 chars + padding
@@ -419,6 +600,453 @@ impl UI {
         frame.render_widget(text, popup_area);
     }
 
+    /// Renders the token budget report as an overlay
+    ///
+    /// One line per collected file, in priority order, saying what fitting did
+    /// to it and why.
+    fn render_fit_report(frame: &mut Frame, app: &App, report: &FitReport) {
+        let area = frame.area();
+        let report_area = Rect::new(
+            area.width / 10,
+            area.height / 20,
+            area.width * 8 / 10,
+            area.height * 9 / 10,
+        );
+
+        let total_style = if report.over_budget() {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Green)
+        };
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("Token Budget Report", Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
+            ]),
+            Line::from(""),
+            Line::from(vec![
+                Span::raw(format!("Budget: {} tokens   Export: ", format_count(report.budget))),
+                Span::styled(app.format_tokens(report.export_tokens), total_style),
+                Span::raw(format!("   ({})", report.summary())),
+            ]),
+            Line::from(""),
+        ];
+
+        for entry in &report.entries {
+            let color = match entry.outcome {
+                FitOutcome::Pinned => Color::Cyan,
+                FitOutcome::Kept => Color::Green,
+                FitOutcome::Outlined { .. } => Color::LightYellow,
+                FitOutcome::Truncated { .. } => Color::Yellow,
+                FitOutcome::Dropped => Color::Red,
+            };
+
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<10}", entry.outcome.label()), Style::default().fg(color)),
+                Span::raw(entry.relative_path.clone()),
+                Span::styled(
+                    format!(
+                        "  {} → {}: {}",
+                        format_count(entry.original_tokens),
+                        format_count(entry.kept_tokens),
+                        entry.reason
+                    ),
+                    Style::default().fg(Color::Gray),
+                ),
+            ]));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Press 'F' or ESC to close", 
+                Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC))
+        ]));
+
+        let report_block = Block::default()
+            .title("  Fit Report  ")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black));
+
+        let report_paragraph = Paragraph::new(lines)
+            .block(report_block)
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(Color::Black)),
+            report_area
+        );
+        frame.render_widget(report_paragraph, report_area);
+    }
+
+    /// Renders the possible secrets found in an export, grouped by file
+    fn render_secret_review(frame: &mut Frame, review: &SecretReview) {
+        let area = frame.area();
+        let review_area = Rect::new(
+            area.width / 10,
+            area.height / 20,
+            area.width * 8 / 10,
+            area.height * 9 / 10,
+        );
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("Possible Secrets", Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED))
+            ]),
+            Line::from(""),
+            Line::from(format!("Found {}. Nothing has been exported yet.", review.summary())),
+            Line::from(""),
+        ];
+
+        let mut current_file = None;
+        for finding in &review.findings {
+            if current_file != Some(&finding.relative_path) {
+                current_file = Some(&finding.relative_path);
+                lines.push(Line::from(vec![
+                    Span::styled(finding.relative_path.clone(), Style::default().fg(Color::Cyan)),
+                ]));
+            }
+
+            lines.push(Line::from(vec![
+                Span::styled(format!("  line {:<6}", finding.line), Style::default().fg(Color::Yellow)),
+                Span::raw(format!("{:<20}", finding.kind.label())),
+                Span::styled(finding.preview.clone(), Style::default().fg(Color::Gray)),
+            ]));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("b/ESC", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::raw(" Block   "),
+            Span::styled("r", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Redact and export   "),
+            Span::styled("p", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::raw(" Proceed anyway"),
+        ]));
+
+        let review_block = Block::default()
+            .title("  Secret Scan  ")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Red))
+            .style(Style::default().bg(Color::Black));
+
+        let review_paragraph = Paragraph::new(lines)
+            .block(review_block)
+            .wrap(Wrap { trim: false });
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(Color::Black)),
+            review_area
+        );
+        frame.render_widget(review_paragraph, review_area);
+    }
+
+    /// Renders the line picker over the file list
+    ///
+    /// The file fills the overlay with line numbers, picked lines in green and
+    /// the selection being made in yellow. The view follows the cursor.
+    fn render_range_picker(frame: &mut Frame, picker: &RangePicker) {
+        let area = frame.area();
+        let picker_area = Rect::new(
+            area.width / 10,
+            area.height / 20,
+            area.width * 8 / 10,
+            area.height * 9 / 10,
+        );
+
+        let summary = if picker.ranges.is_empty() {
+            "Nothing picked yet, Enter collects the whole file".to_string()
+        } else {
+            format!(
+                "Picked lines {} ({} of {} lines)",
+                picker.ranges.iter().map(|r| r.to_string()).collect::<Vec<_>>().join(", "),
+                picker.selected_lines(),
+                picker.lines.len()
+            )
+        };
+        let mut lines = vec![
+            Line::from(Span::styled(summary, Style::default().fg(Color::Cyan))),
+            Line::from(""),
+        ];
+
+        // Borders, the summary and the keys take 6 rows
+        let height = (picker_area.height as usize).saturating_sub(6).max(1);
+        let top = picker.cursor
+            .saturating_sub(height / 2)
+            .min(picker.lines.len().saturating_sub(height));
+        let width = picker.lines.len().max(1).to_string().len();
+
+        for (i, text) in picker.lines.iter().enumerate().skip(top).take(height) {
+            let mut style = if picker.is_marking(i) {
+                Style::default().fg(Color::Yellow)
+            } else if picker.is_selected(i) {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            if i == picker.cursor {
+                style = style.bg(Color::Rgb(80, 80, 80)).add_modifier(Modifier::BOLD);
+            }
+            let marker = if picker.is_selected(i) { "+" } else { " " };
+
+            lines.push(Line::from(vec![
+                Span::styled(format!("{}{:>width$} │ ", marker, i + 1, width = width), Style::default().fg(Color::DarkGray)),
+                Span::styled(text.clone(), style),
+            ]));
+        }
+
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Space", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Start/end selection   "),
+            Span::styled("s", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Pick symbol   "),
+            Span::styled("x", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::raw(" Unpick   "),
+            Span::styled("g/G PgUp/PgDn", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Jump   "),
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::raw(" Collect   "),
+            Span::styled("ESC", Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD)),
+            Span::raw(" Cancel"),
+        ]));
+
+        let picker_block = Block::default()
+            .title(format!("  Pick Lines: {}  ", picker.file.relative_path))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black));
+
+        // No wrapping, a long line would push the ones below out of view
+        let picker_paragraph = Paragraph::new(lines).block(picker_block);
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(Color::Black)),
+            picker_area
+        );
+        frame.render_widget(picker_paragraph, picker_area);
+    }
+
+    /// Renders the recursive add preview over the file list
+    ///
+    /// Totals and the filters come first, then every file that would be added
+    /// (yellow if it's already collected) and the skipped ones with their reasons.
+    fn render_dir_preview(frame: &mut Frame, app: &App, preview: &DirPreview) {
+        let area = frame.area();
+        let preview_area = Rect::new(
+            area.width / 10,
+            area.height / 20,
+            area.width * 8 / 10,
+            area.height * 9 / 10,
+        );
+
+        let new_files = preview.files.len() - preview.already_collected;
+        let mut totals = format!("Would add {} files", new_files);
+        if preview.already_collected > 0 {
+            totals.push_str(&format!(" and update {}", preview.already_collected));
+        }
+        totals.push_str(&format!(
+            " · {} · {}",
+            app.format_size(preview.total_size()),
+            app.format_tokens(preview.total_tokens())
+        ));
+
+        let mut left_out = vec![format!("{} skipped", preview.skipped.len())];
+        if preview.filtered > 0 {
+            left_out.push(format!("{} filtered out", preview.filtered));
+        }
+        if preview.too_deep > 0 {
+            left_out.push(format!("{} directories past the depth limit", preview.too_deep));
+        }
+
+        let mut lines = vec![
+            Line::from(Span::styled(totals, Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+            Line::from(vec![
+                Span::styled("Filters: ", Style::default().fg(Color::Gray)),
+                Span::raw(preview.filter.describe()),
+                Span::styled(format!("   ({})", left_out.join(", ")), Style::default().fg(Color::DarkGray)),
+            ]),
+            Line::from(""),
+        ];
+
+        let mut entries: Vec<Line> = preview.files
+            .iter()
+            .map(|file| {
+                let style = if app.collected_files.iter().any(|f| f.path == file.path) {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default().fg(Color::Green)
+                };
+                Line::from(vec![
+                    Span::styled(format!("  + {}", file.relative_path), style),
+                    Span::styled(
+                        format!("  {}", app.format_tokens(file.token_count)),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect();
+        entries.extend(preview.skipped.iter().map(|skipped| {
+            Line::from(vec![
+                Span::styled(format!("  - {}", skipped.path), Style::default().fg(Color::Red)),
+                Span::styled(format!("  {}", skipped.reason), Style::default().fg(Color::DarkGray)),
+            ])
+        }));
+
+        // Borders, the header and the keys take 7 rows
+        let height = (preview_area.height as usize).saturating_sub(7).max(1);
+        lines.extend(entries.into_iter().skip(preview.scroll).take(height));
+
+        lines.push(Line::from(""));
+        lines.push(Line::from(vec![
+            Span::styled("Enter/y", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::raw(" Add   "),
+            Span::styled("d", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Depth   "),
+            Span::styled("l", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Language   "),
+            Span::styled("j/k PgUp/PgDn", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Scroll   "),
+            Span::styled("ESC/n", Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD)),
+            Span::raw(" Cancel"),
+        ]));
+
+        let preview_block = Block::default()
+            .title(format!("  Add Recursively: {}  ", app.get_display_path(&preview.dir)))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black));
+
+        let preview_paragraph = Paragraph::new(lines).block(preview_block);
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(Color::Black)),
+            preview_area
+        );
+        frame.render_widget(preview_paragraph, preview_area);
+    }
+
+    /// Renders the pattern prompt over the file list
+    ///
+    /// The input sits at the top with the matches listed below it as they
+    /// change, already collected files in yellow.
+    fn render_pattern_prompt(frame: &mut Frame, app: &App, prompt: &PatternPrompt) {
+        let area = frame.area();
+        let prompt_area = Rect::new(
+            area.width / 10,
+            area.height / 20,
+            area.width * 8 / 10,
+            area.height * 9 / 10,
+        );
+
+        let root_name = prompt.root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| prompt.root.to_string_lossy().to_string());
+        let prompt_block = Block::default()
+            .title(format!("  Add by Pattern: {}  ", root_name))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .style(Style::default().bg(Color::Black));
+        let inner = prompt_block.inner(prompt_area);
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(Color::Black)),
+            prompt_area
+        );
+        frame.render_widget(prompt_block, prompt_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Input
+                Constraint::Length(2), // Summary
+                Constraint::Min(1),    // Matches
+                Constraint::Length(1), // Keys
+            ])
+            .split(inner);
+
+        // The input scrolls sideways once the pattern is wider than the box
+        let input_width = chunks[0].width.saturating_sub(2) as usize;
+        let input_scroll = prompt.input.visual_scroll(input_width);
+        let input_style = if prompt.error.is_some() {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default().fg(Color::Yellow)
+        };
+        let input = Paragraph::new(prompt.input.value())
+            .style(Style::default().fg(Color::White))
+            .scroll((0, input_scroll as u16))
+            .block(Block::default()
+                .borders(Borders::ALL)
+                .border_style(input_style)
+                .title(format!(" {} ", prompt.kind.name())));
+        frame.render_widget(input, chunks[0]);
+        frame.set_cursor_position((
+            chunks[0].x + 1 + prompt.input.visual_cursor().saturating_sub(input_scroll) as u16,
+            chunks[0].y + 1,
+        ));
+
+        let summary = match &prompt.error {
+            Some(error) => Line::from(Span::styled(error.clone(), Style::default().fg(Color::Red))),
+            None if prompt.input.value().trim().is_empty() => Line::from(Span::styled(
+                "Type a glob like **/*handler*.rs or migrations/2024*, Tab for a regex",
+                Style::default().fg(Color::Gray),
+            )),
+            None => {
+                let collected = prompt.matched().filter(|m| app.is_collected(&m.path)).count();
+                Line::from(vec![
+                    Span::styled(
+                        format!("{} of {} files match", prompt.matches.len(), prompt.candidates.len()),
+                        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(format!(
+                        " · {} · {} already collected",
+                        app.format_size(prompt.matched_size() as usize),
+                        collected
+                    )),
+                ])
+            }
+        };
+        frame.render_widget(Paragraph::new(summary), chunks[1]);
+
+        let matches: Vec<Line> = prompt.matched()
+            .skip(prompt.scroll)
+            .take(chunks[2].height as usize)
+            .map(|candidate| {
+                if app.is_collected(&candidate.path) {
+                    Line::from(Span::styled(format!("[+] {}", candidate.relative), Style::default().fg(Color::Yellow)))
+                } else {
+                    Line::from(Span::styled(format!("    {}", candidate.relative), Style::default().fg(Color::Green)))
+                }
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(matches), chunks[2]);
+
+        let keys = Line::from(vec![
+            Span::styled("Enter", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+            Span::raw(" Add all   "),
+            Span::styled("Ctrl+D", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            Span::raw(" Remove all   "),
+            Span::styled("Tab", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Glob/regex   "),
+            Span::styled("↑/↓ PgUp/PgDn", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::raw(" Scroll   "),
+            Span::styled("ESC", Style::default().fg(Color::Gray).add_modifier(Modifier::BOLD)),
+            Span::raw(" Cancel"),
+        ]);
+        frame.render_widget(Paragraph::new(keys), chunks[3]);
+    }
+
     /// Renders the help overlay
     pub fn render_help_overlay(frame: &mut Frame) {
         let area = frame.area();
@@ -455,15 +1083,33 @@ impl UI {
             ]),
             Line::from("  a        Add current file to collection"),
             Line::from("  A        Add all files in current directory"),
+            Line::from("  W        Add everything below the selected directory (previewed first)"),
+            Line::from("  /        Add or remove all files matching a glob or regex"),
+            Line::from("  o        Collect current file as an outline (signatures only), again for the full file"),
+            Line::from("  R        Pick lines or symbols of the current file to collect"),
             Line::from("  d        Remove current file from collection"),
             Line::from("  D        Clear entire collection"),
             Line::from("  r        Refresh collected files (sync with changes)"),
+            Line::from("  p        Pin/unpin file (pinned files are never cut by the budget)"),
             Line::from(""),
             Line::from(vec![
                 Span::styled("Export Options", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))
             ]),
-            Line::from("  S        Save collection to markdown file"),
+            Line::from("  S        Save collection to file (in the current format)"),
             Line::from("  C        Copy collection to clipboard"),
+            Line::from("  f        Cycle export format (markdown, xml, json, diff, templates)"),
+            Line::from("  T        Include the project tree in collection exports"),
+            Line::from("  M        Include repository metadata (branch, commit, status) in exports"),
+            Line::from("  L        Prefix exported lines with line numbers (for file:line citations)"),
+            Line::from("  I        Show size, lines, language and modified time above each file"),
+            Line::from("  O        Group exported files by directory with a table of contents"),
+            Line::from("  N        Replace the terms in anonymize.toml with placeholders in exports"),
+            Line::from("  U        Restore the original names in anonymized text on the clipboard"),
+            Line::from("  X        Cycle export transforms: off, whitespace only, or compact (comments too)"),
+            Line::from("  P        Cycle the size big exports are split into parts at"),
+            Line::from("  n        Copy the next part of a split export"),
+            Line::from("  b        Cycle the token budget exports are fitted to"),
+            Line::from("  F        Show what fitting to the budget keeps and cuts"),
             Line::from("  t        Save directory tree to file"),
             Line::from("  c        Copy directory tree to clipboard"),
             Line::from(""),
@@ -479,9 +1125,11 @@ impl UI {
             Line::from("  • Files marked with [+] are in your collection"),
             Line::from("  • Breadcrumbs show your location from the start directory"),
             Line::from("  • Navigation keys gray out when actions aren't available"),
-            Line::from("  • Collection size is shown in the header with health indicators"),
-            Line::from("  • Yellow warning at 25MB, red warning at 50MB"),
+            Line::from("  • Collection size and token count are shown in the header with health indicators"),
+            Line::from("  • Yellow warning at 25MB, red warning at 50MB (or --warn-tokens/--critical-tokens)"),
+            Line::from("  • Token counts starting with ~ are estimates (see --tokenizer)"),
             Line::from("  • Refresh (r) updates modified files and removes deleted ones"),
+            Line::from("  • S and C scan for secrets first: b blocks, r redacts, p exports anyway"),
             Line::from(""),
             Line::from(vec![
                 Span::styled("Press '?' or ESC to close this help", 
@@ -1,5 +1,4 @@
 use git2::Repository;
-use ignore::gitignore::Gitignore;
 use ratatui::style::{Color, Modifier, Style};
 use std::collections::HashMap;
 use std::fs;
@@ -8,6 +7,7 @@ use std::sync::LazyLock;
 
 use crate::app::FileItem;
 use crate::app_error::AppError;
+use crate::ignores::IgnoreStack;
 
 pub const MEGABYTE: usize = 1024 * 1024;
 
@@ -205,7 +205,7 @@ pub fn get_file_display_info(item: &FileItem) -> (&str, Style) {
         ("🔗", Color::Magenta)
     } else {
         FILE_MAPPINGS
-            .get(item.name.split('.').last().unwrap_or(""))
+            .get(item.name.split('.').next_back().unwrap_or(""))
             .copied()
             .unwrap_or(("📄", Color::Gray))
     };
@@ -249,13 +249,22 @@ pub fn parse_target_dir(path_arg: Option<String>) -> Result<PathBuf, AppError> {
                 .map_err(|e| AppError::InvalidPath(format!("Cannot canonicalize path: {}", e)))
         },
         
-        None => std::env::current_dir().map_err(|e| AppError::Io(e)),
+        None => std::env::current_dir().map_err(AppError::Io),
 
     }
 }
 
-/// Get git root and gitignore if they exist
-pub fn find_repo(path: &Path) -> Result<(Option<PathBuf>, Option<Gitignore>), AppError> {
+/// A git repository found around a directory
+pub struct FoundRepo {
+    pub root: PathBuf,
+    pub ignores: IgnoreStack,
+    /// Ignore files that couldn't be parsed and were skipped, left for the
+    /// caller to show since the TUI may own the screen
+    pub warnings: Vec<String>,
+}
+
+/// Get git root and its ignore rules if we're in a repo
+pub fn find_repo(path: &Path) -> Result<Option<FoundRepo>, AppError> {
     match Repository::discover(path) {
         Ok(repo) => {
             let root = repo.workdir()
@@ -263,22 +272,50 @@ pub fn find_repo(path: &Path) -> Result<(Option<PathBuf>, Option<Gitignore>), Ap
                 .map(|p| p.to_path_buf())
                 .ok_or(AppError::GitRepoNoParent)?;
             
-            // Build gitignore, but don't fail if the file doesn't exist
-            let gitignore_path = root.join(".gitignore");
-            let gitignore = if gitignore_path.exists() {
-                let (ignore, err) = Gitignore::new(&gitignore_path);
-                if let Some(err) = err {
-                    eprintln!("Warning: Error parsing .gitignore: {}", err);
-                }
-                Some(ignore)
-            } else {
-                None
-            };
+            // Load the ignore rules, but don't fail over a broken ignore file
+            let (ignores, warnings) = IgnoreStack::new(root.clone(), repo.commondir());
             
-            Ok((Some(root), gitignore))
+            Ok(Some(FoundRepo { root, ignores, warnings }))
         },
-        Err(_) => Ok((None, None)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Format a count with thousands separators, e.g. `1,204`
+pub fn format_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            output.push(',');
+        }
+        output.push(c);
     }
+
+    output
+}
+
+/// Format unix seconds as a UTC timestamp, e.g. `2025-06-01 14:03:22 UTC`
+pub fn format_utc_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil-from-days (Howard Hinnant's algorithm), avoids pulling in a date crate
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year, month, day, rem / 3_600, rem % 3_600 / 60, rem % 60
+    )
 }
 
 /*
@@ -422,21 +459,25 @@ static TEXT_FILE_MAPPINGS: LazyLock<HashMap<&'static str, &'static str>> = LazyL
     map
 });
 
+/// Whether a language name is one `get_file_type` can return, e.g. `rust`
+pub fn is_known_language(name: &str) -> bool {
+    TEXT_FILE_MAPPINGS.values().any(|lang| *lang == name)
+}
 
 pub fn get_file_type(path: &Path) -> Option<&'static str> {
 
     // Check if the filename is in our whitelist and return the corresponding md lang code
-    if let Some(filename) = path.file_name().and_then(|f| f.to_str()) {
-        if let Some(lang) = TEXT_FILE_MAPPINGS.get(filename.to_lowercase().as_str()) {
-            return Some(lang);
-        }
+    if let Some(filename) = path.file_name().and_then(|f| f.to_str())
+        && let Some(lang) = TEXT_FILE_MAPPINGS.get(filename.to_lowercase().as_str())
+    {
+        return Some(lang);
     }
     
     // Do the same for extensions
-    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
-        if let Some(lang) = TEXT_FILE_MAPPINGS.get(extension.to_lowercase().as_str()) {
-            return Some(lang);
-        }
+    if let Some(extension) = path.extension().and_then(|e| e.to_str())
+        && let Some(lang) = TEXT_FILE_MAPPINGS.get(extension.to_lowercase().as_str())
+    {
+        return Some(lang);
     }
 
     